@@ -0,0 +1,42 @@
+//! Helpers shared by the benchmarks.
+
+use ark_std::rand::{rngs::StdRng, SeedableRng};
+use house_snark::{
+    CircuitField, NonUniversalProvingSystem, RawKeys, Relation, SomeProvingSystem,
+    UniversalProvingSystem,
+};
+
+pub const SYSTEMS: [SomeProvingSystem; 4] = [
+    SomeProvingSystem::NonUniversal(NonUniversalProvingSystem::Groth16),
+    SomeProvingSystem::NonUniversal(NonUniversalProvingSystem::Gm17),
+    SomeProvingSystem::Universal(UniversalProvingSystem::Marlin),
+    SomeProvingSystem::Universal(UniversalProvingSystem::Plonk),
+];
+
+pub fn rng() -> StdRng {
+    StdRng::seed_from_u64(0)
+}
+
+/// Generates keys for `relation`. For universal systems, this includes generating the smallest SRS
+/// that fits the relation.
+pub fn setup(system: SomeProvingSystem, relation: &Relation) -> RawKeys {
+    match system {
+        SomeProvingSystem::NonUniversal(system) => {
+            system.generate_keys::<CircuitField, _, _>(relation.clone(), &mut rng())
+        }
+        SomeProvingSystem::Universal(system) => {
+            let params = system.minimal_srs_parameters::<CircuitField, _>(relation.clone());
+            let srs = system.generate_srs::<CircuitField, _>(
+                params.num_constraints,
+                params.num_variables,
+                params.degree,
+                &mut rng(),
+            );
+            system.generate_keys::<CircuitField, _>(relation.clone(), srs)
+        }
+    }
+}
+
+pub fn prove(system: SomeProvingSystem, relation: &Relation, keys: &RawKeys) -> Vec<u8> {
+    system.prove::<CircuitField, _, _>(relation.clone(), keys.pk.clone(), &mut rng())
+}
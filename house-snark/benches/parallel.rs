@@ -0,0 +1,88 @@
+//! Compares single-threaded and parallel setup and proving times of every relation and system.
+//!
+//! Both variants use the same build (with the `parallel` feature). The single-threaded one runs in
+//! a rayon thread pool with a single thread, the parallel one in a pool with a thread per core.
+//!
+//! ```shell
+//! > cargo bench --bench parallel
+//! ```
+
+use std::time::{Duration, Instant};
+
+use common::{prove, setup, SYSTEMS};
+use house_snark::{Relation, SomeProvingSystem};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+mod common;
+
+/// Every measurement is the minimum over this many runs.
+const RUNS: usize = 3;
+
+/// Shortest time of running `f` in `pool`.
+fn measure<T>(pool: &ThreadPool, f: impl Fn() -> T + Sync) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            pool.install(|| {
+                let start = Instant::now();
+                f();
+                start.elapsed()
+            })
+        })
+        .min()
+        .expect("At least one run")
+}
+
+fn report(
+    relation: &Relation,
+    system: SomeProvingSystem,
+    phase: &str,
+    times: (Duration, Duration),
+) {
+    let (single, parallel) = times;
+    println!(
+        "{:<16} {:<8} {:<6} {:>12.2?} {:>12.2?} {:>7.2}x",
+        relation.id(),
+        system.id(),
+        phase,
+        single,
+        parallel,
+        single.as_secs_f64() / parallel.as_secs_f64()
+    );
+}
+
+fn main() {
+    let single = ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .expect("Failed to build single-threaded pool");
+    let parallel = ThreadPoolBuilder::new()
+        .build()
+        .expect("Failed to build parallel pool");
+
+    println!(
+        "{:<16} {:<8} {:<6} {:>12} {:>12} {:>8}",
+        "relation",
+        "system",
+        "phase",
+        "1 thread",
+        format!("{} threads", parallel.current_num_threads()),
+        "speedup"
+    );
+
+    for relation in Relation::templates() {
+        for system in SYSTEMS {
+            let setup_times = (
+                measure(&single, || setup(system, &relation)),
+                measure(&parallel, || setup(system, &relation)),
+            );
+            report(&relation, system, "setup", setup_times);
+
+            let keys = setup(system, &relation);
+            let prove_times = (
+                measure(&single, || prove(system, &relation, &keys)),
+                measure(&parallel, || prove(system, &relation, &keys)),
+            );
+            report(&relation, system, "prove", prove_times);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Constraint synthesis, key generation, proving and verification of every relation with every
+//! proving system.
+//!
+//! `merkle-tree` and `withdraw` are measured for several tree heights, given as a comma-separated
+//! list in `MERKLE_HEIGHTS`:
+//!
+//! ```shell
+//! > MERKLE_HEIGHTS=4,8,16 cargo bench --bench relations
+//! ```
+
+use std::env;
+
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+use common::{prove, setup, SYSTEMS};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use house_snark::{
+    serialization::serialize, CircuitField, DepositRelationArgs, GetPublicInput,
+    JoinSplitRelationArgs, LinearEqRelation, MerkleTreeRelationArgs, Relation,
+    TransferRelationArgs, WithdrawRelationArgs, Witness, WitnessTemplate, XorRelation,
+};
+
+mod common;
+
+const DEFAULT_MERKLE_HEIGHTS: &str = "2,4,8";
+
+/// Benchmarked relations, each with a parameter (tree height) to tell its instances apart.
+fn relations() -> Vec<(Relation, String)> {
+    let heights = env::var("MERKLE_HEIGHTS")
+        .unwrap_or_else(|_| DEFAULT_MERKLE_HEIGHTS.to_string())
+        .split(',')
+        .map(|height| height.trim().parse::<u8>().expect("Invalid tree height"))
+        .collect::<Vec<_>>();
+
+    let mut relations = vec![
+        (
+            Relation::Xor(Witness(XorRelation::template())),
+            String::from("-"),
+        ),
+        (
+            Relation::LinearEquation(Witness(LinearEqRelation::template())),
+            String::from("-"),
+        ),
+        (
+            Relation::Deposit(Witness(DepositRelationArgs::template())),
+            String::from("-"),
+        ),
+        (
+            Relation::JoinSplit(Witness(JoinSplitRelationArgs::template())),
+            String::from("-"),
+        ),
+        (
+            Relation::Transfer(Witness(TransferRelationArgs::template())),
+            String::from("-"),
+        ),
+    ];
+    for &height in &heights {
+        relations.push((
+            Relation::MerkleTree(Witness(MerkleTreeRelationArgs::template_of_height(height))),
+            format!("height {}", height),
+        ));
+        relations.push((
+            Relation::Withdraw(Witness(WithdrawRelationArgs::template_of_height(height))),
+            format!("height {}", height),
+        ));
+    }
+    relations
+}
+
+fn synthesis(c: &mut Criterion) {
+    let mut group = c.benchmark_group("synthesis");
+    for (relation, parameter) in relations() {
+        group.bench_with_input(
+            BenchmarkId::new(relation.id(), parameter),
+            &relation,
+            |b, relation| {
+                b.iter(|| {
+                    let cs = ConstraintSystem::<CircuitField>::new_ref();
+                    relation.clone().generate_constraints(cs).unwrap();
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn proving_systems(c: &mut Criterion) {
+    for system in SYSTEMS {
+        let mut setup_group = c.benchmark_group(format!("setup/{}", system.id()));
+        setup_group.sample_size(10);
+        for (relation, parameter) in relations() {
+            setup_group.bench_with_input(
+                BenchmarkId::new(relation.id(), &parameter),
+                &relation,
+                |b, relation| b.iter(|| setup(system, relation)),
+            );
+        }
+        setup_group.finish();
+
+        let mut prove_group = c.benchmark_group(format!("prove/{}", system.id()));
+        prove_group.sample_size(10);
+        let mut verification_inputs = vec![];
+        for (relation, parameter) in relations() {
+            let keys = setup(system, &relation);
+            prove_group.bench_with_input(
+                BenchmarkId::new(relation.id(), &parameter),
+                &relation,
+                |b, relation| b.iter(|| prove(system, relation, &keys)),
+            );
+            let proof = prove(system, &relation, &keys);
+            let public_input = serialize(&GetPublicInput::<CircuitField>::public_input(&relation));
+            verification_inputs.push((relation, parameter, keys.vk, proof, public_input));
+        }
+        prove_group.finish();
+
+        let mut verify_group = c.benchmark_group(format!("verify/{}", system.id()));
+        for (relation, parameter, vk, proof, public_input) in verification_inputs {
+            verify_group.bench_function(BenchmarkId::new(relation.id(), parameter), |b| {
+                b.iter(|| {
+                    assert!(system
+                        .verify::<CircuitField>(vk.clone(), proof.clone(), public_input.clone())
+                        .unwrap())
+                })
+            });
+        }
+        verify_group.finish();
+    }
+}
+
+criterion_group!(benches, synthesis, proving_systems);
+criterion_main!(benches);
@@ -0,0 +1,87 @@
+#!/usr/bin/env python3
+"""Standalone reference implementation of the Poseidon sponge used for Shielder notes.
+
+Regenerates the round constants from the Grain LFSR (as `generate_parameters_grain` in the
+reference implementation accompanying the Poseidon paper), checks them against `constants.rs` and
+prints the hashes used as test vectors in `src/relations/shielder/poseidon/mod.rs`.
+
+Usage: python3 scripts/poseidon_reference.py
+"""
+
+import os
+import re
+
+# Modulus of the BLS12-381 scalar field.
+P = 0x73EDA753299D7D483339D80809A1D80553BDA402FFFE5BFEFFFFFFFF00000001
+WIDTH, FULL_ROUNDS, PARTIAL_ROUNDS, FIELD_BITS = 3, 8, 57, 255
+RATE = WIDTH - 1
+
+CONSTANTS_FILE = os.path.join(
+    os.path.dirname(__file__), "..", "src", "relations", "shielder", "poseidon", "constants.rs"
+)
+
+
+def grain_round_constants():
+    # Field: prime (1), S-box: x^alpha (0), then n, t, R_F, R_P and 30 ones.
+    state = []
+    for value, width in [(1, 2), (0, 4), (FIELD_BITS, 12), (WIDTH, 12), (FULL_ROUNDS, 10),
+                         (PARTIAL_ROUNDS, 10)]:
+        state += [int(b) for b in bin(value)[2:].zfill(width)]
+    state += [1] * 30
+
+    def step():
+        new_bit = state[62] ^ state[51] ^ state[38] ^ state[23] ^ state[13] ^ state[0]
+        state.pop(0)
+        state.append(new_bit)
+        return new_bit
+
+    for _ in range(160):
+        step()
+
+    def next_bit():
+        while True:
+            if step() == 1:
+                return step()
+            step()
+
+    def next_element():
+        while True:
+            value = int("".join(str(next_bit()) for _ in range(FIELD_BITS)), 2)
+            if value < P:
+                return value
+
+    return [[next_element() for _ in range(WIDTH)] for _ in range(FULL_ROUNDS + PARTIAL_ROUNDS)]
+
+
+ROUND_CONSTANTS = grain_round_constants()
+MDS_MATRIX = [[pow(i + j + WIDTH, P - 2, P) for j in range(WIDTH)] for i in range(WIDTH)]
+
+
+def permute(state):
+    for round, constants in enumerate(ROUND_CONSTANTS):
+        state = [(x + c) % P for x, c in zip(state, constants)]
+        if round < FULL_ROUNDS // 2 or round >= FULL_ROUNDS // 2 + PARTIAL_ROUNDS:
+            state = [pow(x, 5, P) for x in state]
+        else:
+            state[0] = pow(state[0], 5, P)
+        state = [sum(m * x for m, x in zip(row, state)) % P for row in MDS_MATRIX]
+    return state
+
+
+def poseidon_hash(values):
+    state = [len(values)] + [0] * RATE
+    for i in range(0, len(values), RATE):
+        for j, value in enumerate(values[i:i + RATE]):
+            state[1 + j] = (state[1 + j] + value) % P
+        state = permute(state)
+    return state[1]
+
+
+if __name__ == "__main__":
+    with open(CONSTANTS_FILE) as f:
+        in_rust = [int(x) for x in re.findall(r'"(\d+)"', f.read())]
+    expected = [x for row in ROUND_CONSTANTS + MDS_MATRIX for x in row]
+    assert in_rust == expected, "constants.rs doesn't match the Grain LFSR output"
+
+    print(poseidon_hash([1, 10, 17, 19]))
+    print(poseidon_hash([1, 10]))
@@ -0,0 +1,838 @@
+//! Aggregation of many Groth16 proofs under the same verifying key into a single proof.
+//!
+//! This follows SnarkPack (Gailly, Maller and Nitulescu). Proofs `(A_i, B_i, C_i)` are combined
+//! with powers of a random challenge `r`, so that all of them are correct (with overwhelming
+//! probability) if and only if
+//!
+//! ```text
+//! Π e(A_i, B_i)^{r^i} = e(α, β)^{Σ r^i} · e(Σ r^i S_i, γ) · e(Σ r^i C_i, δ)
+//! ```
+//!
+//! where `S_i` is the combination of the verifying key with the public input of the `i`-th proof.
+//! The prover commits to the vectors `A`, `B` and `C` and shows with an inner pairing product
+//! argument (TIPP for `Π e(A_i, B_i)^{r^i}` and MIPP for `Σ r^i C_i`) that the two aggregated
+//! values are consistent with the commitments. The argument halves the vectors in every round, so
+//! the aggregate proof has `O(log n)` size.
+//!
+//! Commitment keys come from an aggregation SRS: powers of two secrets `a` and `b` in both groups.
+//! Verification needs `O(log n)` operations in the target group and a constant number of pairings
+//! (around 20), while checking `n` proofs separately takes `3n` of them.
+
+use ark_ec::{msm::VariableBaseMSM, AffineCurve, PairingEngine, ProjectiveCurve};
+use ark_ff::{Field, One, PrimeField, UniformRand, Zero};
+use ark_groth16::{Proof, VerifyingKey};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write};
+use ark_std::rand::{CryptoRng, RngCore};
+use blake2::{Blake2s, Digest};
+
+use crate::{environment::CurveField, serialization::serialize};
+
+/// Powers of two secrets `a` and `b` in both groups.
+///
+/// For aggregating `m` proofs (`m` being a power of two), commitment keys are `h^{a^i}`,
+/// `h^{b^i}` (in G2) and `g^{a^{m + i}}`, `g^{b^{m + i}}` (in G1), for `i < m`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AggregationSrs<E: PairingEngine> {
+    /// `g^{a^i}` for `i < 2 * max_proofs`.
+    g_a: Vec<E::G1Affine>,
+    /// `g^{b^i}` for `i < 2 * max_proofs`.
+    g_b: Vec<E::G1Affine>,
+    /// `h^{a^i}` for `i < max_proofs`.
+    h_a: Vec<E::G2Affine>,
+    /// `h^{b^i}` for `i < max_proofs`.
+    h_b: Vec<E::G2Affine>,
+}
+
+fn powers<G: ProjectiveCurve>(generator: G, x: G::ScalarField, count: usize) -> Vec<G::Affine> {
+    let mut powers = Vec::with_capacity(count);
+    let mut current = generator;
+    for _ in 0..count {
+        powers.push(current);
+        current *= x;
+    }
+    G::batch_normalization_into_affine(&powers)
+}
+
+impl<E: PairingEngine> AggregationSrs<E> {
+    /// Generates SRS for aggregating up to `max_proofs` proofs. The secrets are sampled from
+    /// `rng` and discarded.
+    pub fn generate<R: RngCore + CryptoRng>(max_proofs: usize, rng: &mut R) -> Self {
+        let max_proofs = max_proofs.next_power_of_two();
+        let (a, b) = (E::Fr::rand(rng), E::Fr::rand(rng));
+        let (g, h) = (
+            E::G1Projective::prime_subgroup_generator(),
+            E::G2Projective::prime_subgroup_generator(),
+        );
+        AggregationSrs {
+            g_a: powers(g, a, 2 * max_proofs),
+            g_b: powers(g, b, 2 * max_proofs),
+            h_a: powers(h, a, max_proofs),
+            h_b: powers(h, b, max_proofs),
+        }
+    }
+
+    /// Maximal number of proofs that can be aggregated with this SRS.
+    pub fn max_proofs(&self) -> usize {
+        self.h_a.len()
+    }
+}
+
+/// Commitments to the same vectors under the keys derived from `a` and `b`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Commitment<E: PairingEngine>(E::Fqk, E::Fqk);
+
+impl<E: PairingEngine> Commitment<E> {
+    /// Commitment to `left` and `right` under `(v, w)`: `Π e(left_i, v_i) · e(w_i, right_i)`.
+    fn pair(
+        left: &[E::G1Affine],
+        right: &[E::G2Affine],
+        v: (&[E::G2Affine], &[E::G2Affine]),
+        w: (&[E::G1Affine], &[E::G1Affine]),
+    ) -> Self {
+        Commitment(
+            pairing_product::<E>(left, v.0) * pairing_product::<E>(w.0, right),
+            pairing_product::<E>(left, v.1) * pairing_product::<E>(w.1, right),
+        )
+    }
+
+    /// Commitment to `left` under `v`: `Π e(left_i, v_i)`.
+    fn single(left: &[E::G1Affine], v: (&[E::G2Affine], &[E::G2Affine])) -> Self {
+        Commitment(
+            pairing_product::<E>(left, v.0),
+            pairing_product::<E>(left, v.1),
+        )
+    }
+
+    /// Commitment after a folding round with challenge `x`, given the cross commitments.
+    fn fold(&self, left: &Self, right: &Self, x: E::Fr, x_inv: E::Fr) -> Self {
+        Commitment(
+            fold_target::<E>(self.0, left.0, right.0, x, x_inv),
+            fold_target::<E>(self.1, left.1, right.1, x, x_inv),
+        )
+    }
+}
+
+/// `Π e(left_i, right_i)`.
+fn pairing_product<E: PairingEngine>(left: &[E::G1Affine], right: &[E::G2Affine]) -> E::Fqk {
+    let pairs = left
+        .iter()
+        .zip(right)
+        .map(|(&l, &r)| (E::G1Prepared::from(l), E::G2Prepared::from(r)))
+        .collect::<Vec<_>>();
+    E::product_of_pairings(&pairs)
+}
+
+/// `value · left^x · right^{x^{-1}}`, i.e. the folded value in the target group.
+fn fold_target<E: PairingEngine>(
+    value: E::Fqk,
+    left: E::Fqk,
+    right: E::Fqk,
+    x: E::Fr,
+    x_inv: E::Fr,
+) -> E::Fqk {
+    value * left.pow(x.into_repr()) * right.pow(x_inv.into_repr())
+}
+
+/// `left + right * x` for every pair of the halves of `points`.
+fn fold_points<G: AffineCurve>(points: &[G], x: G::ScalarField) -> Vec<G> {
+    let (left, right) = points.split_at(points.len() / 2);
+    let folded = left
+        .iter()
+        .zip(right)
+        .map(|(l, r)| r.mul(x) + l.into_projective())
+        .collect::<Vec<_>>();
+    G::Projective::batch_normalization_into_affine(&folded)
+}
+
+fn sum<G: AffineCurve>(points: &[G]) -> G::Projective {
+    points.iter().map(AffineCurve::into_projective).sum()
+}
+
+/// Messages of a single folding round.
+#[derive(Clone, Debug, PartialEq)]
+struct Round<E: PairingEngine> {
+    com_ab: (Commitment<E>, Commitment<E>),
+    z_ab: (E::Fqk, E::Fqk),
+    com_c: (Commitment<E>, Commitment<E>),
+    z_c: (E::G1Affine, E::G1Affine),
+}
+
+/// Aggregate proof.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AggregateProof<E: PairingEngine> {
+    /// Commitment to `A` and `B`.
+    com_ab: Commitment<E>,
+    /// Commitment to `C`.
+    com_c: Commitment<E>,
+    /// `Π e(A_i, B_i)^{r^i}`.
+    z_ab: E::Fqk,
+    /// `Σ r^i C_i`.
+    z_c: E::G1Affine,
+    rounds: Vec<Round<E>>,
+    /// Vectors and commitment keys folded to single elements.
+    final_a: E::G1Affine,
+    final_b: E::G2Affine,
+    final_c: E::G1Affine,
+    final_v: (E::G2Affine, E::G2Affine),
+    final_w: (E::G1Affine, E::G1Affine),
+    /// KZG openings proving that the final keys were folded correctly.
+    v_openings: (E::G2Affine, E::G2Affine),
+    w_openings: (E::G1Affine, E::G1Affine),
+}
+
+/// Fiat-Shamir transcript.
+struct Transcript(Blake2s);
+
+impl Transcript {
+    fn new() -> Self {
+        let mut hasher = Blake2s::new();
+        hasher.update(b"house-snark groth16 aggregation");
+        Transcript(hasher)
+    }
+
+    fn append<T: CanonicalSerialize>(&mut self, item: &T) {
+        self.0.update(serialize(item));
+    }
+
+    /// Challenge depending on everything appended so far. `None` in the negligible case of zero.
+    fn challenge<F: PrimeField>(&mut self) -> Option<(F, F)> {
+        let digest = self.0.clone().finalize();
+        let challenge = F::from_le_bytes_mod_order(&digest);
+        self.0.update(digest);
+        challenge.inverse().map(|inverse| (challenge, inverse))
+    }
+}
+
+/// `Π_k (1 + factors_k X^{2^{l - 1 - k}})`, where `l` is the number of factors.
+fn folding_polynomial<F: Field>(factors: &[F]) -> Vec<F> {
+    let mut coefficients = vec![F::one()];
+    for factor in factors.iter().rev() {
+        let shifted = coefficients.iter().map(|c| *c * factor).collect::<Vec<_>>();
+        coefficients.extend(shifted);
+    }
+    coefficients
+}
+
+/// Evaluation of `folding_polynomial(factors)` at `z`.
+fn evaluate_folding_polynomial<F: Field>(factors: &[F], z: F) -> F {
+    let mut result = F::one();
+    let mut z_power = z;
+    for factor in factors.iter().rev() {
+        result *= F::one() + *factor * z_power;
+        z_power.square_in_place();
+    }
+    result
+}
+
+/// Coefficients of `(p(X) - p(z)) / (X - z)`.
+fn divide_by_linear<F: Field>(p: &[F], z: F) -> Vec<F> {
+    let mut quotient = vec![F::zero(); p.len().saturating_sub(1)];
+    let mut carry = F::zero();
+    for i in (1..p.len()).rev() {
+        carry = p[i] + carry * z;
+        quotient[i - 1] = carry;
+    }
+    quotient
+}
+
+/// KZG commitment to the quotient of `p` by `X - z` over the `powers` of a secret.
+fn open<G: AffineCurve>(powers: &[G], p: &[G::ScalarField], z: G::ScalarField) -> G {
+    let quotient = divide_by_linear(p, z)
+        .into_iter()
+        .map(|c| c.into_repr())
+        .collect::<Vec<_>>();
+    VariableBaseMSM::multi_scalar_mul(&powers[..quotient.len()], &quotient).into_affine()
+}
+
+/// Factors of the folding polynomials of the keys `v` (rescaled by `r^{-i}`) and `w`.
+fn key_factors<F: Field>(challenges: &[(F, F)], r_inv: F) -> (Vec<F>, Vec<F>) {
+    let rounds = challenges.len();
+    challenges
+        .iter()
+        .enumerate()
+        .map(|(k, (x, x_inv))| {
+            let exponent = 1u64 << (rounds - 1 - k);
+            (*x_inv * r_inv.pow([exponent]), *x)
+        })
+        .unzip()
+}
+
+/// Appends to `transcript` everything the challenge `r` depends on.
+fn begin_transcript<E: PairingEngine>(
+    vk: &VerifyingKey<E>,
+    public_inputs: &[Vec<E::Fr>],
+    com_ab: &Commitment<E>,
+    com_c: &Commitment<E>,
+) -> Transcript {
+    let mut transcript = Transcript::new();
+    transcript.append(vk);
+    transcript.append(&public_inputs.to_vec());
+    transcript.append(com_ab);
+    transcript.append(com_c);
+    transcript
+}
+
+/// Aggregates `proofs` of the respective `public_inputs`, all of them under `vk`.
+pub fn aggregate_proofs<E: PairingEngine>(
+    srs: &AggregationSrs<E>,
+    vk: &VerifyingKey<E>,
+    proofs: &[Proof<E>],
+    public_inputs: &[Vec<E::Fr>],
+) -> AggregateProof<E> {
+    assert!(!proofs.is_empty(), "Nothing to aggregate");
+    assert_eq!(
+        proofs.len(),
+        public_inputs.len(),
+        "Every proof needs its public input"
+    );
+    let m = proofs.len().next_power_of_two();
+    assert!(
+        m <= srs.max_proofs(),
+        "SRS supports aggregating up to {} proofs",
+        srs.max_proofs()
+    );
+
+    // Padding with zeros does not affect any of the aggregated values.
+    let mut a = proofs.iter().map(|p| p.a).collect::<Vec<_>>();
+    let mut b = proofs.iter().map(|p| p.b).collect::<Vec<_>>();
+    let mut c = proofs.iter().map(|p| p.c).collect::<Vec<_>>();
+    a.resize(m, E::G1Affine::zero());
+    b.resize(m, E::G2Affine::zero());
+    c.resize(m, E::G1Affine::zero());
+
+    let (mut v1, mut v2) = (srs.h_a[..m].to_vec(), srs.h_b[..m].to_vec());
+    let (mut w1, mut w2) = (srs.g_a[m..2 * m].to_vec(), srs.g_b[m..2 * m].to_vec());
+
+    let com_ab = Commitment::pair(&a, &b, (&v1, &v2), (&w1, &w2));
+    let com_c = Commitment::single(&c, (&v1, &v2));
+    let mut transcript = begin_transcript(vk, public_inputs, &com_ab, &com_c);
+    let (r, r_inv) = transcript.challenge::<E::Fr>().expect("Zero challenge");
+
+    // Rescale `A` and `C` by `r^i` and `v` by `r^{-i}`, which keeps the commitments unchanged.
+    let (mut r_power, mut r_inv_power) = (E::Fr::one(), E::Fr::one());
+    for i in 0..m {
+        a[i] = a[i].mul(r_power).into_affine();
+        c[i] = c[i].mul(r_power).into_affine();
+        v1[i] = v1[i].mul(r_inv_power).into_affine();
+        v2[i] = v2[i].mul(r_inv_power).into_affine();
+        r_power *= r;
+        r_inv_power *= r_inv;
+    }
+
+    let z_ab = pairing_product::<E>(&a, &b);
+    let z_c = sum(&c).into_affine();
+    transcript.append(&z_ab);
+    transcript.append(&z_c);
+
+    let mut rounds = vec![];
+    let mut challenges = vec![];
+    // `Σ C_i` is multiplied by this scalar (the folded vector of ones) in MIPP.
+    let mut scalar = E::Fr::one();
+    while a.len() > 1 {
+        let half = a.len() / 2;
+        let (a_l, a_r) = a.split_at(half);
+        let (b_l, b_r) = b.split_at(half);
+        let (c_l, c_r) = c.split_at(half);
+        let (v1_l, v1_r) = v1.split_at(half);
+        let (v2_l, v2_r) = v2.split_at(half);
+        let (w1_l, w1_r) = w1.split_at(half);
+        let (w2_l, w2_r) = w2.split_at(half);
+
+        let round = Round {
+            com_ab: (
+                Commitment::pair(a_r, b_l, (v1_l, v2_l), (w1_r, w2_r)),
+                Commitment::pair(a_l, b_r, (v1_r, v2_r), (w1_l, w2_l)),
+            ),
+            z_ab: (
+                pairing_product::<E>(a_r, b_l),
+                pairing_product::<E>(a_l, b_r),
+            ),
+            com_c: (
+                Commitment::single(c_r, (v1_l, v2_l)),
+                Commitment::single(c_l, (v1_r, v2_r)),
+            ),
+            z_c: (
+                sum(c_r).mul(scalar.into_repr()).into_affine(),
+                sum(c_l).mul(scalar.into_repr()).into_affine(),
+            ),
+        };
+        transcript.append(&round);
+        let (x, x_inv) = transcript.challenge::<E::Fr>().expect("Zero challenge");
+
+        a = fold_points(&a, x);
+        b = fold_points(&b, x_inv);
+        c = fold_points(&c, x);
+        v1 = fold_points(&v1, x_inv);
+        v2 = fold_points(&v2, x_inv);
+        w1 = fold_points(&w1, x);
+        w2 = fold_points(&w2, x);
+        scalar *= E::Fr::one() + x_inv;
+
+        rounds.push(round);
+        challenges.push((x, x_inv));
+    }
+
+    let (final_a, final_b, final_c) = (a[0], b[0], c[0]);
+    let (final_v, final_w) = ((v1[0], v2[0]), (w1[0], w2[0]));
+    transcript.append(&(final_a, final_b, final_c));
+    transcript.append(&(final_v.0, final_v.1, final_w.0, final_w.1));
+    let (z, _) = transcript.challenge::<E::Fr>().expect("Zero challenge");
+
+    let (v_factors, w_factors) = key_factors(&challenges, r_inv);
+    let f_v = folding_polynomial(&v_factors);
+    // `w` starts at the `m`-th power, so its polynomial is shifted by `X^m`.
+    let f_w = [vec![E::Fr::zero(); m], folding_polynomial(&w_factors)].concat();
+
+    AggregateProof {
+        com_ab,
+        com_c,
+        z_ab,
+        z_c,
+        rounds,
+        final_a,
+        final_b,
+        final_c,
+        final_v,
+        final_w,
+        v_openings: (open(&srs.h_a, &f_v, z), open(&srs.h_b, &f_v, z)),
+        w_openings: (open(&srs.g_a, &f_w, z), open(&srs.g_b, &f_w, z)),
+    }
+}
+
+/// Checks that `key` commits to a polynomial evaluating to `value` at `z`, given the `opening`.
+///
+/// `key` and `opening` are in G2, `g` and `g_x` are `g` and `g^x`, `x` being the secret.
+fn check_g2_opening<E: PairingEngine>(
+    key: E::G2Affine,
+    opening: E::G2Affine,
+    value: E::Fr,
+    z: E::Fr,
+    (g, g_x): (E::G1Affine, E::G1Affine),
+    h: E::G2Affine,
+) -> bool {
+    // e(g^x - g^z, opening) == e(g, key - h^value)
+    let lhs = (g_x.into_projective() - g.mul(z)).into_affine();
+    let rhs = (key.into_projective() - h.mul(value)).into_affine();
+    E::pairing(lhs, opening) == E::pairing(g, rhs)
+}
+
+/// Like `check_g2_opening`, but for `key` and `opening` in G1.
+fn check_g1_opening<E: PairingEngine>(
+    key: E::G1Affine,
+    opening: E::G1Affine,
+    value: E::Fr,
+    z: E::Fr,
+    g: E::G1Affine,
+    (h, h_x): (E::G2Affine, E::G2Affine),
+) -> bool {
+    // e(opening, h^x - h^z) == e(key - g^value, h)
+    let lhs = (h_x.into_projective() - h.mul(z)).into_affine();
+    let rhs = (key.into_projective() - g.mul(value)).into_affine();
+    E::pairing(opening, lhs) == E::pairing(rhs, h)
+}
+
+/// Verifies `proof` aggregating proofs of `public_inputs` under `vk`.
+pub fn verify_aggregate_proof<E: PairingEngine>(
+    srs: &AggregationSrs<E>,
+    vk: &VerifyingKey<E>,
+    public_inputs: &[Vec<E::Fr>],
+    proof: &AggregateProof<E>,
+) -> bool {
+    let n = public_inputs.len();
+    // The number of rounds comes from the proof, so it may be arbitrarily large.
+    let m = match u32::try_from(proof.rounds.len())
+        .ok()
+        .and_then(|rounds| 1usize.checked_shl(rounds))
+    {
+        Some(m) => m,
+        None => return false,
+    };
+    if n == 0
+        || n.next_power_of_two() != m
+        || m > srs.max_proofs()
+        || public_inputs
+            .iter()
+            .any(|input| input.len() + 1 != vk.gamma_abc_g1.len())
+    {
+        return false;
+    }
+
+    let mut transcript = begin_transcript(vk, public_inputs, &proof.com_ab, &proof.com_c);
+    let (r, r_inv) = match transcript.challenge::<E::Fr>() {
+        Some(challenge) => challenge,
+        None => return false,
+    };
+    transcript.append(&proof.z_ab);
+    transcript.append(&proof.z_c);
+
+    // Groth16 equation for the random combination of the proofs.
+    let mut r_power = E::Fr::one();
+    let mut r_sum = E::Fr::zero();
+    let mut input_scalars = vec![E::Fr::zero(); vk.gamma_abc_g1.len()];
+    for input in public_inputs {
+        r_sum += r_power;
+        input_scalars[0] += r_power;
+        for (scalar, value) in input_scalars[1..].iter_mut().zip(input) {
+            *scalar += r_power * value;
+        }
+        r_power *= r;
+    }
+    let input_scalars = input_scalars
+        .into_iter()
+        .map(|s| s.into_repr())
+        .collect::<Vec<_>>();
+    let s = VariableBaseMSM::multi_scalar_mul(&vk.gamma_abc_g1, &input_scalars).into_affine();
+    let groth16_rhs = E::pairing(vk.alpha_g1, vk.beta_g2).pow(r_sum.into_repr())
+        * pairing_product::<E>(&[s, proof.z_c], &[vk.gamma_g2, vk.delta_g2]);
+    if proof.z_ab != groth16_rhs {
+        return false;
+    }
+
+    // Folding rounds.
+    let (mut com_ab, mut z_ab) = (proof.com_ab, proof.z_ab);
+    let (mut com_c, mut z_c) = (proof.com_c, proof.z_c.into_projective());
+    let mut scalar = E::Fr::one();
+    let mut challenges = vec![];
+    for round in &proof.rounds {
+        transcript.append(round);
+        let (x, x_inv) = match transcript.challenge::<E::Fr>() {
+            Some(challenge) => challenge,
+            None => return false,
+        };
+        com_ab = com_ab.fold(&round.com_ab.0, &round.com_ab.1, x, x_inv);
+        z_ab = fold_target::<E>(z_ab, round.z_ab.0, round.z_ab.1, x, x_inv);
+        com_c = com_c.fold(&round.com_c.0, &round.com_c.1, x, x_inv);
+        z_c += round.z_c.0.mul(x) + round.z_c.1.mul(x_inv);
+        scalar *= E::Fr::one() + x_inv;
+        challenges.push((x, x_inv));
+    }
+
+    // Final values.
+    let (a, b, c) = (proof.final_a, proof.final_b, proof.final_c);
+    let (v, w) = (proof.final_v, proof.final_w);
+    if com_ab != Commitment::pair(&[a], &[b], (&[v.0], &[v.1]), (&[w.0], &[w.1]))
+        || z_ab != E::pairing(a, b)
+        || com_c != Commitment::single(&[c], (&[v.0], &[v.1]))
+        || z_c != c.mul(scalar)
+    {
+        return false;
+    }
+
+    // Final commitment keys.
+    transcript.append(&(a, b, c));
+    transcript.append(&(v.0, v.1, w.0, w.1));
+    let (z, _) = match transcript.challenge::<E::Fr>() {
+        Some(challenge) => challenge,
+        None => return false,
+    };
+    let (v_factors, w_factors) = key_factors(&challenges, r_inv);
+    let v_value = evaluate_folding_polynomial(&v_factors, z);
+    let w_value = z.pow([m as u64]) * evaluate_folding_polynomial(&w_factors, z);
+    let (g, h) = (srs.g_a[0], srs.h_a[0]);
+    check_g2_opening::<E>(v.0, proof.v_openings.0, v_value, z, (g, srs.g_a[1]), h)
+        && check_g2_opening::<E>(v.1, proof.v_openings.1, v_value, z, (g, srs.g_b[1]), h)
+        && check_g1_opening::<E>(w.0, proof.w_openings.0, w_value, z, g, (h, srs.h_a[1]))
+        && check_g1_opening::<E>(w.1, proof.w_openings.1, w_value, z, g, (h, srs.h_b[1]))
+}
+
+/// Generates serialized SRS for aggregating up to `max_proofs` Groth16 proofs over `F`.
+pub fn generate_aggregation_srs<F: CurveField, R: RngCore + CryptoRng>(
+    max_proofs: usize,
+    rng: &mut R,
+) -> Vec<u8> {
+    serialize(&AggregationSrs::<F::Engine>::generate(max_proofs, rng))
+}
+
+/// Aggregates serialized Groth16 `proofs` of the respective `public_inputs` under `vk`.
+pub fn aggregate<F: CurveField>(
+    srs: Vec<u8>,
+    vk: Vec<u8>,
+    proofs: Vec<Vec<u8>>,
+    public_inputs: Vec<Vec<u8>>,
+) -> Vec<u8> {
+    let srs = AggregationSrs::<F::Engine>::deserialize(&*srs).expect("Failed to deserialize SRS");
+    let vk =
+        VerifyingKey::<F::Engine>::deserialize(&*vk).expect("Failed to deserialize verifying key");
+    let proofs = proofs
+        .iter()
+        .map(|proof| {
+            Proof::<F::Engine>::deserialize(&**proof).expect("Failed to deserialize proof")
+        })
+        .collect::<Vec<_>>();
+    let public_inputs = deserialize_public_inputs::<F>(public_inputs);
+    serialize(&aggregate_proofs(&srs, &vk, &proofs, &public_inputs))
+}
+
+/// Verifies serialized aggregate `proof` of proofs of `public_inputs` under `vk`.
+pub fn verify_aggregate<F: CurveField>(
+    srs: Vec<u8>,
+    vk: Vec<u8>,
+    proof: Vec<u8>,
+    public_inputs: Vec<Vec<u8>>,
+) -> bool {
+    let srs = AggregationSrs::<F::Engine>::deserialize(&*srs).expect("Failed to deserialize SRS");
+    let vk =
+        VerifyingKey::<F::Engine>::deserialize(&*vk).expect("Failed to deserialize verifying key");
+    let proof = AggregateProof::<F::Engine>::deserialize(&*proof)
+        .expect("Failed to deserialize aggregate proof");
+    let public_inputs = deserialize_public_inputs::<F>(public_inputs);
+    verify_aggregate_proof(&srs, &vk, &public_inputs, &proof)
+}
+
+fn deserialize_public_inputs<F: CurveField>(public_inputs: Vec<Vec<u8>>) -> Vec<Vec<F>> {
+    public_inputs
+        .iter()
+        .map(|input| Vec::<F>::deserialize(&**input).expect("Failed to deserialize public input"))
+        .collect()
+}
+
+impl<E: PairingEngine> CanonicalSerialize for AggregationSrs<E> {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.g_a.serialize(&mut writer)?;
+        self.g_b.serialize(&mut writer)?;
+        self.h_a.serialize(&mut writer)?;
+        self.h_b.serialize(&mut writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.g_a.serialized_size()
+            + self.g_b.serialized_size()
+            + self.h_a.serialized_size()
+            + self.h_b.serialized_size()
+    }
+}
+
+impl<E: PairingEngine> CanonicalDeserialize for AggregationSrs<E> {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        let srs = AggregationSrs {
+            g_a: Vec::<E::G1Affine>::deserialize(&mut reader)?,
+            g_b: Vec::<E::G1Affine>::deserialize(&mut reader)?,
+            h_a: Vec::<E::G2Affine>::deserialize(&mut reader)?,
+            h_b: Vec::<E::G2Affine>::deserialize(&mut reader)?,
+        };
+        let max_proofs = srs.h_a.len();
+        if !max_proofs.is_power_of_two()
+            || srs.h_b.len() != max_proofs
+            || srs.g_a.len() != 2 * max_proofs
+            || srs.g_b.len() != 2 * max_proofs
+        {
+            return Err(SerializationError::InvalidData);
+        }
+        Ok(srs)
+    }
+}
+
+impl<E: PairingEngine> CanonicalSerialize for Commitment<E> {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.0.serialize(&mut writer)?;
+        self.1.serialize(&mut writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.0.serialized_size() + self.1.serialized_size()
+    }
+}
+
+impl<E: PairingEngine> CanonicalDeserialize for Commitment<E> {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        Ok(Commitment(
+            E::Fqk::deserialize(&mut reader)?,
+            E::Fqk::deserialize(&mut reader)?,
+        ))
+    }
+}
+
+impl<E: PairingEngine> CanonicalSerialize for Round<E> {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.com_ab.serialize(&mut writer)?;
+        self.z_ab.serialize(&mut writer)?;
+        self.com_c.serialize(&mut writer)?;
+        self.z_c.serialize(&mut writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.com_ab.serialized_size()
+            + self.z_ab.serialized_size()
+            + self.com_c.serialized_size()
+            + self.z_c.serialized_size()
+    }
+}
+
+impl<E: PairingEngine> CanonicalDeserialize for Round<E> {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        Ok(Round {
+            com_ab: <(Commitment<E>, Commitment<E>)>::deserialize(&mut reader)?,
+            z_ab: <(E::Fqk, E::Fqk)>::deserialize(&mut reader)?,
+            com_c: <(Commitment<E>, Commitment<E>)>::deserialize(&mut reader)?,
+            z_c: <(E::G1Affine, E::G1Affine)>::deserialize(&mut reader)?,
+        })
+    }
+}
+
+impl<E: PairingEngine> CanonicalSerialize for AggregateProof<E> {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.com_ab.serialize(&mut writer)?;
+        self.com_c.serialize(&mut writer)?;
+        self.z_ab.serialize(&mut writer)?;
+        self.z_c.serialize(&mut writer)?;
+        self.rounds.serialize(&mut writer)?;
+        self.final_a.serialize(&mut writer)?;
+        self.final_b.serialize(&mut writer)?;
+        self.final_c.serialize(&mut writer)?;
+        self.final_v.serialize(&mut writer)?;
+        self.final_w.serialize(&mut writer)?;
+        self.v_openings.serialize(&mut writer)?;
+        self.w_openings.serialize(&mut writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.com_ab.serialized_size()
+            + self.com_c.serialized_size()
+            + self.z_ab.serialized_size()
+            + self.z_c.serialized_size()
+            + self.rounds.serialized_size()
+            + self.final_a.serialized_size()
+            + self.final_b.serialized_size()
+            + self.final_c.serialized_size()
+            + self.final_v.serialized_size()
+            + self.final_w.serialized_size()
+            + self.v_openings.serialized_size()
+            + self.w_openings.serialized_size()
+    }
+}
+
+impl<E: PairingEngine> CanonicalDeserialize for AggregateProof<E> {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        Ok(AggregateProof {
+            com_ab: Commitment::deserialize(&mut reader)?,
+            com_c: Commitment::deserialize(&mut reader)?,
+            z_ab: E::Fqk::deserialize(&mut reader)?,
+            z_c: E::G1Affine::deserialize(&mut reader)?,
+            rounds: Vec::<Round<E>>::deserialize(&mut reader)?,
+            final_a: E::G1Affine::deserialize(&mut reader)?,
+            final_b: E::G2Affine::deserialize(&mut reader)?,
+            final_c: E::G1Affine::deserialize(&mut reader)?,
+            final_v: <(E::G2Affine, E::G2Affine)>::deserialize(&mut reader)?,
+            final_w: <(E::G1Affine, E::G1Affine)>::deserialize(&mut reader)?,
+            v_openings: <(E::G2Affine, E::G2Affine)>::deserialize(&mut reader)?,
+            w_openings: <(E::G1Affine, E::G1Affine)>::deserialize(&mut reader)?,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "xor"))]
+mod tests {
+    use ark_bls12_381::Bls12_381;
+    use ark_groth16::Groth16;
+    use ark_snark::SNARK;
+
+    use super::*;
+    use crate::{
+        environment::{rng, CircuitField},
+        relations::{GetPublicInput, XorRelation},
+    };
+
+    fn relation(public_xoree: u8) -> XorRelation {
+        XorRelation {
+            public_xoree,
+            private_xoree: 3,
+            result: public_xoree ^ 3,
+        }
+    }
+
+    type Proofs = (Vec<Proof<Bls12_381>>, Vec<Vec<CircuitField>>);
+
+    /// SRS for up to 8 proofs, verifying key and `count` proofs of different public inputs.
+    fn setup(count: u8) -> (AggregationSrs<Bls12_381>, VerifyingKey<Bls12_381>, Proofs) {
+        let rng = &mut rng(None);
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(relation(0), rng)
+            .expect("Failed to generate keys");
+        let proofs = (0..count)
+            .map(|i| {
+                let proof = Groth16::<Bls12_381>::prove(&pk, relation(i), rng)
+                    .expect("Failed to generate proof");
+                (proof, relation(i).public_input())
+            })
+            .unzip();
+        (AggregationSrs::generate(8, rng), vk, proofs)
+    }
+
+    #[test]
+    fn aggregate_of_valid_proofs_verifies() {
+        for count in [1, 2, 5, 8] {
+            let (srs, vk, (proofs, inputs)) = setup(count);
+            let proof = aggregate_proofs(&srs, &vk, &proofs, &inputs);
+            assert!(verify_aggregate_proof(&srs, &vk, &inputs, &proof));
+        }
+    }
+
+    #[test]
+    fn aggregate_has_logarithmic_size() {
+        let (srs, vk, (proofs, inputs)) = setup(8);
+        let proof = aggregate_proofs(&srs, &vk, &proofs, &inputs);
+        assert_eq!(proof.rounds.len(), 3);
+    }
+
+    #[test]
+    fn wrong_public_input_is_rejected() {
+        let (srs, vk, (proofs, mut inputs)) = setup(4);
+        let proof = aggregate_proofs(&srs, &vk, &proofs, &inputs);
+        inputs[2][0] += CircuitField::one();
+        assert!(!verify_aggregate_proof(&srs, &vk, &inputs, &proof));
+    }
+
+    #[test]
+    fn missing_proof_is_rejected() {
+        let (srs, vk, (proofs, inputs)) = setup(4);
+        let proof = aggregate_proofs(&srs, &vk, &proofs[..3], &inputs[..3]);
+        assert!(!verify_aggregate_proof(&srs, &vk, &inputs, &proof));
+    }
+
+    #[test]
+    fn invalid_proof_is_rejected() {
+        let (srs, vk, (mut proofs, inputs)) = setup(4);
+        proofs[1].c = proofs[0].c;
+        let proof = aggregate_proofs(&srs, &vk, &proofs, &inputs);
+        assert!(!verify_aggregate_proof(&srs, &vk, &inputs, &proof));
+    }
+
+    #[test]
+    fn tampered_aggregate_is_rejected() {
+        let (srs, vk, (proofs, inputs)) = setup(4);
+        let proof = aggregate_proofs(&srs, &vk, &proofs, &inputs);
+
+        let mut tampered = proof.clone();
+        tampered.rounds[0].z_c.0 = tampered.final_c;
+        assert!(!verify_aggregate_proof(&srs, &vk, &inputs, &tampered));
+
+        let mut tampered = proof;
+        tampered.w_openings.1 = tampered.final_w.0;
+        assert!(!verify_aggregate_proof(&srs, &vk, &inputs, &tampered));
+    }
+
+    #[test]
+    fn too_many_rounds_are_rejected() {
+        let (srs, vk, (proofs, inputs)) = setup(2);
+        let proof = aggregate_proofs(&srs, &vk, &proofs, &inputs);
+
+        for rounds in [4, 63, 64, 65] {
+            let mut tampered = proof.clone();
+            tampered.rounds = vec![proof.rounds[0].clone(); rounds];
+            assert!(!verify_aggregate_proof(&srs, &vk, &inputs, &tampered));
+        }
+    }
+
+    #[test]
+    fn serialized_aggregate_verifies() {
+        let (srs, vk, (proofs, inputs)) = setup(3);
+        let (srs, vk) = (serialize(&srs), serialize(&vk));
+        let inputs = inputs.iter().map(serialize).collect::<Vec<_>>();
+        let proof = aggregate::<CircuitField>(
+            srs.clone(),
+            vk.clone(),
+            proofs.iter().map(serialize).collect(),
+            inputs.clone(),
+        );
+        assert!(verify_aggregate::<CircuitField>(srs, vk, proof, inputs));
+    }
+}
@@ -0,0 +1,267 @@
+//! Proving many witnesses at once.
+//!
+//! Jobs are listed in a JSON manifest, e.g.:
+//!
+//! ```text
+//! {
+//!   "jobs": [
+//!     {
+//!       "relation": "withdraw",
+//!       "witness_file": "withdraw-1.json",
+//!       "proving_key_file": "withdraw.groth16.bls12_381.pk.bytes"
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Jobs sharing the proving system, curve and proving key are proved in parallel, with the key read
+//! and deserialized only once. A failure of a single job does not stop the others.
+
+use std::{
+    collections::HashSet,
+    fmt::{Display, Formatter},
+    fs,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::{
+    config::{parse_seed, parse_some_system},
+    envelope::{pack, ArtifactKind, Origin},
+    environment::{panic_message, rng, Curve, Seed, SomeProvingSystem},
+    relations::{GetPublicInput, Relation},
+    serialization::{env_id, read_proving_key, save_proving_artifacts, serialize},
+};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    jobs: Vec<JobSpec>,
+}
+
+/// Single job, as given in the manifest. Paths are relative to the manifest.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JobSpec {
+    /// Subcommand name of the relation (e.g. `linear-equation`).
+    relation: String,
+    witness_file: PathBuf,
+    proving_key_file: PathBuf,
+    #[serde(default = "default_system")]
+    system: String,
+    #[serde(default = "default_curve")]
+    curve: String,
+    /// Hex-encoded 32-byte seed for the randomness source. Defaults to OS entropy.
+    seed: Option<String>,
+    /// Prefix of the produced artifacts. Defaults to the stem of the witness file.
+    name: Option<String>,
+}
+
+fn default_system() -> String {
+    String::from("groth16")
+}
+
+fn default_curve() -> String {
+    String::from("bls12-381")
+}
+
+impl JobSpec {
+    fn name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| {
+            self.witness_file
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        })
+    }
+
+    fn prepare(self, name: String, manifest_dir: &Path) -> Result<Job, String> {
+        let system = parse_some_system(&self.system).map_err(|e| e.to_string())?;
+        let curve = Curve::from_str(&self.curve, true)?;
+        let seed = match self.seed {
+            Some(seed) => Some(parse_seed(&seed).map_err(|e| e.to_string())?),
+            None => None,
+        };
+        let relation =
+            Relation::from_witness_file(&self.relation, &manifest_dir.join(self.witness_file))?;
+
+        Ok(Job {
+            name,
+            relation,
+            system,
+            curve,
+            proving_key_file: manifest_dir.join(self.proving_key_file),
+            seed,
+        })
+    }
+}
+
+/// Job ready to be proved.
+struct Job {
+    name: String,
+    relation: Relation,
+    system: SomeProvingSystem,
+    curve: Curve,
+    proving_key_file: PathBuf,
+    seed: Option<Seed>,
+}
+
+impl Job {
+    fn shares_key_with(&self, other: &Job) -> bool {
+        self.system == other.system
+            && self.curve == other.curve
+            && self.proving_key_file == other.proving_key_file
+    }
+
+    /// Saves `proof` together with the public input of the job.
+    fn save(&self, proof: Vec<u8>, envelope: bool) {
+        let public_input = over_curve!(self.curve, F => {
+            serialize(&GetPublicInput::<F>::public_input(&self.relation))
+        });
+        let origin =
+            envelope.then(|| Origin::of_relation(self.system.id(), self.curve, &self.relation));
+        let proof = pack(origin.as_ref(), ArtifactKind::Proof, proof);
+        let public_input = pack(origin.as_ref(), ArtifactKind::PublicInput, public_input);
+        save_proving_artifacts(
+            &self.name,
+            &env_id(&self.system.id(), self.curve),
+            &proof,
+            &public_input,
+        );
+    }
+}
+
+/// Result of a single job.
+struct JobOutcome {
+    index: usize,
+    name: String,
+    result: Result<(), String>,
+    duration: Duration,
+}
+
+/// Results of all jobs from a manifest, in the order of the manifest.
+pub struct Summary {
+    outcomes: Vec<JobOutcome>,
+    duration: Duration,
+}
+
+impl Summary {
+    pub fn all_succeeded(&self) -> bool {
+        self.outcomes.iter().all(|outcome| outcome.result.is_ok())
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for outcome in &self.outcomes {
+            match &outcome.result {
+                Ok(()) => writeln!(f, "{}: proved in {:.2?}", outcome.name, outcome.duration)?,
+                Err(e) => writeln!(f, "{}: FAILED: {}", outcome.name, e)?,
+            }
+        }
+        let failed = self
+            .outcomes
+            .iter()
+            .filter(|outcome| outcome.result.is_err())
+            .count();
+        write!(
+            f,
+            "Proved {} of {} jobs in {:.2?} ({} failed)",
+            self.outcomes.len() - failed,
+            self.outcomes.len(),
+            self.duration,
+            failed
+        )
+    }
+}
+
+/// Proves all jobs from `manifest_file`, saving proofs and public inputs to the current directory
+/// (as `<name>.<system>.<curve>.(proof|public_input).bytes`).
+pub fn prove_batch(manifest_file: PathBuf, envelope: bool) -> Summary {
+    let start = Instant::now();
+    let manifest = fs::read_to_string(&manifest_file).expect("Failed to read manifest");
+    let manifest: Manifest = serde_json::from_str(&manifest).expect("Invalid manifest");
+    let manifest_dir = manifest_file.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut outcomes = vec![];
+    let mut groups: Vec<Vec<(usize, Job)>> = vec![];
+    let mut names = HashSet::new();
+    for (index, spec) in manifest.jobs.into_iter().enumerate() {
+        let name = spec.name();
+        let job = if names.insert(name.clone()) {
+            spec.prepare(name.clone(), manifest_dir)
+        } else {
+            Err(String::from("Duplicate job name"))
+        };
+        match job {
+            Ok(job) => match groups
+                .iter_mut()
+                .find(|group| group[0].1.shares_key_with(&job))
+            {
+                Some(group) => group.push((index, job)),
+                None => groups.push(vec![(index, job)]),
+            },
+            Err(e) => outcomes.push(JobOutcome {
+                index,
+                name,
+                result: Err(e),
+                duration: Duration::ZERO,
+            }),
+        }
+    }
+
+    for group in groups {
+        outcomes.extend(prove_group(group, envelope));
+    }
+    outcomes.sort_by_key(|outcome| outcome.index);
+
+    Summary {
+        outcomes,
+        duration: start.elapsed(),
+    }
+}
+
+/// Proves jobs sharing the same proving key.
+fn prove_group(jobs: Vec<(usize, Job)>, envelope: bool) -> Vec<JobOutcome> {
+    let (system, curve) = (jobs[0].1.system, jobs[0].1.curve);
+    let (proving_key_file, relation) = (jobs[0].1.proving_key_file.clone(), &jobs[0].1.relation);
+    let circuits = jobs
+        .iter()
+        .map(|(_, job)| (job.relation.clone(), rng(job.seed)))
+        .collect::<Vec<_>>();
+
+    let proofs = read_proving_key(proving_key_file, &system.id(), curve, relation)
+        .map_err(|e| e.to_string())
+        .and_then(|proving_key| {
+            panic::catch_unwind(AssertUnwindSafe(
+                || over_curve!(curve, F => system.prove_batch::<F, _, _>(circuits, proving_key)),
+            ))
+            .map_err(panic_message)
+        });
+
+    match proofs {
+        Ok(proofs) => jobs
+            .into_iter()
+            .zip(proofs)
+            .map(|((index, job), (proof, duration))| JobOutcome {
+                index,
+                result: proof.map(|proof| job.save(proof, envelope)),
+                name: job.name,
+                duration,
+            })
+            .collect(),
+        Err(e) => jobs
+            .into_iter()
+            .map(|(index, job)| JobOutcome {
+                index,
+                name: job.name,
+                result: Err(e.clone()),
+                duration: Duration::ZERO,
+            })
+            .collect(),
+    }
+}
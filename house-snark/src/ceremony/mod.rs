@@ -0,0 +1,562 @@
+//! Multi-party trusted setup ceremony for Groth16 keys.
+//!
+//! This follows the Bowe-Gabizon-Miers protocol and consists of two phases:
+//!  1. Powers of tau (see `powers_of_tau`): participants re-randomize `tau`, `alpha` and `beta`.
+//!  2. Once `prepare` derives Groth16 parameters for the relation from the final powers (with
+//!     `gamma = delta = 1`), participants multiply `delta` by their own secret scalars (dividing
+//!     `h_query` and `l_query` by it accordingly).
+//!
+//! Every contribution comes with proofs of knowledge of the secret scalars, bound to the transcript
+//! so far. As long as at least one participant of each phase discards their scalars, nobody knows
+//! the trapdoors of the final keys. `verify_transcript` replays both phases: it checks the powers
+//! against the first-phase contributions, re-derives the initial parameters from the powers and
+//! checks the current parameters against the second-phase contributions.
+
+mod powers_of_tau;
+
+use anyhow::{anyhow, ensure, Result};
+use ark_bls12_381::{Bls12_381, G1Affine, G1Projective, G2Affine};
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ff::{Field, UniformRand, Zero};
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
+use ark_relations::r1cs::{
+    ConstraintMatrices, ConstraintSynthesizer, ConstraintSystem, OptimizationGoal, SynthesisMode,
+};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write};
+use ark_std::rand::{CryptoRng, RngCore};
+use blake2::{Blake2s, Digest};
+use powers_of_tau::{PowersContribution, PowersOfTau};
+
+use crate::{
+    environment::{CircuitField, RawKeys},
+    pairing_checks::{random_combinations, same_ratio, KnowledgeProof},
+    serialization::serialize,
+};
+
+type Parameters = ark_groth16::ProvingKey<Bls12_381>;
+
+/// Public part of a single contribution to `delta`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Contribution {
+    /// `delta_g1` after applying this contribution.
+    delta_after: G1Affine,
+    /// Proof of knowledge of the secret scalar, bound to the transcript so far.
+    proof: KnowledgeProof,
+}
+
+/// Second phase of the ceremony: initial and current Groth16 parameters with contributions to
+/// `delta`.
+#[derive(Clone, Debug, PartialEq)]
+struct ParametersPhase {
+    initial: Parameters,
+    current: Parameters,
+    contributions: Vec<Contribution>,
+}
+
+/// State of the ceremony: powers of tau and (once prepared) Groth16 parameters, together with the
+/// transcript.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ceremony {
+    relation_id: String,
+    powers: PowersOfTau,
+    powers_contributions: Vec<PowersContribution>,
+    parameters: Option<ParametersPhase>,
+}
+
+impl Ceremony {
+    /// Starts a ceremony for `circuit` with `tau = alpha = beta = 1`.
+    pub fn init<C: ConstraintSynthesizer<CircuitField>>(
+        relation_id: String,
+        circuit: C,
+    ) -> Result<Self> {
+        let matrices = constraint_matrices(circuit)?;
+        Ok(Ceremony {
+            relation_id,
+            powers: PowersOfTau::new(domain_size(&matrices)?),
+            powers_contributions: vec![],
+            parameters: None,
+        })
+    }
+
+    pub fn relation_id(&self) -> &str {
+        &self.relation_id
+    }
+
+    /// Number of contributions in both phases.
+    pub fn num_contributions(&self) -> usize {
+        self.powers_contributions.len()
+            + self
+                .parameters
+                .as_ref()
+                .map_or(0, |phase| phase.contributions.len())
+    }
+
+    /// Applies fresh secret scalars to the powers of tau or, once the ceremony is prepared, to
+    /// `delta` and appends a transcript entry. Returns the transcript hash after the contribution,
+    /// so that the participant can publish it.
+    pub fn contribute<R: RngCore + CryptoRng>(&mut self, rng: &mut R) -> [u8; 32] {
+        let context_hash = self.transcript_hash();
+        match &mut self.parameters {
+            None => {
+                let contribution = self.powers.contribute(&context_hash, rng);
+                self.powers_contributions.push(contribution);
+            }
+            Some(phase) => phase.contribute(&context_hash, rng),
+        }
+        self.transcript_hash()
+    }
+
+    /// Closes the powers of tau phase: verifies the powers and derives from them the initial
+    /// Groth16 parameters for `circuit` (of relation `relation_id`), which later contributions
+    /// re-randomize.
+    pub fn prepare<C: ConstraintSynthesizer<CircuitField>>(
+        &mut self,
+        relation_id: &str,
+        circuit: C,
+    ) -> Result<()> {
+        ensure!(
+            self.parameters.is_none(),
+            "Ceremony has already been prepared"
+        );
+        let matrices = self.verify_powers(relation_id, circuit)?;
+        let initial = self.powers.groth16_parameters(&matrices)?;
+        self.parameters = Some(ParametersPhase {
+            current: initial.clone(),
+            initial,
+            contributions: vec![],
+        });
+        Ok(())
+    }
+
+    /// Checks that the ceremony has been started for `circuit` (of relation `relation_id`), that
+    /// the powers of tau are the result of applying all the first-phase contributions, that the
+    /// initial parameters are derived from the powers and that the current parameters are the
+    /// result of applying all the second-phase contributions to them.
+    pub fn verify_transcript<C: ConstraintSynthesizer<CircuitField>>(
+        &self,
+        relation_id: &str,
+        circuit: C,
+    ) -> Result<()> {
+        let matrices = self.verify_powers(relation_id, circuit)?;
+        if let Some(phase) = &self.parameters {
+            ensure!(
+                phase.initial == self.powers.groth16_parameters(&matrices)?,
+                "Initial parameters are not derived from the powers of tau"
+            );
+            phase.verify(self.powers_hasher())?;
+        }
+        Ok(())
+    }
+
+    /// Returns the final keys, serialized in the same way as
+    /// `NonUniversalProvingSystem::generate_keys` does.
+    pub fn export_keys(&self) -> Result<RawKeys> {
+        let phase = self
+            .parameters
+            .as_ref()
+            .ok_or_else(|| anyhow!("Ceremony has not been prepared yet"))?;
+        Ok(RawKeys {
+            pk: serialize(&phase.current),
+            vk: serialize(&phase.current.vk),
+        })
+    }
+
+    /// Hash of the whole transcript so far.
+    pub fn transcript_hash(&self) -> [u8; 32] {
+        let mut hasher = self.powers_hasher();
+        if let Some(phase) = &self.parameters {
+            hasher.update(serialize(&phase.initial));
+            for contribution in &phase.contributions {
+                hasher.update(serialize(contribution));
+            }
+        }
+        hasher.finalize().into()
+    }
+
+    /// Checks the relation and the first phase, returning the constraint matrices of `circuit`.
+    fn verify_powers<C: ConstraintSynthesizer<CircuitField>>(
+        &self,
+        relation_id: &str,
+        circuit: C,
+    ) -> Result<ConstraintMatrices<CircuitField>> {
+        ensure!(
+            self.relation_id == relation_id,
+            "Ceremony has been started for {}, not for {}",
+            self.relation_id,
+            relation_id
+        );
+        let matrices = constraint_matrices(circuit)?;
+        ensure!(
+            self.powers.domain_size() == domain_size(&matrices)?,
+            "Powers of tau do not match the number of relation constraints"
+        );
+
+        let mut hasher = self.initial_hasher();
+        let mut context_hashes = vec![];
+        for contribution in &self.powers_contributions {
+            context_hashes.push(hasher.clone().finalize().into());
+            hasher.update(serialize(contribution));
+        }
+        self.powers
+            .verify(&self.powers_contributions, &context_hashes)?;
+        Ok(matrices)
+    }
+
+    /// Hasher fed with everything up to the end of the first phase.
+    fn powers_hasher(&self) -> Blake2s {
+        let mut hasher = self.initial_hasher();
+        for contribution in &self.powers_contributions {
+            hasher.update(serialize(contribution));
+        }
+        hasher
+    }
+
+    fn initial_hasher(&self) -> Blake2s {
+        let mut hasher = Blake2s::new();
+        hasher.update(self.relation_id.as_bytes());
+        hasher.update((self.powers.domain_size() as u64).to_le_bytes());
+        hasher
+    }
+}
+
+impl ParametersPhase {
+    fn contribute<R: RngCore + CryptoRng>(&mut self, context_hash: &[u8; 32], rng: &mut R) {
+        let delta = CircuitField::rand(rng);
+        let delta_inverse = delta.inverse().expect("Sampled zero scalar");
+
+        let proof = KnowledgeProof::prove(delta, context_hash, rng);
+
+        let params = &mut self.current;
+        params.delta_g1 = params.delta_g1.mul(delta).into_affine();
+        params.vk.delta_g2 = params.vk.delta_g2.mul(delta).into_affine();
+        params.h_query = scale(&params.h_query, delta_inverse);
+        params.l_query = scale(&params.l_query, delta_inverse);
+
+        self.contributions.push(Contribution {
+            delta_after: params.delta_g1,
+            proof,
+        });
+    }
+
+    /// Checks that the current parameters are the result of applying all the contributions to the
+    /// initial ones. `hasher` must be fed with the transcript of the first phase.
+    fn verify(&self, mut hasher: Blake2s) -> Result<()> {
+        let (initial, current) = (&self.initial, &self.current);
+        ensure!(
+            initial.delta_g1 == G1Affine::prime_subgroup_generator()
+                && initial.vk.delta_g2 == G2Affine::prime_subgroup_generator(),
+            "Initial parameters must start with delta = 1"
+        );
+
+        hasher.update(serialize(initial));
+        let mut delta_before = initial.delta_g1;
+        for (i, contribution) in self.contributions.iter().enumerate() {
+            ensure!(
+                !contribution.delta_after.is_zero(),
+                "Contribution {} is degenerate",
+                i
+            );
+            let (r, r_delta) = contribution
+                .proof
+                .verify(&hasher.clone().finalize().into())
+                .ok_or_else(|| anyhow!("Contribution {} has invalid proof of knowledge", i))?;
+            ensure!(
+                same_ratio((delta_before, contribution.delta_after), (r, r_delta)),
+                "Contribution {} does not match its proof of knowledge",
+                i
+            );
+            hasher.update(serialize(contribution));
+            delta_before = contribution.delta_after;
+        }
+
+        ensure!(
+            current.delta_g1 == delta_before,
+            "Current delta does not match the transcript"
+        );
+        ensure!(
+            same_ratio(
+                (G1Affine::prime_subgroup_generator(), current.delta_g1),
+                (G2Affine::prime_subgroup_generator(), current.vk.delta_g2)
+            ),
+            "Current delta is inconsistent between G1 and G2"
+        );
+
+        let mut untouched = current.clone();
+        untouched.delta_g1 = initial.delta_g1;
+        untouched.vk.delta_g2 = initial.vk.delta_g2;
+        untouched.h_query = initial.h_query.clone();
+        untouched.l_query = initial.l_query.clone();
+        ensure!(
+            &untouched == initial,
+            "Parameters other than delta have been modified"
+        );
+
+        ensure!(
+            current.h_query.len() == initial.h_query.len()
+                && current.l_query.len() == initial.l_query.len(),
+            "Query lengths have been modified"
+        );
+        let initial_queries = [initial.h_query.as_slice(), &initial.l_query].concat();
+        let current_queries = [current.h_query.as_slice(), &current.l_query].concat();
+        let (initial_combination, current_combination) =
+            random_combinations(&initial_queries, &current_queries);
+        ensure!(
+            same_ratio(
+                (current_combination, initial_combination),
+                (initial.vk.delta_g2, current.vk.delta_g2)
+            ),
+            "Queries do not match the current delta"
+        );
+
+        Ok(())
+    }
+}
+
+impl CanonicalSerialize for Contribution {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.delta_after.serialize(&mut writer)?;
+        self.proof.serialize(&mut writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.delta_after.serialized_size() + self.proof.serialized_size()
+    }
+}
+
+impl CanonicalDeserialize for Contribution {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        Ok(Contribution {
+            delta_after: G1Affine::deserialize(&mut reader)?,
+            proof: KnowledgeProof::deserialize(&mut reader)?,
+        })
+    }
+}
+
+impl CanonicalSerialize for ParametersPhase {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.initial.serialize(&mut writer)?;
+        self.current.serialize(&mut writer)?;
+        self.contributions.serialize(&mut writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.initial.serialized_size()
+            + self.current.serialized_size()
+            + self.contributions.serialized_size()
+    }
+}
+
+impl CanonicalDeserialize for ParametersPhase {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        Ok(ParametersPhase {
+            initial: Parameters::deserialize(&mut reader)?,
+            current: Parameters::deserialize(&mut reader)?,
+            contributions: Vec::<Contribution>::deserialize(&mut reader)?,
+        })
+    }
+}
+
+impl CanonicalSerialize for Ceremony {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.relation_id.serialize(&mut writer)?;
+        self.powers.serialize(&mut writer)?;
+        self.powers_contributions.serialize(&mut writer)?;
+        self.parameters.serialize(&mut writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.relation_id.serialized_size()
+            + self.powers.serialized_size()
+            + self.powers_contributions.serialized_size()
+            + self.parameters.serialized_size()
+    }
+}
+
+impl CanonicalDeserialize for Ceremony {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        Ok(Ceremony {
+            relation_id: String::deserialize(&mut reader)?,
+            powers: PowersOfTau::deserialize(&mut reader)?,
+            powers_contributions: Vec::<PowersContribution>::deserialize(&mut reader)?,
+            parameters: Option::<ParametersPhase>::deserialize(&mut reader)?,
+        })
+    }
+}
+
+/// Synthesizes `circuit` exactly as `ark_groth16::generate_parameters` does.
+fn constraint_matrices<C: ConstraintSynthesizer<CircuitField>>(
+    circuit: C,
+) -> Result<ConstraintMatrices<CircuitField>> {
+    let cs = ConstraintSystem::<CircuitField>::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    cs.set_mode(SynthesisMode::Setup);
+    circuit
+        .generate_constraints(cs.clone())
+        .map_err(|e| anyhow!("Failed to synthesize relation: {}", e))?;
+    cs.finalize();
+    cs.to_matrices()
+        .ok_or_else(|| anyhow!("Failed to build constraint matrices"))
+}
+
+/// Size of the evaluation domain the powers of tau are needed for (at least 2, so that there is
+/// some tau to re-randomize).
+fn domain_size(matrices: &ConstraintMatrices<CircuitField>) -> Result<usize> {
+    let domain = GeneralEvaluationDomain::<CircuitField>::new(
+        matrices.num_constraints + matrices.num_instance_variables,
+    )
+    .ok_or_else(|| anyhow!("Relation is too large"))?;
+    Ok(domain.size().max(2))
+}
+
+/// Multiplies every point by `scalar`.
+fn scale(points: &[G1Affine], scalar: CircuitField) -> Vec<G1Affine> {
+    let scaled = points
+        .iter()
+        .map(|point| point.mul(scalar))
+        .collect::<Vec<_>>();
+    G1Projective::batch_normalization_into_affine(&scaled)
+}
+
+#[cfg(all(test, feature = "xor"))]
+mod tests {
+    use super::*;
+    use crate::{
+        environment::{rng, NonUniversalProvingSystem, SomeProvingSystem},
+        relations::{GetPublicInput, XorRelation},
+    };
+
+    fn relation() -> XorRelation {
+        XorRelation {
+            public_xoree: 2,
+            private_xoree: 3,
+            result: 1,
+        }
+    }
+
+    fn ceremony_with_contributions(powers: usize, deltas: usize) -> Ceremony {
+        let mut ceremony = Ceremony::init(String::from("xor"), relation()).unwrap();
+        for _ in 0..powers {
+            ceremony.contribute(&mut rng(None));
+        }
+        ceremony.prepare("xor", relation()).unwrap();
+        for _ in 0..deltas {
+            ceremony.contribute(&mut rng(None));
+        }
+        ceremony
+    }
+
+    fn current_parameters(ceremony: &mut Ceremony) -> &mut Parameters {
+        &mut ceremony.parameters.as_mut().unwrap().current
+    }
+
+    #[test]
+    fn honest_transcript_verifies() {
+        let ceremony = ceremony_with_contributions(2, 3);
+        assert_eq!(ceremony.num_contributions(), 5);
+        assert!(ceremony.verify_transcript("xor", relation()).is_ok());
+
+        let bytes = serialize(&ceremony);
+        let deserialized = Ceremony::deserialize(&*bytes).unwrap();
+        assert_eq!(ceremony, deserialized);
+        assert!(deserialized.verify_transcript("xor", relation()).is_ok());
+    }
+
+    #[test]
+    fn unprepared_ceremony_verifies_but_cannot_be_exported() {
+        let mut ceremony = Ceremony::init(String::from("xor"), relation()).unwrap();
+        ceremony.contribute(&mut rng(None));
+        assert!(ceremony.verify_transcript("xor", relation()).is_ok());
+        assert!(ceremony.export_keys().is_err());
+
+        let bytes = serialize(&ceremony);
+        assert_eq!(ceremony, Ceremony::deserialize(&*bytes).unwrap());
+    }
+
+    #[test]
+    fn exported_keys_work_with_groth16() {
+        let keys = ceremony_with_contributions(2, 2).export_keys().unwrap();
+        let system = SomeProvingSystem::NonUniversal(NonUniversalProvingSystem::Groth16);
+
+        let proof = system.prove::<CircuitField, _, _>(relation(), keys.pk, &mut rng(None));
+        let input = serialize::<Vec<CircuitField>>(&relation().public_input());
+        assert!(system
+            .verify::<CircuitField>(keys.vk, proof, input)
+            .unwrap());
+    }
+
+    #[test]
+    fn replacing_delta_is_detected() {
+        let mut ceremony = ceremony_with_contributions(1, 1);
+        let delta = CircuitField::rand(&mut rng(None));
+        let current = current_parameters(&mut ceremony);
+        current.delta_g1 = G1Affine::prime_subgroup_generator()
+            .mul(delta)
+            .into_affine();
+        current.vk.delta_g2 = G2Affine::prime_subgroup_generator()
+            .mul(delta)
+            .into_affine();
+        assert!(ceremony.verify_transcript("xor", relation()).is_err());
+    }
+
+    #[test]
+    fn tampering_with_queries_is_detected() {
+        let mut ceremony = ceremony_with_contributions(1, 1);
+        current_parameters(&mut ceremony).h_query[0] = G1Affine::prime_subgroup_generator();
+        assert!(ceremony.verify_transcript("xor", relation()).is_err());
+    }
+
+    #[test]
+    fn parameters_not_derived_from_powers_are_detected() {
+        let mut ceremony = ceremony_with_contributions(1, 1);
+        let alpha = CircuitField::rand(&mut rng(None));
+        let alpha_g1 = G1Affine::prime_subgroup_generator()
+            .mul(alpha)
+            .into_affine();
+        let phase = ceremony.parameters.as_mut().unwrap();
+        phase.initial.vk.alpha_g1 = alpha_g1;
+        phase.current.vk.alpha_g1 = alpha_g1;
+        assert!(ceremony.verify_transcript("xor", relation()).is_err());
+    }
+
+    #[test]
+    fn replacing_powers_is_detected() {
+        let mut ceremony = ceremony_with_contributions(1, 1);
+        ceremony.powers = ceremony_with_contributions(1, 0).powers;
+        assert!(ceremony.verify_transcript("xor", relation()).is_err());
+    }
+
+    #[test]
+    fn reordering_contributions_is_detected() {
+        let mut ceremony = ceremony_with_contributions(2, 0);
+        ceremony.powers_contributions.swap(0, 1);
+        assert!(ceremony.verify_transcript("xor", relation()).is_err());
+
+        let mut ceremony = ceremony_with_contributions(0, 2);
+        ceremony
+            .parameters
+            .as_mut()
+            .unwrap()
+            .contributions
+            .swap(0, 1);
+        assert!(ceremony.verify_transcript("xor", relation()).is_err());
+    }
+
+    #[test]
+    fn other_relation_id_is_detected() {
+        let ceremony = ceremony_with_contributions(1, 1);
+        assert!(ceremony
+            .verify_transcript("linear_equation", relation())
+            .is_err());
+    }
+
+    #[cfg(feature = "linear")]
+    #[test]
+    fn other_circuit_is_detected() {
+        use crate::relations::{LinearEqRelation, WitnessTemplate};
+
+        let ceremony = ceremony_with_contributions(1, 1);
+        assert!(ceremony
+            .verify_transcript("xor", LinearEqRelation::template())
+            .is_err());
+    }
+}
@@ -0,0 +1,456 @@
+//! First phase of the ceremony: powers of tau.
+//!
+//! The accumulator holds `tau^i * G1` (for `i < 2n - 1`), `tau^i * G2`, `alpha * tau^i * G1` and
+//! `beta * tau^i * G1` (for `i < n`) together with `beta * G2`, where `n` is the size of the
+//! evaluation domain of the relation. It starts with `tau = alpha = beta = 1` and every participant
+//! multiplies all three trapdoors by their own secret scalars, publishing proofs of knowledge of
+//! them. Groth16 parameters (with `gamma = delta = 1`) are then derived from the final powers
+//! without any further secrets.
+
+use std::iter;
+
+use anyhow::{anyhow, ensure, Result};
+use ark_bls12_381::{G1Affine, G1Projective, G2Affine, G2Projective};
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ff::{One, PrimeField, UniformRand, Zero};
+use ark_groth16::VerifyingKey;
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
+use ark_relations::r1cs::ConstraintMatrices;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write};
+use ark_std::rand::{CryptoRng, RngCore};
+
+use super::Parameters;
+use crate::{
+    environment::CircuitField,
+    pairing_checks::{random_combinations, same_ratio, KnowledgeProof},
+};
+
+/// Accumulated powers of tau for an evaluation domain of `n` elements.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PowersOfTau {
+    tau_g1: Vec<G1Affine>,
+    tau_g2: Vec<G2Affine>,
+    alpha_tau_g1: Vec<G1Affine>,
+    beta_tau_g1: Vec<G1Affine>,
+    beta_g2: G2Affine,
+}
+
+/// `tau * G1`, `alpha * G1` and `beta * G1`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Trapdoors {
+    tau: G1Affine,
+    alpha: G1Affine,
+    beta: G1Affine,
+}
+
+/// Public part of a single contribution to the powers of tau.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PowersContribution {
+    /// Trapdoors after applying this contribution.
+    after: Trapdoors,
+    /// Proofs of knowledge of the secret scalars, bound to the transcript so far.
+    tau_proof: KnowledgeProof,
+    alpha_proof: KnowledgeProof,
+    beta_proof: KnowledgeProof,
+}
+
+impl PowersOfTau {
+    /// Powers with `tau = alpha = beta = 1` for a domain of `n >= 2` elements.
+    pub fn new(n: usize) -> Self {
+        assert!(n >= 2, "Domain must have at least two elements");
+        let g1 = G1Affine::prime_subgroup_generator();
+        let g2 = G2Affine::prime_subgroup_generator();
+        PowersOfTau {
+            tau_g1: vec![g1; 2 * n - 1],
+            tau_g2: vec![g2; n],
+            alpha_tau_g1: vec![g1; n],
+            beta_tau_g1: vec![g1; n],
+            beta_g2: g2,
+        }
+    }
+
+    /// Size of the evaluation domain the powers are meant for.
+    pub fn domain_size(&self) -> usize {
+        self.tau_g2.len()
+    }
+
+    /// Multiplies the trapdoors by fresh secret scalars, with proofs of knowledge bound to
+    /// `context_hash`.
+    pub fn contribute<R: RngCore + CryptoRng>(
+        &mut self,
+        context_hash: &[u8; 32],
+        rng: &mut R,
+    ) -> PowersContribution {
+        let tau = CircuitField::rand(rng);
+        let alpha = CircuitField::rand(rng);
+        let beta = CircuitField::rand(rng);
+
+        let tau_proof = KnowledgeProof::prove(tau, context_hash, rng);
+        let alpha_proof = KnowledgeProof::prove(alpha, context_hash, rng);
+        let beta_proof = KnowledgeProof::prove(beta, context_hash, rng);
+
+        let powers = iter::successors(Some(CircuitField::one()), |power| Some(*power * tau))
+            .take(self.tau_g1.len())
+            .collect::<Vec<_>>();
+        self.tau_g1 = scale_by_powers(&self.tau_g1, &powers, CircuitField::one());
+        self.tau_g2 = scale_by_powers(&self.tau_g2, &powers, CircuitField::one());
+        self.alpha_tau_g1 = scale_by_powers(&self.alpha_tau_g1, &powers, alpha);
+        self.beta_tau_g1 = scale_by_powers(&self.beta_tau_g1, &powers, beta);
+        self.beta_g2 = self.beta_g2.mul(beta).into_affine();
+
+        PowersContribution {
+            after: self.trapdoors(),
+            tau_proof,
+            alpha_proof,
+            beta_proof,
+        }
+    }
+
+    /// Checks that the powers are well-formed and that they are the result of applying
+    /// `contributions` (in order) to the initial powers, where the `i`-th contribution is bound to
+    /// `context_hashes[i]`.
+    pub fn verify(
+        &self,
+        contributions: &[PowersContribution],
+        context_hashes: &[[u8; 32]],
+    ) -> Result<()> {
+        ensure!(
+            contributions.len() == context_hashes.len(),
+            "Every contribution must have its context"
+        );
+        let mut before = Trapdoors::initial();
+        for (i, (contribution, context_hash)) in
+            contributions.iter().zip(context_hashes).enumerate()
+        {
+            contribution
+                .verify(&before, context_hash)
+                .map_err(|e| anyhow!("Powers of tau contribution {}: {}", i, e))?;
+            before = contribution.after;
+        }
+        ensure!(
+            self.check_lengths() && self.trapdoors() == before,
+            "Powers of tau do not match the transcript"
+        );
+        self.check_structure()
+    }
+
+    /// Groth16 parameters (with `gamma = delta = 1`) for the relation with constraint `matrices`.
+    ///
+    /// This follows `ark_groth16::generate_parameters`, but evaluates the polynomials in the
+    /// exponent: Lagrange polynomials at tau are obtained from the powers by inverse FFT.
+    pub fn groth16_parameters(
+        &self,
+        matrices: &ConstraintMatrices<CircuitField>,
+    ) -> Result<Parameters> {
+        let num_instance = matrices.num_instance_variables;
+        let num_variables = num_instance + matrices.num_witness_variables;
+        let domain =
+            GeneralEvaluationDomain::<CircuitField>::new(matrices.num_constraints + num_instance)
+                .ok_or_else(|| anyhow!("Relation is too large"))?;
+        let n = domain.size();
+        ensure!(
+            self.check_lengths() && n <= self.domain_size(),
+            "Powers of tau are too short for the relation"
+        );
+
+        let lagrange = |powers: &[G1Affine]| {
+            domain.ifft(
+                &powers[..n]
+                    .iter()
+                    .map(|power| power.into_projective())
+                    .collect::<Vec<_>>(),
+            )
+        };
+        let lagrange_g1 = lagrange(&self.tau_g1);
+        let alpha_lagrange_g1 = lagrange(&self.alpha_tau_g1);
+        let beta_lagrange_g1 = lagrange(&self.beta_tau_g1);
+        let lagrange_g2 = domain.ifft(
+            &self.tau_g2[..n]
+                .iter()
+                .map(|power| power.into_projective())
+                .collect::<Vec<_>>(),
+        );
+
+        let mut a = vec![G1Projective::zero(); num_variables];
+        let mut b_g1 = vec![G1Projective::zero(); num_variables];
+        let mut b_g2 = vec![G2Projective::zero(); num_variables];
+        // `beta * a + alpha * b + c` for every variable.
+        let mut abc = vec![G1Projective::zero(); num_variables];
+
+        // Public inputs are additionally enforced in `A` by the QAP reduction.
+        for (i, (a_i, abc_i)) in a.iter_mut().zip(&mut abc).take(num_instance).enumerate() {
+            *a_i += lagrange_g1[matrices.num_constraints + i];
+            *abc_i += beta_lagrange_g1[matrices.num_constraints + i];
+        }
+        for (j, ((row_a, row_b), row_c)) in matrices
+            .a
+            .iter()
+            .zip(&matrices.b)
+            .zip(&matrices.c)
+            .enumerate()
+        {
+            for (coefficient, index) in row_a {
+                a[*index] += scaled(lagrange_g1[j], coefficient);
+                abc[*index] += scaled(beta_lagrange_g1[j], coefficient);
+            }
+            for (coefficient, index) in row_b {
+                b_g1[*index] += scaled(lagrange_g1[j], coefficient);
+                b_g2[*index] += scaled(lagrange_g2[j], coefficient);
+                abc[*index] += scaled(alpha_lagrange_g1[j], coefficient);
+            }
+            for (coefficient, index) in row_c {
+                abc[*index] += scaled(lagrange_g1[j], coefficient);
+            }
+        }
+
+        // `Z(tau) * tau^i = tau^(n + i) - tau^i`, as `Z(X) = X^n - 1`.
+        let h = (0..n - 1)
+            .map(|i| self.tau_g1[n + i].into_projective() - self.tau_g1[i].into_projective())
+            .collect::<Vec<_>>();
+
+        let g2 = G2Affine::prime_subgroup_generator();
+        Ok(Parameters {
+            vk: VerifyingKey {
+                alpha_g1: self.alpha_tau_g1[0],
+                beta_g2: self.beta_g2,
+                gamma_g2: g2,
+                delta_g2: g2,
+                gamma_abc_g1: G1Projective::batch_normalization_into_affine(&abc[..num_instance]),
+            },
+            beta_g1: self.beta_tau_g1[0],
+            delta_g1: G1Affine::prime_subgroup_generator(),
+            a_query: G1Projective::batch_normalization_into_affine(&a),
+            b_g1_query: G1Projective::batch_normalization_into_affine(&b_g1),
+            b_g2_query: G2Projective::batch_normalization_into_affine(&b_g2),
+            h_query: G1Projective::batch_normalization_into_affine(&h),
+            l_query: G1Projective::batch_normalization_into_affine(&abc[num_instance..]),
+        })
+    }
+
+    fn trapdoors(&self) -> Trapdoors {
+        Trapdoors {
+            tau: self.tau_g1[1],
+            alpha: self.alpha_tau_g1[0],
+            beta: self.beta_tau_g1[0],
+        }
+    }
+
+    fn check_lengths(&self) -> bool {
+        let n = self.domain_size();
+        n >= 2
+            && self.tau_g1.len() == 2 * n - 1
+            && self.alpha_tau_g1.len() == n
+            && self.beta_tau_g1.len() == n
+    }
+
+    /// Checks that every vector consists of consecutive powers of the same tau and that beta is
+    /// the same in G1 and G2.
+    fn check_structure(&self) -> Result<()> {
+        let g1 = G1Affine::prime_subgroup_generator();
+        let g2 = G2Affine::prime_subgroup_generator();
+        ensure!(
+            self.tau_g1[0] == g1 && self.tau_g2[0] == g2,
+            "Powers of tau must start with the generators"
+        );
+
+        let tau_g2 = (g2, self.tau_g2[1]);
+        let consecutive = |powers: &[G1Affine]| {
+            let (lower, higher) = random_combinations(&powers[..powers.len() - 1], &powers[1..]);
+            same_ratio((lower, higher), tau_g2)
+        };
+        ensure!(
+            consecutive(&self.tau_g1)
+                && consecutive(&self.alpha_tau_g1)
+                && consecutive(&self.beta_tau_g1),
+            "Powers of tau in G1 are not consecutive"
+        );
+
+        let n = self.domain_size();
+        let (lower, higher) = random_combinations(&self.tau_g2[..n - 1], &self.tau_g2[1..]);
+        ensure!(
+            same_ratio((g1, self.tau_g1[1]), (lower, higher)),
+            "Powers of tau in G2 are not consecutive"
+        );
+        ensure!(
+            same_ratio((g1, self.beta_tau_g1[0]), (g2, self.beta_g2)),
+            "Beta is inconsistent between G1 and G2"
+        );
+        Ok(())
+    }
+}
+
+impl Trapdoors {
+    fn initial() -> Self {
+        let g1 = G1Affine::prime_subgroup_generator();
+        Trapdoors {
+            tau: g1,
+            alpha: g1,
+            beta: g1,
+        }
+    }
+}
+
+impl PowersContribution {
+    /// Checks that this contribution has been applied on top of `before` by someone knowing the
+    /// secret scalars.
+    fn verify(&self, before: &Trapdoors, context_hash: &[u8; 32]) -> Result<()> {
+        for (name, proof, before, after) in [
+            ("tau", &self.tau_proof, before.tau, self.after.tau),
+            ("alpha", &self.alpha_proof, before.alpha, self.after.alpha),
+            ("beta", &self.beta_proof, before.beta, self.after.beta),
+        ] {
+            ensure!(!after.is_zero(), "{} is degenerate", name);
+            let (r, r_x) = proof
+                .verify(context_hash)
+                .ok_or_else(|| anyhow!("invalid proof of knowledge for {}", name))?;
+            ensure!(
+                same_ratio((before, after), (r, r_x)),
+                "{} does not match its proof of knowledge",
+                name
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Multiplies the `i`-th point by `factor * powers[i]`.
+fn scale_by_powers<G: AffineCurve<ScalarField = CircuitField>>(
+    points: &[G],
+    powers: &[CircuitField],
+    factor: CircuitField,
+) -> Vec<G> {
+    let scaled = points
+        .iter()
+        .zip(powers)
+        .map(|(point, power)| point.mul((factor * power).into_repr()))
+        .collect::<Vec<_>>();
+    G::Projective::batch_normalization_into_affine(&scaled)
+}
+
+fn scaled<G: ProjectiveCurve<ScalarField = CircuitField>>(
+    mut point: G,
+    coefficient: &CircuitField,
+) -> G {
+    point *= *coefficient;
+    point
+}
+
+impl CanonicalSerialize for PowersOfTau {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.tau_g1.serialize(&mut writer)?;
+        self.tau_g2.serialize(&mut writer)?;
+        self.alpha_tau_g1.serialize(&mut writer)?;
+        self.beta_tau_g1.serialize(&mut writer)?;
+        self.beta_g2.serialize(&mut writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.tau_g1.serialized_size()
+            + self.tau_g2.serialized_size()
+            + self.alpha_tau_g1.serialized_size()
+            + self.beta_tau_g1.serialized_size()
+            + self.beta_g2.serialized_size()
+    }
+}
+
+impl CanonicalDeserialize for PowersOfTau {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        Ok(PowersOfTau {
+            tau_g1: Vec::<G1Affine>::deserialize(&mut reader)?,
+            tau_g2: Vec::<G2Affine>::deserialize(&mut reader)?,
+            alpha_tau_g1: Vec::<G1Affine>::deserialize(&mut reader)?,
+            beta_tau_g1: Vec::<G1Affine>::deserialize(&mut reader)?,
+            beta_g2: G2Affine::deserialize(&mut reader)?,
+        })
+    }
+}
+
+impl CanonicalSerialize for PowersContribution {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.after.tau.serialize(&mut writer)?;
+        self.after.alpha.serialize(&mut writer)?;
+        self.after.beta.serialize(&mut writer)?;
+        self.tau_proof.serialize(&mut writer)?;
+        self.alpha_proof.serialize(&mut writer)?;
+        self.beta_proof.serialize(&mut writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.after.tau.serialized_size()
+            + self.after.alpha.serialized_size()
+            + self.after.beta.serialized_size()
+            + self.tau_proof.serialized_size()
+            + self.alpha_proof.serialized_size()
+            + self.beta_proof.serialized_size()
+    }
+}
+
+impl CanonicalDeserialize for PowersContribution {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        Ok(PowersContribution {
+            after: Trapdoors {
+                tau: G1Affine::deserialize(&mut reader)?,
+                alpha: G1Affine::deserialize(&mut reader)?,
+                beta: G1Affine::deserialize(&mut reader)?,
+            },
+            tau_proof: KnowledgeProof::deserialize(&mut reader)?,
+            alpha_proof: KnowledgeProof::deserialize(&mut reader)?,
+            beta_proof: KnowledgeProof::deserialize(&mut reader)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::rng;
+
+    const CONTEXTS: [[u8; 32]; 2] = [[1; 32], [2; 32]];
+
+    fn powers_with_contributions() -> (PowersOfTau, Vec<PowersContribution>) {
+        let mut powers = PowersOfTau::new(4);
+        let contributions = CONTEXTS
+            .iter()
+            .map(|context| powers.contribute(context, &mut rng(None)))
+            .collect();
+        (powers, contributions)
+    }
+
+    #[test]
+    fn honest_powers_verify() {
+        let (powers, contributions) = powers_with_contributions();
+        assert!(powers.verify(&contributions, &CONTEXTS).is_ok());
+        assert!(PowersOfTau::new(4).verify(&[], &[]).is_ok());
+    }
+
+    #[test]
+    fn contributions_are_bound_to_context() {
+        let (powers, contributions) = powers_with_contributions();
+        assert!(powers.verify(&contributions, &[[2; 32], [1; 32]]).is_err());
+    }
+
+    #[test]
+    fn tampering_with_powers_is_detected() {
+        let (powers, contributions) = powers_with_contributions();
+        let g1 = G1Affine::prime_subgroup_generator();
+
+        let mut tampered = powers.clone();
+        tampered.tau_g1[3] = g1;
+        assert!(tampered.verify(&contributions, &CONTEXTS).is_err());
+
+        let mut tampered = powers.clone();
+        tampered.alpha_tau_g1[2] = g1;
+        assert!(tampered.verify(&contributions, &CONTEXTS).is_err());
+
+        let mut tampered = powers;
+        tampered.beta_g2 = G2Affine::prime_subgroup_generator();
+        assert!(tampered.verify(&contributions, &CONTEXTS).is_err());
+    }
+
+    #[test]
+    fn replacing_powers_is_detected() {
+        let (_, contributions) = powers_with_contributions();
+        let (other_powers, _) = powers_with_contributions();
+        assert!(other_powers.verify(&contributions, &CONTEXTS).is_err());
+    }
+}
@@ -4,8 +4,11 @@ use anyhow::{Error, Result};
 use clap::{Args, Parser, Subcommand, ValueEnum};
 
 use crate::{
-    environment::{NonUniversalProvingSystem, SomeProvingSystem, UniversalProvingSystem},
-    relations::Relation,
+    environment::{
+        Curve, NonUniversalProvingSystem, Seed, SomeProvingSystem, UniversalProvingSystem,
+    },
+    export::VerifierFormat,
+    relations::{Relation, WitnessFormat},
 };
 
 #[derive(Clone, Eq, PartialEq, Hash, Debug, Parser)]
@@ -13,6 +16,10 @@ use crate::{
 pub struct Cli {
     #[clap(subcommand)]
     pub command: Command,
+
+    /// Wrap produced artifacts in a self-describing envelope (see `inspect` and `strip`).
+    #[clap(long, global = true)]
+    pub envelope: bool,
 }
 
 #[derive(Clone, Eq, PartialEq, Hash, Debug, Subcommand)]
@@ -25,9 +32,38 @@ pub enum Command {
     GenerateKeys(GenerateKeysCmd),
     /// Generate proof and public input and save them to separate binary files.
     GenerateProof(GenerateProofCmd),
+    /// Generate proofs for all jobs listed in a manifest file, in parallel.
+    ProveBatch(ProveBatchCmd),
+    /// Verify proof against public input using verifying key.
+    Verify(VerifyCmd),
+    /// Generate SRS for aggregating Groth16 proofs and save it to a binary file.
+    GenerateAggregationSrs(GenerateAggregationSrsCmd),
+    /// Aggregate Groth16 proofs under the same verifying key into a single proof.
+    Aggregate(AggregateCmd),
+    /// Verify aggregate proof against public inputs of all aggregated proofs.
+    VerifyAggregate(VerifyAggregateCmd),
+    /// Print the envelope header of an artifact.
+    Inspect(InspectCmd),
+    /// Print the size of a relation and the smallest SRS parameters that fit it.
+    InspectRelation(InspectRelationCmd),
+    /// Print an example witness file for a relation (to be passed with `--witness-file`).
+    WitnessTemplate(WitnessTemplateCmd),
+    /// Remove the envelope from an artifact, leaving the raw bytes expected by the pallet.
+    Strip(StripCmd),
+    /// Generate verifier of Groth16 proofs for another execution environment.
+    ExportVerifier(ExportVerifierCmd),
+    /// Print proof and public input encoded as calldata of the exported Solidity verifier.
+    ExportCalldata(ExportCalldataCmd),
+    /// Update, verify or trim SRS of a universal proving system.
+    #[clap(subcommand)]
+    Srs(SrsCmd),
+    /// Run multi-party trusted setup ceremony for Groth16 keys.
+    #[clap(subcommand)]
+    Ceremony(CeremonyCmd),
     /// Kill all Snarks!
     ///
-    /// Remove all artifacts from the current directory.
+    /// Remove all artifacts (including `public_input.json` files and exported verifiers) from the
+    /// current directory.
     RedWedding,
 }
 
@@ -37,6 +73,10 @@ pub struct GenerateSrsCmd {
     #[clap(long, short, value_enum, default_value = "marlin")]
     pub system: UniversalProvingSystem,
 
+    /// Curve to work over.
+    #[clap(long, value_enum, default_value = "bls12-381")]
+    pub curve: Curve,
+
     /// Maximum supported number of constraints.
     #[clap(long, default_value = "100")]
     pub num_constraints: usize,
@@ -48,6 +88,12 @@ pub struct GenerateSrsCmd {
     /// Maximum supported polynomial degree.
     #[clap(long, default_value = "100")]
     pub degree: usize,
+
+    /// Hex-encoded 32-byte seed for the randomness source. Defaults to OS entropy.
+    ///
+    /// Use only for reproducible test vectors: the seed reveals all the secret randomness.
+    #[clap(long, value_parser = parse_seed)]
+    pub seed: Option<Seed>,
 }
 
 #[derive(Clone, Eq, PartialEq, Hash, Debug, Args)]
@@ -59,6 +105,10 @@ pub struct GenerateKeysFromSrsCmd {
     #[clap(long, short, value_enum, default_value = "marlin")]
     pub system: UniversalProvingSystem,
 
+    /// Curve to work over.
+    #[clap(long, value_enum, default_value = "bls12-381")]
+    pub curve: Curve,
+
     /// Path to a file containing SRS.
     #[clap(long)]
     pub srs_file: PathBuf,
@@ -73,6 +123,16 @@ pub struct GenerateKeysCmd {
     /// Proving system to use.
     #[clap(long, short, value_enum, default_value = "groth16")]
     pub system: NonUniversalProvingSystem,
+
+    /// Curve to work over.
+    #[clap(long, value_enum, default_value = "bls12-381")]
+    pub curve: Curve,
+
+    /// Hex-encoded 32-byte seed for the randomness source. Defaults to OS entropy.
+    ///
+    /// Use only for reproducible test vectors: the seed reveals all the secret randomness.
+    #[clap(long, value_parser = parse_seed)]
+    pub seed: Option<Seed>,
 }
 
 #[derive(Clone, Eq, PartialEq, Hash, Debug, Args)]
@@ -86,12 +146,291 @@ pub struct GenerateProofCmd {
     #[clap(long, short, value_enum, default_value = "groth16", value_parser = parse_some_system)]
     pub system: SomeProvingSystem,
 
+    /// Curve to work over.
+    #[clap(long, value_enum, default_value = "bls12-381")]
+    pub curve: Curve,
+
     /// Path to a file containing proving key.
     #[clap(long, short)]
     pub proving_key_file: PathBuf,
+
+    /// Hex-encoded 32-byte seed for the randomness source. Defaults to OS entropy.
+    ///
+    /// Use only for reproducible test vectors: the seed reveals all the secret randomness.
+    #[clap(long, value_parser = parse_seed)]
+    pub seed: Option<Seed>,
+
+    /// Additionally save public input by names to `<relation-id>.<system>.<curve>.public_input.json`.
+    #[clap(long)]
+    pub public_input_json: bool,
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Args)]
+pub struct ProveBatchCmd {
+    /// Path to a JSON file listing proving jobs.
+    ///
+    /// Every job specifies `relation` (subcommand name), `witness_file` and `proving_key_file`
+    /// (relative to the manifest), and optionally `system`, `curve`, `seed` and `name` (prefix of
+    /// the produced artifacts, by default the stem of the witness file).
+    #[clap(long)]
+    pub manifest: PathBuf,
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Args)]
+pub struct VerifyCmd {
+    /// Proving system to use.
+    ///
+    /// Accepts either `NonUniversalProvingSystem` or `UniversalProvingSystem`.
+    #[clap(long, short, value_enum, default_value = "groth16", value_parser = parse_some_system)]
+    pub system: SomeProvingSystem,
+
+    /// Curve to work over.
+    #[clap(long, value_enum, default_value = "bls12-381")]
+    pub curve: Curve,
+
+    /// Path to a file containing verifying key.
+    #[clap(long)]
+    pub verifying_key_file: PathBuf,
+
+    /// Path to a file containing proof.
+    #[clap(long)]
+    pub proof_file: PathBuf,
+
+    /// Path to a file containing public input.
+    #[clap(long)]
+    pub public_input_file: PathBuf,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Args)]
+pub struct GenerateAggregationSrsCmd {
+    /// Curve to work over.
+    #[clap(long, value_enum, default_value = "bls12-381")]
+    pub curve: Curve,
+
+    /// Maximum number of proofs to aggregate (rounded up to a power of two).
+    #[clap(long, default_value = "64")]
+    pub max_proofs: usize,
+
+    /// Hex-encoded 32-byte seed for the randomness source. Defaults to OS entropy.
+    ///
+    /// Use only for reproducible test vectors: the seed reveals all the secret randomness.
+    #[clap(long, value_parser = parse_seed)]
+    pub seed: Option<Seed>,
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Args)]
+pub struct AggregateCmd {
+    /// Curve to work over.
+    #[clap(long, value_enum, default_value = "bls12-381")]
+    pub curve: Curve,
+
+    /// Path to a file containing aggregation SRS.
+    #[clap(long)]
+    pub srs_file: PathBuf,
+
+    /// Path to a file containing Groth16 verifying key shared by all proofs.
+    #[clap(long)]
+    pub verifying_key_file: PathBuf,
+
+    /// Comma-separated paths to files containing Groth16 proofs.
+    #[clap(long, value_delimiter = ',', required = true)]
+    pub proof_files: Vec<PathBuf>,
+
+    /// Comma-separated paths to files containing public inputs, in the order of the proofs.
+    #[clap(long, value_delimiter = ',', required = true)]
+    pub public_input_files: Vec<PathBuf>,
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Args)]
+pub struct VerifyAggregateCmd {
+    /// Curve to work over.
+    #[clap(long, value_enum, default_value = "bls12-381")]
+    pub curve: Curve,
+
+    /// Path to a file containing aggregation SRS.
+    #[clap(long)]
+    pub srs_file: PathBuf,
+
+    /// Path to a file containing Groth16 verifying key shared by all proofs.
+    #[clap(long)]
+    pub verifying_key_file: PathBuf,
+
+    /// Path to a file containing aggregate proof.
+    #[clap(long)]
+    pub aggregate_proof_file: PathBuf,
+
+    /// Comma-separated paths to files containing public inputs, in the order of the proofs.
+    #[clap(long, value_delimiter = ',', required = true)]
+    pub public_input_files: Vec<PathBuf>,
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Args)]
+pub struct InspectCmd {
+    /// Path to an artifact file.
+    pub artifact_file: PathBuf,
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Args)]
+pub struct InspectRelationCmd {
+    /// Relation to work with.
+    #[clap(subcommand)]
+    pub relation: Relation,
+
+    /// Universal proving system for which SRS parameters should be computed.
+    #[clap(long, short, value_enum, default_value = "marlin")]
+    pub system: UniversalProvingSystem,
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Args)]
+pub struct WitnessTemplateCmd {
+    /// Relation subcommand, e.g. `withdraw`.
+    pub relation: String,
+
+    /// Format of the witness file.
+    #[clap(long, value_enum, default_value = "json")]
+    pub format: WitnessFormat,
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Args)]
+pub struct StripCmd {
+    /// Path to an artifact file.
+    pub artifact_file: PathBuf,
+
+    /// Where to save the raw artifact. Defaults to the artifact path with `.raw.bytes` extension.
+    #[clap(long)]
+    pub output_file: Option<PathBuf>,
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Args)]
+pub struct ExportVerifierCmd {
+    /// Format of the verifier.
+    #[clap(long, value_enum, default_value = "solidity")]
+    pub format: VerifierFormat,
+
+    /// Curve of the verifying key. Defaults to BN254 for `solidity` and BLS12-381 for `ink`.
+    #[clap(long, value_enum)]
+    pub curve: Option<Curve>,
+
+    /// Path to a file containing Groth16 verifying key.
+    #[clap(long)]
+    pub verifying_key_file: PathBuf,
+
+    /// Where to save the verifier. Defaults to the verifying key path with the extension of the
+    /// format (e.g. `.sol`).
+    #[clap(long)]
+    pub output_file: Option<PathBuf>,
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Args)]
+pub struct ExportCalldataCmd {
+    /// Path to a file containing Groth16 proof over BN254.
+    #[clap(long)]
+    pub proof_file: PathBuf,
+
+    /// Path to a file containing public input.
+    #[clap(long)]
+    pub public_input_file: PathBuf,
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Subcommand)]
+pub enum SrsCmd {
+    /// Re-randomize SRS and save it together with a proof of the update to separate binary files.
+    Contribute(SrsContributeCmd),
+    /// Check that SRS has been correctly obtained by updating another one.
+    VerifyUpdate(SrsVerifyUpdateCmd),
+    /// Cut SRS down to the size required by a relation and save it to a binary file.
+    Trim(SrsTrimCmd),
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Args)]
+pub struct SrsContributeCmd {
+    /// Proving system to use.
+    #[clap(long, short, value_enum, default_value = "marlin")]
+    pub system: UniversalProvingSystem,
+
+    /// Path to a file containing SRS to be updated.
+    #[clap(long)]
+    pub srs_file: PathBuf,
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Args)]
+pub struct SrsVerifyUpdateCmd {
+    /// Proving system to use.
+    #[clap(long, short, value_enum, default_value = "marlin")]
+    pub system: UniversalProvingSystem,
+
+    /// Path to a file containing SRS before the update.
+    #[clap(long)]
+    pub old_srs_file: PathBuf,
+
+    /// Path to a file containing SRS after the update.
+    #[clap(long)]
+    pub new_srs_file: PathBuf,
+
+    /// Path to a file containing update proof.
+    #[clap(long)]
+    pub update_proof_file: PathBuf,
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Args)]
+pub struct SrsTrimCmd {
+    /// Relation to work with.
+    #[clap(subcommand)]
+    pub relation: Relation,
+    /// Proving system to use.
+    #[clap(long, short, value_enum, default_value = "marlin")]
+    pub system: UniversalProvingSystem,
+
+    /// Curve to work over.
+    #[clap(long, value_enum, default_value = "bls12-381")]
+    pub curve: Curve,
+
+    /// Path to a file containing SRS.
+    #[clap(long)]
+    pub srs_file: PathBuf,
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Subcommand)]
+pub enum CeremonyCmd {
+    /// Start a new ceremony (powers of tau phase) for a relation and save its state to a binary
+    /// file.
+    Init(CeremonyInitCmd),
+    /// Add fresh randomness to the ceremony and append an entry to its transcript.
+    Contribute(CeremonyFileCmd),
+    /// Close the powers of tau phase and derive the initial Groth16 parameters for the relation.
+    Prepare(CeremonyVerifyCmd),
+    /// Check that the ceremony state matches the relation and is consistent with its transcript.
+    VerifyTranscript(CeremonyVerifyCmd),
+    /// Verify the transcript and save the resulting keys to separate binary files.
+    Export(CeremonyVerifyCmd),
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Args)]
+pub struct CeremonyInitCmd {
+    /// Relation to work with.
+    #[clap(subcommand)]
+    pub relation: Relation,
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Args)]
+pub struct CeremonyFileCmd {
+    /// Path to a file containing ceremony state.
+    #[clap(long)]
+    pub ceremony_file: PathBuf,
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Args)]
+pub struct CeremonyVerifyCmd {
+    /// Path to a file containing ceremony state.
+    #[clap(long)]
+    pub ceremony_file: PathBuf,
+
+    /// Relation the ceremony should have been started for.
+    #[clap(subcommand)]
+    pub relation: Relation,
 }
 
-fn parse_some_system(system: &str) -> Result<SomeProvingSystem> {
+pub fn parse_some_system(system: &str) -> Result<SomeProvingSystem> {
     let maybe_universal =
         UniversalProvingSystem::from_str(system, true).map(SomeProvingSystem::Universal);
     let maybe_non_universal =
@@ -99,6 +438,13 @@ fn parse_some_system(system: &str) -> Result<SomeProvingSystem> {
     maybe_universal.or(maybe_non_universal).map_err(Error::msg)
 }
 
+pub fn parse_seed(seed: &str) -> Result<Seed> {
+    let bytes = hex::decode(seed.trim_start_matches("0x"))?;
+    bytes
+        .try_into()
+        .map_err(|bytes: Vec<u8>| Error::msg(format!("Expected 32 bytes, got {}", bytes.len())))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -108,4 +454,16 @@ mod tests {
         use clap::CommandFactory;
         Cli::command().debug_assert()
     }
+
+    #[test]
+    fn parses_seed() {
+        let seed = [0xab; 32];
+        assert_eq!(parse_seed(&hex::encode(seed)).unwrap(), seed);
+        assert_eq!(
+            parse_seed(&format!("0x{}", hex::encode(seed))).unwrap(),
+            seed
+        );
+        assert!(parse_seed("abab").is_err());
+        assert!(parse_seed("not hex").is_err());
+    }
 }
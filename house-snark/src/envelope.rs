@@ -0,0 +1,401 @@
+//! Optional self-describing envelope for produced artifacts.
+//!
+//! An enveloped artifact consists of `MAGIC`, the format version, a header describing where the
+//! artifact comes from and, finally, the raw artifact bytes (exactly what the pallet expects). Raw
+//! artifacts (without envelope) are still accepted everywhere.
+
+use std::fmt::{Display, Formatter};
+
+use anyhow::{anyhow, ensure, Result};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write};
+use blake2::{Blake2s, Digest};
+
+use crate::{environment::Curve, relations::Relation};
+
+/// Marks enveloped artifacts.
+pub const MAGIC: [u8; 4] = *b"HSNK";
+/// Version of the envelope format.
+pub const FORMAT_VERSION: u8 = 1;
+
+/// Type of the wrapped artifact.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum ArtifactKind {
+    Srs,
+    ProvingKey,
+    VerifyingKey,
+    Proof,
+    PublicInput,
+}
+
+impl ArtifactKind {
+    const ALL: [ArtifactKind; 5] = [
+        ArtifactKind::Srs,
+        ArtifactKind::ProvingKey,
+        ArtifactKind::VerifyingKey,
+        ArtifactKind::Proof,
+        ArtifactKind::PublicInput,
+    ];
+
+    /// Same identifier as in the artifact file names.
+    pub fn id(&self) -> &'static str {
+        match self {
+            ArtifactKind::Srs => "srs",
+            ArtifactKind::ProvingKey => "pk",
+            ArtifactKind::VerifyingKey => "vk",
+            ArtifactKind::Proof => "proof",
+            ArtifactKind::PublicInput => "public_input",
+        }
+    }
+}
+
+/// Where the artifact comes from.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Origin {
+    system_id: String,
+    curve: String,
+    relation_id: String,
+    relation_hash: [u8; 32],
+}
+
+impl Origin {
+    /// Artifact produced for `relation` with proving system `system_id` over `curve`.
+    pub fn of_relation(system_id: String, curve: Curve, relation: &Relation) -> Self {
+        Origin {
+            system_id,
+            curve: curve.id(),
+            relation_id: relation.id(),
+            relation_hash: relation.parameters_hash(),
+        }
+    }
+
+    /// Artifact produced by proving system `system_id` over `curve` independently of any relation.
+    pub fn of_system(system_id: String, curve: Curve) -> Self {
+        Origin {
+            system_id,
+            curve: curve.id(),
+            relation_id: String::new(),
+            relation_hash: [0; 32],
+        }
+    }
+}
+
+/// Header of an enveloped artifact.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Header {
+    pub format_version: u8,
+    pub house_snark_version: String,
+    pub kind: ArtifactKind,
+    pub origin: Origin,
+    /// Blake2s digest of the raw artifact.
+    pub content_digest: [u8; 32],
+}
+
+impl Display for Header {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Format version:      {}", self.format_version)?;
+        writeln!(f, "House-snark version: {}", self.house_snark_version)?;
+        writeln!(f, "Artifact kind:       {}", self.kind.id())?;
+        writeln!(f, "Curve:               {}", self.origin.curve)?;
+        writeln!(f, "Proving system:      {}", self.origin.system_id)?;
+        if !self.origin.relation_id.is_empty() {
+            writeln!(f, "Relation:            {}", self.origin.relation_id)?;
+            writeln!(
+                f,
+                "Relation hash:       0x{}",
+                hex::encode(self.origin.relation_hash)
+            )?;
+        }
+        write!(
+            f,
+            "Content digest:      0x{}",
+            hex::encode(self.content_digest)
+        )
+    }
+}
+
+/// Wraps `payload` in an envelope if `origin` is known. Otherwise, returns `payload` unchanged.
+pub fn pack(origin: Option<&Origin>, kind: ArtifactKind, payload: Vec<u8>) -> Vec<u8> {
+    let origin = match origin {
+        Some(origin) => origin.clone(),
+        None => return payload,
+    };
+    let header = Header {
+        format_version: FORMAT_VERSION,
+        house_snark_version: env!("CARGO_PKG_VERSION").to_string(),
+        kind,
+        origin,
+        content_digest: digest(&payload),
+    };
+
+    let mut bytes = MAGIC.to_vec();
+    header
+        .serialize(&mut bytes)
+        .expect("Failed to serialize envelope header");
+    bytes.extend(payload);
+    bytes
+}
+
+/// Splits `bytes` into the header (if the artifact is enveloped) and the raw artifact.
+///
+/// Fails if the envelope is malformed or the content digest doesn't match.
+pub fn unpack(bytes: &[u8]) -> Result<(Option<Header>, &[u8])> {
+    if !bytes.starts_with(&MAGIC) {
+        return Ok((None, bytes));
+    }
+    let mut reader = &bytes[MAGIC.len()..];
+    let header = Header::deserialize(&mut reader)
+        .map_err(|e| anyhow!("Failed to deserialize envelope header: {}", e))?;
+    ensure!(
+        header.content_digest == digest(reader),
+        "Content digest doesn't match the envelope header"
+    );
+    Ok((Some(header), reader))
+}
+
+/// Returns the raw artifact from `bytes`, checking that the envelope (if present) describes an
+/// artifact of type `kind` produced by proving system `system_id` over `curve`. If the caller knows
+/// the `relation` and the artifact is relation-specific, it is checked as well (including the hash
+/// of relation parameters).
+pub fn open(
+    bytes: &[u8],
+    kind: ArtifactKind,
+    system_id: &str,
+    curve: Curve,
+    relation: Option<&Relation>,
+) -> Result<Vec<u8>> {
+    let (header, payload) = unpack(bytes)?;
+    if let Some(header) = header {
+        ensure!(
+            header.kind == kind,
+            "Expected {}, but the envelope contains {}",
+            kind.id(),
+            header.kind.id()
+        );
+        ensure!(
+            header.origin.system_id == system_id,
+            "Artifact has been produced by {}, not by {}",
+            header.origin.system_id,
+            system_id
+        );
+        ensure!(
+            header.origin.curve == curve.id(),
+            "Artifact has been produced over {}, not over {}",
+            header.origin.curve,
+            curve.id()
+        );
+        if let Some(relation) = relation.filter(|_| !header.origin.relation_id.is_empty()) {
+            ensure!(
+                header.origin.relation_id == relation.id(),
+                "Artifact has been produced for {}, not for {}",
+                header.origin.relation_id,
+                relation.id()
+            );
+            ensure!(
+                header.origin.relation_hash == relation.parameters_hash(),
+                "Artifact has been produced for {} with different parameters",
+                relation.id()
+            );
+        }
+    }
+    Ok(payload.to_vec())
+}
+
+fn digest(bytes: &[u8]) -> [u8; 32] {
+    Blake2s::digest(bytes).into()
+}
+
+impl CanonicalSerialize for ArtifactKind {
+    fn serialize<W: Write>(&self, writer: W) -> Result<(), SerializationError> {
+        let index = Self::ALL.iter().position(|kind| kind == self).unwrap() as u8;
+        index.serialize(writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        0u8.serialized_size()
+    }
+}
+
+impl CanonicalDeserialize for ArtifactKind {
+    fn deserialize<R: Read>(reader: R) -> Result<Self, SerializationError> {
+        let index = u8::deserialize(reader)?;
+        Self::ALL
+            .get(index as usize)
+            .copied()
+            .ok_or(SerializationError::InvalidData)
+    }
+}
+
+impl CanonicalSerialize for Header {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.format_version.serialize(&mut writer)?;
+        self.house_snark_version.serialize(&mut writer)?;
+        self.kind.serialize(&mut writer)?;
+        self.origin.curve.serialize(&mut writer)?;
+        self.origin.system_id.serialize(&mut writer)?;
+        self.origin.relation_id.serialize(&mut writer)?;
+        writer.write_all(&self.origin.relation_hash)?;
+        writer.write_all(&self.content_digest)?;
+        Ok(())
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.format_version.serialized_size()
+            + self.house_snark_version.serialized_size()
+            + self.kind.serialized_size()
+            + self.origin.curve.serialized_size()
+            + self.origin.system_id.serialized_size()
+            + self.origin.relation_id.serialized_size()
+            + 2 * 32
+    }
+}
+
+impl CanonicalDeserialize for Header {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        let format_version = u8::deserialize(&mut reader)?;
+        if format_version != FORMAT_VERSION {
+            return Err(SerializationError::InvalidData);
+        }
+        let house_snark_version = String::deserialize(&mut reader)?;
+        let kind = ArtifactKind::deserialize(&mut reader)?;
+        let curve = String::deserialize(&mut reader)?;
+        let system_id = String::deserialize(&mut reader)?;
+        let relation_id = String::deserialize(&mut reader)?;
+        let mut relation_hash = [0; 32];
+        reader.read_exact(&mut relation_hash)?;
+        let mut content_digest = [0; 32];
+        reader.read_exact(&mut content_digest)?;
+
+        Ok(Header {
+            format_version,
+            house_snark_version,
+            kind,
+            origin: Origin {
+                system_id,
+                curve,
+                relation_id,
+                relation_hash,
+            },
+            content_digest,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "xor"))]
+mod tests {
+    use super::*;
+    use crate::relations::{Witness, XorRelation};
+
+    fn relation() -> Relation {
+        Relation::Xor(Witness(XorRelation {
+            public_xoree: 2,
+            private_xoree: 3,
+            result: 1,
+        }))
+    }
+
+    fn origin() -> Origin {
+        Origin::of_relation(String::from("groth16"), Curve::Bls12_381, &relation())
+    }
+
+    #[test]
+    fn raw_artifacts_pass_through() {
+        let payload = vec![1, 2, 3];
+        let packed = pack(None, ArtifactKind::Proof, payload.clone());
+        assert_eq!(packed, payload);
+        assert_eq!(
+            open(&packed, ArtifactKind::Proof, "gm17", Curve::Bls12_381, None).unwrap(),
+            payload
+        );
+    }
+
+    #[test]
+    fn enveloped_artifacts_can_be_opened() {
+        let payload = vec![1, 2, 3];
+        let packed = pack(Some(&origin()), ArtifactKind::Proof, payload.clone());
+
+        let (header, raw) = unpack(&packed).unwrap();
+        let header = header.expect("Envelope should be present");
+        assert_eq!(header.kind, ArtifactKind::Proof);
+        assert_eq!(header.origin, origin());
+        assert_eq!(raw, payload);
+
+        assert_eq!(
+            open(
+                &packed,
+                ArtifactKind::Proof,
+                "groth16",
+                Curve::Bls12_381,
+                None
+            )
+            .unwrap(),
+            payload
+        );
+        assert_eq!(
+            open(
+                &packed,
+                ArtifactKind::Proof,
+                "groth16",
+                Curve::Bls12_381,
+                Some(&relation())
+            )
+            .unwrap(),
+            payload
+        );
+    }
+
+    #[test]
+    fn mixing_up_systems_is_detected() {
+        let packed = pack(Some(&origin()), ArtifactKind::Proof, vec![1, 2, 3]);
+        assert!(open(&packed, ArtifactKind::Proof, "gm17", Curve::Bls12_381, None).is_err());
+    }
+
+    #[test]
+    fn mixing_up_kinds_is_detected() {
+        let packed = pack(Some(&origin()), ArtifactKind::Proof, vec![1, 2, 3]);
+        assert!(open(
+            &packed,
+            ArtifactKind::VerifyingKey,
+            "groth16",
+            Curve::Bls12_381,
+            None,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn mixing_up_curves_is_detected() {
+        let packed = pack(Some(&origin()), ArtifactKind::Proof, vec![1, 2, 3]);
+        assert!(open(&packed, ArtifactKind::Proof, "groth16", Curve::Bn254, None).is_err());
+    }
+
+    #[cfg(feature = "linear")]
+    #[test]
+    fn mixing_up_relations_is_detected() {
+        use crate::relations::{LinearEqRelation, WitnessTemplate};
+
+        let packed = pack(Some(&origin()), ArtifactKind::ProvingKey, vec![1, 2, 3]);
+        let other = Relation::LinearEquation(Witness(LinearEqRelation::template()));
+        let error = open(
+            &packed,
+            ArtifactKind::ProvingKey,
+            "groth16",
+            Curve::Bls12_381,
+            Some(&other),
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("not for"), "{}", error);
+    }
+
+    #[test]
+    fn corrupted_content_is_detected() {
+        let mut packed = pack(Some(&origin()), ArtifactKind::Proof, vec![1, 2, 3]);
+        *packed.last_mut().unwrap() ^= 1;
+        assert!(unpack(&packed).is_err());
+    }
+
+    #[test]
+    fn malformed_header_is_detected() {
+        let packed = pack(Some(&origin()), ArtifactKind::Proof, vec![1, 2, 3]);
+        assert!(unpack(&packed[..MAGIC.len() + 2]).is_err());
+    }
+}
@@ -1,26 +1,99 @@
-use std::fmt::Debug;
-
+use std::{
+    any::Any,
+    fmt::Debug,
+    panic::{self, AssertUnwindSafe},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use ark_ff::PrimeField;
 use ark_poly::univariate::DensePolynomial;
 use ark_poly_commit::marlin_pc::MarlinKZG10;
 use ark_relations::r1cs::ConstraintSynthesizer;
 use ark_serialize::CanonicalDeserialize;
+use ark_std::rand::{rngs::StdRng, CryptoRng, RngCore, SeedableRng};
 use blake2::Blake2s;
 use clap::ValueEnum;
-use traits::{NonUniversalSystem, ProvingSystem};
+use rayon::prelude::*;
+use traits::{NonUniversalSystem, ProvingSystem, UniversalSystem, UpdatableSrsSystem};
 
-use crate::{environment::traits::UniversalSystem, serialization::serialize};
+use crate::serialization::serialize;
 
-// For now, we can settle with these types.
-/// Common pairing engine.
-pub type PairingEngine = ark_bls12_381::Bls12_381;
-/// Common scalar field.
+/// Scalar field of the default curve (BLS12-381). All relations are available over it.
 pub type CircuitField = ark_bls12_381::Fr;
 
-// Systems with hardcoded parameters.
-type Groth16 = ark_groth16::Groth16<PairingEngine>;
-type GM17 = ark_gm17::GM17<PairingEngine>;
-type MarlinPolynomialCommitment = MarlinKZG10<PairingEngine, DensePolynomial<CircuitField>>;
-type Marlin = ark_marlin::Marlin<CircuitField, MarlinPolynomialCommitment, Blake2s>;
+/// All available curves.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, ValueEnum)]
+pub enum Curve {
+    #[clap(name = "bls12-381")]
+    Bls12_381,
+    Bn254,
+}
+
+impl Curve {
+    pub fn id(&self) -> String {
+        format!("{:?}", self).to_lowercase()
+    }
+}
+
+/// Scalar field of one of the available curves.
+pub trait CurveField: PrimeField {
+    /// Pairing engine of the curve.
+    type Engine: ark_ec::PairingEngine<Fr = Self>;
+
+    const CURVE: Curve;
+}
+
+impl CurveField for ark_bls12_381::Fr {
+    type Engine = ark_bls12_381::Bls12_381;
+
+    const CURVE: Curve = Curve::Bls12_381;
+}
+
+impl CurveField for ark_bn254::Fr {
+    type Engine = ark_bn254::Bn254;
+
+    const CURVE: Curve = Curve::Bn254;
+}
+
+/// Seed for the randomness used during setup and proving.
+pub type Seed = [u8; 32];
+
+/// Randomness source for setup and proving.
+///
+/// Unless `seed` is provided, it is seeded with OS entropy. An explicit seed should be used only
+/// for generating reproducible test vectors: anyone knowing it can recover the toxic waste of the
+/// setup and the blinding factors of the proofs.
+pub fn rng(seed: Option<Seed>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::from_seed(seed),
+        None => StdRng::from_entropy(),
+    }
+}
+
+/// Message of a caught panic.
+pub fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => String::from("Unknown error"),
+        },
+    }
+}
+
+// Systems with hardcoded parameters (apart from the pairing engine).
+type Groth16<E> = ark_groth16::Groth16<E>;
+type GM17<E> = ark_gm17::GM17<E>;
+type MarlinPolynomialCommitment<E> =
+    MarlinKZG10<E, DensePolynomial<<E as ark_ec::PairingEngine>::Fr>>;
+type Marlin<E> =
+    ark_marlin::Marlin<<E as ark_ec::PairingEngine>::Fr, MarlinPolynomialCommitment<E>, Blake2s>;
+type Plonk<E> = crate::plonk::Plonk<E>;
+
+// SRS updates are supported only over BLS12-381.
+type UpdatableMarlin = Marlin<ark_bls12_381::Bls12_381>;
+type UpdatablePlonk = Plonk<ark_bls12_381::Bls12_381>;
 
 /// All available non universal proving systems.
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, ValueEnum)]
@@ -33,6 +106,7 @@ pub enum NonUniversalProvingSystem {
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, ValueEnum)]
 pub enum UniversalProvingSystem {
     Marlin,
+    Plonk,
 }
 
 /// Any proving system.
@@ -43,6 +117,9 @@ pub enum SomeProvingSystem {
 }
 
 /// Common API for all systems.
+///
+/// Every method is generic over the scalar field `F` of the curve to work with. Usually, it can
+/// be inferred from the circuit.
 impl SomeProvingSystem {
     pub fn id(&self) -> String {
         match self {
@@ -52,31 +129,147 @@ impl SomeProvingSystem {
     }
 
     /// Generates proof for `circuit` using proving key `pk`. Returns serialized proof.
-    pub fn prove<C: ConstraintSynthesizer<CircuitField>>(
+    pub fn prove<F: CurveField, C: ConstraintSynthesizer<F>, R: RngCore + CryptoRng>(
         &self,
         circuit: C,
         pk: Vec<u8>,
+        rng: &mut R,
     ) -> Vec<u8> {
         use SomeProvingSystem::*;
 
         match self {
             NonUniversal(NonUniversalProvingSystem::Groth16) => {
-                self._prove::<_, Groth16>(circuit, pk)
+                self._prove::<_, _, _, Groth16<F::Engine>>(circuit, pk, rng)
+            }
+            NonUniversal(NonUniversalProvingSystem::Gm17) => {
+                self._prove::<_, _, _, GM17<F::Engine>>(circuit, pk, rng)
+            }
+            Universal(UniversalProvingSystem::Marlin) => {
+                self._prove::<_, _, _, Marlin<F::Engine>>(circuit, pk, rng)
+            }
+            Universal(UniversalProvingSystem::Plonk) => {
+                self._prove::<_, _, _, Plonk<F::Engine>>(circuit, pk, rng)
             }
-            NonUniversal(NonUniversalProvingSystem::Gm17) => self._prove::<_, GM17>(circuit, pk),
-            Universal(UniversalProvingSystem::Marlin) => self._prove::<_, Marlin>(circuit, pk),
         }
     }
 
-    fn _prove<C: ConstraintSynthesizer<CircuitField>, S: ProvingSystem>(
+    fn _prove<
+        F: CurveField,
+        C: ConstraintSynthesizer<F>,
+        R: RngCore + CryptoRng,
+        S: ProvingSystem<Field = F>,
+    >(
         &self,
         circuit: C,
         pk: Vec<u8>,
+        rng: &mut R,
     ) -> Vec<u8> {
         let pk = <S::ProvingKey>::deserialize(&*pk).expect("Failed to deserialize proving key");
-        let proof = S::prove(&pk, circuit);
+        let proof = S::prove(&pk, circuit, rng);
         serialize(&proof)
     }
+
+    /// Generates proofs for many circuits using the same proving key `pk`, which is deserialized
+    /// only once. Circuits are proved in parallel, each one with its own randomness source.
+    ///
+    /// For every circuit, returns either the serialized proof or the reason of failure, together
+    /// with the time spent on proving.
+    pub fn prove_batch<
+        F: CurveField,
+        C: ConstraintSynthesizer<F> + Send,
+        R: RngCore + CryptoRng + Send,
+    >(
+        &self,
+        circuits: Vec<(C, R)>,
+        pk: Vec<u8>,
+    ) -> Vec<(Result<Vec<u8>, String>, Duration)> {
+        use SomeProvingSystem::*;
+
+        match self {
+            NonUniversal(NonUniversalProvingSystem::Groth16) => {
+                self._prove_batch::<_, _, _, Groth16<F::Engine>>(circuits, pk)
+            }
+            NonUniversal(NonUniversalProvingSystem::Gm17) => {
+                self._prove_batch::<_, _, _, GM17<F::Engine>>(circuits, pk)
+            }
+            Universal(UniversalProvingSystem::Marlin) => {
+                self._prove_batch::<_, _, _, Marlin<F::Engine>>(circuits, pk)
+            }
+            Universal(UniversalProvingSystem::Plonk) => {
+                self._prove_batch::<_, _, _, Plonk<F::Engine>>(circuits, pk)
+            }
+        }
+    }
+
+    fn _prove_batch<
+        F: CurveField,
+        C: ConstraintSynthesizer<F> + Send,
+        R: RngCore + CryptoRng + Send,
+        S: ProvingSystem<Field = F>,
+    >(
+        &self,
+        circuits: Vec<(C, R)>,
+        pk: Vec<u8>,
+    ) -> Vec<(Result<Vec<u8>, String>, Duration)>
+    where
+        S::ProvingKey: Sync,
+    {
+        let pk = <S::ProvingKey>::deserialize(&*pk).expect("Failed to deserialize proving key");
+        circuits
+            .into_par_iter()
+            .map(|(circuit, mut rng)| {
+                let start = Instant::now();
+                let proof = panic::catch_unwind(AssertUnwindSafe(|| {
+                    serialize(&S::prove(&pk, circuit, &mut rng))
+                }))
+                .map_err(panic_message);
+                (proof, start.elapsed())
+            })
+            .collect()
+    }
+
+    /// Verifies proof `proof` against public input `public_input` using verifying key `vk`.
+    ///
+    /// All arguments are expected to be in serialized form (exactly as `generate_keys` and `prove`
+    /// output them). Returns an error if any of them cannot be deserialized.
+    pub fn verify<F: CurveField>(
+        &self,
+        vk: Vec<u8>,
+        proof: Vec<u8>,
+        public_input: Vec<u8>,
+    ) -> Result<bool> {
+        use SomeProvingSystem::*;
+
+        match self {
+            NonUniversal(NonUniversalProvingSystem::Groth16) => {
+                self._verify::<Groth16<F::Engine>>(vk, proof, public_input)
+            }
+            NonUniversal(NonUniversalProvingSystem::Gm17) => {
+                self._verify::<GM17<F::Engine>>(vk, proof, public_input)
+            }
+            Universal(UniversalProvingSystem::Marlin) => {
+                self._verify::<Marlin<F::Engine>>(vk, proof, public_input)
+            }
+            Universal(UniversalProvingSystem::Plonk) => {
+                self._verify::<Plonk<F::Engine>>(vk, proof, public_input)
+            }
+        }
+    }
+
+    fn _verify<S: ProvingSystem>(
+        &self,
+        vk: Vec<u8>,
+        proof: Vec<u8>,
+        public_input: Vec<u8>,
+    ) -> Result<bool> {
+        let vk = <S::VerifyingKey>::deserialize(&*vk)
+            .map_err(|e| anyhow!("Failed to deserialize verifying key: {}", e))?;
+        let proof = <S::Proof>::deserialize(&*proof)
+            .map_err(|e| anyhow!("Failed to deserialize proof: {}", e))?;
+        let public_input = <Vec<S::Field>>::deserialize(&*public_input)
+            .map_err(|e| anyhow!("Failed to deserialize public input: {}", e))?;
+        Ok(S::verify(&vk, &public_input, &proof))
+    }
 }
 
 /// Serialized keys.
@@ -92,18 +285,32 @@ impl NonUniversalProvingSystem {
     }
 
     /// Generates proving and verifying key for `circuit`. Returns serialized keys.
-    pub fn generate_keys<C: ConstraintSynthesizer<CircuitField>>(&self, circuit: C) -> RawKeys {
+    pub fn generate_keys<F: CurveField, C: ConstraintSynthesizer<F>, R: RngCore + CryptoRng>(
+        &self,
+        circuit: C,
+        rng: &mut R,
+    ) -> RawKeys {
         match self {
-            NonUniversalProvingSystem::Groth16 => self._generate_keys::<_, Groth16>(circuit),
-            NonUniversalProvingSystem::Gm17 => self._generate_keys::<_, GM17>(circuit),
+            NonUniversalProvingSystem::Groth16 => {
+                self._generate_keys::<_, _, _, Groth16<F::Engine>>(circuit, rng)
+            }
+            NonUniversalProvingSystem::Gm17 => {
+                self._generate_keys::<_, _, _, GM17<F::Engine>>(circuit, rng)
+            }
         }
     }
 
-    fn _generate_keys<C: ConstraintSynthesizer<CircuitField>, S: NonUniversalSystem>(
+    fn _generate_keys<
+        F: CurveField,
+        C: ConstraintSynthesizer<F>,
+        R: RngCore + CryptoRng,
+        S: NonUniversalSystem<Field = F>,
+    >(
         &self,
         circuit: C,
+        rng: &mut R,
     ) -> RawKeys {
-        let (pk, vk) = S::generate_keys(circuit);
+        let (pk, vk) = S::generate_keys(circuit, rng);
         RawKeys {
             pk: serialize(&pk),
             vk: serialize(&vk),
@@ -111,48 +318,78 @@ impl NonUniversalProvingSystem {
     }
 }
 
+/// Smallest values of the `generate-srs` parameters that yield SRS sufficient for a circuit.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct SrsParameters {
+    pub num_constraints: usize,
+    pub num_variables: usize,
+    pub degree: usize,
+}
+
+/// Serialized SRS obtained by an update, together with the serialized update proof.
+pub struct RawSrsUpdate {
+    pub srs: Vec<u8>,
+    pub proof: Vec<u8>,
+}
+
 /// API available only for universal proving systems.
 impl UniversalProvingSystem {
     pub fn id(&self) -> String {
         format!("{:?}", self).to_lowercase()
     }
 
-    /// Generates SRS. Returns in serialized version.
-    pub fn generate_srs(
+    /// Generates SRS over the curve of `F`. Returns in serialized version.
+    pub fn generate_srs<F: CurveField, R: RngCore + CryptoRng>(
         &self,
         num_constraints: usize,
         num_variables: usize,
         degree: usize,
+        rng: &mut R,
     ) -> Vec<u8> {
         match self {
-            UniversalProvingSystem::Marlin => {
-                self._generate_srs::<Marlin>(num_constraints, num_variables, degree)
-            }
+            UniversalProvingSystem::Marlin => self._generate_srs::<_, Marlin<F::Engine>>(
+                num_constraints,
+                num_variables,
+                degree,
+                rng,
+            ),
+            UniversalProvingSystem::Plonk => self._generate_srs::<_, Plonk<F::Engine>>(
+                num_constraints,
+                num_variables,
+                degree,
+                rng,
+            ),
         }
     }
 
-    fn _generate_srs<S: UniversalSystem>(
+    fn _generate_srs<R: RngCore + CryptoRng, S: UniversalSystem>(
         &self,
         num_constraints: usize,
         num_variables: usize,
         degree: usize,
+        rng: &mut R,
     ) -> Vec<u8> {
-        let srs = S::generate_srs(num_constraints, num_variables, degree);
+        let srs = S::generate_srs(num_constraints, num_variables, degree, rng);
         serialize(&srs)
     }
 
     /// Generates proving and verifying key for `circuit` using `srs`. Returns serialized keys.
-    pub fn generate_keys<C: ConstraintSynthesizer<CircuitField>>(
+    pub fn generate_keys<F: CurveField, C: ConstraintSynthesizer<F>>(
         &self,
         circuit: C,
         srs: Vec<u8>,
     ) -> RawKeys {
         match self {
-            UniversalProvingSystem::Marlin => self._generate_keys::<_, Marlin>(circuit, srs),
+            UniversalProvingSystem::Marlin => {
+                self._generate_keys::<_, _, Marlin<F::Engine>>(circuit, srs)
+            }
+            UniversalProvingSystem::Plonk => {
+                self._generate_keys::<_, _, Plonk<F::Engine>>(circuit, srs)
+            }
         }
     }
 
-    fn _generate_keys<C: ConstraintSynthesizer<CircuitField>, S: UniversalSystem>(
+    fn _generate_keys<F: CurveField, C: ConstraintSynthesizer<F>, S: UniversalSystem<Field = F>>(
         &self,
         circuit: C,
         srs: Vec<u8>,
@@ -165,139 +402,588 @@ impl UniversalProvingSystem {
             vk: serialize(&vk),
         }
     }
+
+    /// Re-randomizes `srs` (over BLS12-381). Returns serialized new SRS and a proof that it
+    /// extends `srs`.
+    pub fn update_srs<R: RngCore + CryptoRng>(&self, srs: Vec<u8>, rng: &mut R) -> RawSrsUpdate {
+        match self {
+            UniversalProvingSystem::Marlin => self._update_srs::<_, UpdatableMarlin>(srs, rng),
+            UniversalProvingSystem::Plonk => self._update_srs::<_, UpdatablePlonk>(srs, rng),
+        }
+    }
+
+    fn _update_srs<R: RngCore + CryptoRng, S: UpdatableSrsSystem>(
+        &self,
+        srs: Vec<u8>,
+        rng: &mut R,
+    ) -> RawSrsUpdate {
+        let srs =
+            <<S as UniversalSystem>::Srs>::deserialize(&*srs).expect("Failed to deserialize srs");
+        let (srs, proof) = S::update_srs(&srs, rng);
+        RawSrsUpdate {
+            srs: serialize(&srs),
+            proof: serialize(&proof),
+        }
+    }
+
+    /// Checks that `new_srs` is a correctly formed update of `old_srs` (over BLS12-381).
+    pub fn verify_srs_update(&self, old_srs: Vec<u8>, new_srs: Vec<u8>, proof: Vec<u8>) -> bool {
+        match self {
+            UniversalProvingSystem::Marlin => {
+                self._verify_srs_update::<UpdatableMarlin>(old_srs, new_srs, proof)
+            }
+            UniversalProvingSystem::Plonk => {
+                self._verify_srs_update::<UpdatablePlonk>(old_srs, new_srs, proof)
+            }
+        }
+    }
+
+    fn _verify_srs_update<S: UpdatableSrsSystem>(
+        &self,
+        old_srs: Vec<u8>,
+        new_srs: Vec<u8>,
+        proof: Vec<u8>,
+    ) -> bool {
+        let old_srs = <<S as UniversalSystem>::Srs>::deserialize(&*old_srs)
+            .expect("Failed to deserialize srs");
+        let new_srs = <<S as UniversalSystem>::Srs>::deserialize(&*new_srs)
+            .expect("Failed to deserialize srs");
+        let proof =
+            <S::SrsUpdateProof>::deserialize(&*proof).expect("Failed to deserialize update proof");
+        S::verify_srs_update(&old_srs, &new_srs, &proof)
+    }
+
+    /// Cuts `srs` down to the size required by `circuit`. Returns serialized trimmed SRS.
+    pub fn trim_srs<F: CurveField, C: ConstraintSynthesizer<F>>(
+        &self,
+        circuit: C,
+        srs: Vec<u8>,
+    ) -> Vec<u8> {
+        match self {
+            UniversalProvingSystem::Marlin => {
+                self._trim_srs::<_, _, Marlin<F::Engine>>(circuit, srs)
+            }
+            UniversalProvingSystem::Plonk => self._trim_srs::<_, _, Plonk<F::Engine>>(circuit, srs),
+        }
+    }
+
+    fn _trim_srs<F: CurveField, C: ConstraintSynthesizer<F>, S: UniversalSystem<Field = F>>(
+        &self,
+        circuit: C,
+        srs: Vec<u8>,
+    ) -> Vec<u8> {
+        let srs =
+            <<S as UniversalSystem>::Srs>::deserialize(&*srs).expect("Failed to deserialize srs");
+        serialize(&S::trim_srs(&srs, circuit))
+    }
+
+    /// Computes the smallest SRS parameters that are sufficient for `circuit`.
+    pub fn minimal_srs_parameters<F: CurveField, C: ConstraintSynthesizer<F>>(
+        &self,
+        circuit: C,
+    ) -> SrsParameters {
+        match self {
+            UniversalProvingSystem::Marlin => Marlin::<F::Engine>::minimal_srs_parameters(circuit),
+            UniversalProvingSystem::Plonk => Plonk::<F::Engine>::minimal_srs_parameters(circuit),
+        }
+    }
 }
 
 pub mod traits {
+    use ark_ff::PrimeField;
     use ark_relations::r1cs::ConstraintSynthesizer;
     use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use ark_std::rand::{CryptoRng, RngCore};
 
-    use super::CircuitField;
+    use super::SrsParameters;
 
     /// Common API for every proving system.
     pub trait ProvingSystem {
+        /// Scalar field of the underlying curve.
+        type Field: PrimeField;
         type Proof: CanonicalSerialize + CanonicalDeserialize;
         type ProvingKey: CanonicalSerialize + CanonicalDeserialize;
         type VerifyingKey: CanonicalSerialize + CanonicalDeserialize;
 
-        /// Generates proof for `circuit` using proving key `pk`
-        fn prove<C: ConstraintSynthesizer<CircuitField>>(
+        /// Generates proof for `circuit` using proving key `pk` and randomness from `rng`.
+        fn prove<C: ConstraintSynthesizer<Self::Field>, R: RngCore + CryptoRng>(
             pk: &Self::ProvingKey,
             circuit: C,
+            rng: &mut R,
         ) -> Self::Proof;
+
+        /// Verifies `proof` against `public_input` using verifying key `vk`.
+        ///
+        /// Malformed arguments (like public input of a wrong length) make the proof invalid.
+        fn verify(
+            vk: &Self::VerifyingKey,
+            public_input: &[Self::Field],
+            proof: &Self::Proof,
+        ) -> bool;
     }
 
     /// Common API for every universal proving system.
     pub trait UniversalSystem: ProvingSystem {
         type Srs: CanonicalSerialize + CanonicalDeserialize;
 
-        /// Generates SRS.
-        fn generate_srs(num_constraints: usize, num_variables: usize, degree: usize) -> Self::Srs;
+        /// Generates SRS using randomness from `rng`.
+        fn generate_srs<R: RngCore + CryptoRng>(
+            num_constraints: usize,
+            num_variables: usize,
+            degree: usize,
+            rng: &mut R,
+        ) -> Self::Srs;
 
         /// Generates proving and verifying key for `circuit` using `srs`.
-        fn generate_keys<C: ConstraintSynthesizer<CircuitField>>(
+        fn generate_keys<C: ConstraintSynthesizer<Self::Field>>(
             circuit: C,
             srs: &Self::Srs,
         ) -> (Self::ProvingKey, Self::VerifyingKey);
+
+        /// Cuts `srs` down to the size required by `circuit`.
+        fn trim_srs<C: ConstraintSynthesizer<Self::Field>>(
+            srs: &Self::Srs,
+            circuit: C,
+        ) -> Self::Srs;
+
+        /// Computes the smallest SRS parameters that are sufficient for `circuit`.
+        fn minimal_srs_parameters<C: ConstraintSynthesizer<Self::Field>>(
+            circuit: C,
+        ) -> SrsParameters;
+    }
+
+    /// API of universal proving systems whose SRS can be updated.
+    pub trait UpdatableSrsSystem: UniversalSystem {
+        /// Proof that an updated SRS extends the original one.
+        type SrsUpdateProof: CanonicalSerialize + CanonicalDeserialize;
+
+        /// Re-randomizes `srs` using randomness from `rng`.
+        fn update_srs<R: RngCore + CryptoRng>(
+            srs: &Self::Srs,
+            rng: &mut R,
+        ) -> (Self::Srs, Self::SrsUpdateProof);
+
+        /// Checks that `new` has been correctly obtained from `old` by `update_srs`.
+        fn verify_srs_update(
+            old: &Self::Srs,
+            new: &Self::Srs,
+            proof: &Self::SrsUpdateProof,
+        ) -> bool;
     }
 
     /// Common API for every non universal proving system.
     pub trait NonUniversalSystem: ProvingSystem {
-        /// Generates proving and verifying key for `circuit`.
-        fn generate_keys<C: ConstraintSynthesizer<CircuitField>>(
+        /// Generates proving and verifying key for `circuit` using randomness from `rng`.
+        fn generate_keys<C: ConstraintSynthesizer<Self::Field>, R: RngCore + CryptoRng>(
             circuit: C,
+            rng: &mut R,
         ) -> (Self::ProvingKey, Self::VerifyingKey);
     }
 }
 
 mod trait_implementations {
+    use ark_bls12_381::Bls12_381;
+    use ark_ec::PairingEngine;
     use ark_relations::r1cs::ConstraintSynthesizer;
     use ark_snark::SNARK;
-    use ark_std::rand::{rngs::StdRng, SeedableRng};
+    use ark_std::rand::{rngs::StdRng, CryptoRng, RngCore, SeedableRng};
 
     use crate::{
         environment::{
-            traits::{NonUniversalSystem, ProvingSystem, UniversalSystem},
-            Groth16, Marlin, MarlinPolynomialCommitment, GM17,
+            traits::{NonUniversalSystem, ProvingSystem, UniversalSystem, UpdatableSrsSystem},
+            Groth16, Marlin, MarlinPolynomialCommitment, Plonk, SrsParameters, GM17,
         },
-        CircuitField,
+        plonk, srs,
     };
 
-    fn dummy_rng() -> StdRng {
-        StdRng::from_seed([0u8; 32])
-    }
-
     // Unfortunately, Groth16, GM17 and Marlin don't have any common supertrait, and therefore,
     // we cannot provide any blanket implementation without running into damned `upstream crates may
     // add a new impl of trait` error (see https://github.com/rust-lang/rfcs/issues/2758).
     // Tfu. Disgusting.
 
-    /// This macro takes a type `system` as the only argument and provides `ProvingSystem` and
-    /// `NonUniversalSystem` implementations for it.
+    /// This macro takes a type `system` (generic over pairing engine `E`) as the only argument and
+    /// provides `ProvingSystem` and `NonUniversalSystem` implementations for it.
     ///
-    /// `system` should implement `SNARK<CircuitField>` trait.  
+    /// `system` should implement `SNARK<E::Fr>` trait.  
     macro_rules! impl_non_universal_system_for_snark {
         ($system:ty) => {
-            impl ProvingSystem for $system {
-                type Proof = <$system as SNARK<CircuitField>>::Proof;
-                type ProvingKey = <$system as SNARK<CircuitField>>::ProvingKey;
-                type VerifyingKey = <$system as SNARK<CircuitField>>::VerifyingKey;
+            impl<E: PairingEngine> ProvingSystem for $system {
+                type Field = E::Fr;
+                type Proof = <$system as SNARK<E::Fr>>::Proof;
+                type ProvingKey = <$system as SNARK<E::Fr>>::ProvingKey;
+                type VerifyingKey = <$system as SNARK<E::Fr>>::VerifyingKey;
 
-                fn prove<C: ConstraintSynthesizer<CircuitField>>(
+                fn prove<C: ConstraintSynthesizer<E::Fr>, R: RngCore + CryptoRng>(
                     pk: &Self::ProvingKey,
                     circuit: C,
+                    rng: &mut R,
                 ) -> Self::Proof {
-                    let mut rng = dummy_rng();
-                    <$system as SNARK<CircuitField>>::prove(pk, circuit, &mut rng)
-                        .expect("Failed to generate keys")
+                    <$system as SNARK<E::Fr>>::prove(pk, circuit, rng)
+                        .expect("Failed to generate proof")
+                }
+
+                fn verify(
+                    vk: &Self::VerifyingKey,
+                    public_input: &[E::Fr],
+                    proof: &Self::Proof,
+                ) -> bool {
+                    <$system as SNARK<E::Fr>>::verify(vk, public_input, proof).unwrap_or(false)
                 }
             }
 
-            impl NonUniversalSystem for $system {
-                fn generate_keys<C: ConstraintSynthesizer<CircuitField>>(
+            impl<E: PairingEngine> NonUniversalSystem for $system {
+                fn generate_keys<C: ConstraintSynthesizer<E::Fr>, R: RngCore + CryptoRng>(
                     circuit: C,
+                    rng: &mut R,
                 ) -> (Self::ProvingKey, Self::VerifyingKey) {
-                    let mut rng = dummy_rng();
-                    <$system as SNARK<CircuitField>>::circuit_specific_setup(circuit, &mut rng)
+                    <$system as SNARK<E::Fr>>::circuit_specific_setup(circuit, rng)
                         .expect("Failed to generate keys")
                 }
             }
         };
     }
 
-    impl_non_universal_system_for_snark!(Groth16);
-    impl_non_universal_system_for_snark!(GM17);
+    impl_non_universal_system_for_snark!(Groth16<E>);
+    impl_non_universal_system_for_snark!(GM17<E>);
 
-    impl ProvingSystem for Marlin {
-        type Proof = ark_marlin::Proof<CircuitField, MarlinPolynomialCommitment>;
-        type ProvingKey = ark_marlin::IndexProverKey<CircuitField, MarlinPolynomialCommitment>;
-        type VerifyingKey = ark_marlin::IndexVerifierKey<CircuitField, MarlinPolynomialCommitment>;
+    impl<E: PairingEngine> ProvingSystem for Marlin<E> {
+        type Field = E::Fr;
+        type Proof = ark_marlin::Proof<E::Fr, MarlinPolynomialCommitment<E>>;
+        type ProvingKey = ark_marlin::IndexProverKey<E::Fr, MarlinPolynomialCommitment<E>>;
+        type VerifyingKey = ark_marlin::IndexVerifierKey<E::Fr, MarlinPolynomialCommitment<E>>;
 
-        fn prove<C: ConstraintSynthesizer<CircuitField>>(
+        fn prove<C: ConstraintSynthesizer<E::Fr>, R: RngCore + CryptoRng>(
             pk: &Self::ProvingKey,
             circuit: C,
+            rng: &mut R,
         ) -> Self::Proof {
-            let mut rng = dummy_rng();
-            Marlin::prove(pk, circuit, &mut rng).expect("Failed to generate proof")
+            Marlin::<E>::prove(pk, circuit, rng).expect("Failed to generate proof")
+        }
+
+        fn verify(vk: &Self::VerifyingKey, public_input: &[E::Fr], proof: &Self::Proof) -> bool {
+            // Randomness is used here only for batching polynomial commitment checks.
+            let mut rng = StdRng::from_entropy();
+            Marlin::<E>::verify(vk, public_input, proof, &mut rng).unwrap_or(false)
         }
     }
 
-    impl UniversalSystem for Marlin {
-        type Srs = ark_marlin::UniversalSRS<CircuitField, MarlinPolynomialCommitment>;
+    impl<E: PairingEngine> UniversalSystem for Marlin<E> {
+        type Srs = ark_marlin::UniversalSRS<E::Fr, MarlinPolynomialCommitment<E>>;
 
-        fn generate_srs(num_constraints: usize, num_variables: usize, degree: usize) -> Self::Srs {
-            let mut rng = dummy_rng();
-            Marlin::universal_setup(num_constraints, num_variables, degree, &mut rng)
+        fn generate_srs<R: RngCore + CryptoRng>(
+            num_constraints: usize,
+            num_variables: usize,
+            degree: usize,
+            rng: &mut R,
+        ) -> Self::Srs {
+            Marlin::<E>::universal_setup(num_constraints, num_variables, degree, rng)
                 .expect("Failed to generate SRS")
         }
 
-        fn generate_keys<C: ConstraintSynthesizer<CircuitField>>(
+        fn generate_keys<C: ConstraintSynthesizer<E::Fr>>(
             circuit: C,
             srs: &Self::Srs,
         ) -> (Self::ProvingKey, Self::VerifyingKey) {
-            Marlin::index(srs, circuit).expect(
+            Marlin::<E>::index(srs, circuit).expect(
                 "Failed to generate keys from SRS (it might be the case, that the circuit is \
                 larger than the SRS allows).",
             )
         }
+
+        fn trim_srs<C: ConstraintSynthesizer<E::Fr>>(srs: &Self::Srs, circuit: C) -> Self::Srs {
+            let index =
+                ark_marlin::AHPForR1CS::<E::Fr>::index(circuit).expect("Failed to index circuit");
+            srs::trim(srs, index.max_degree())
+        }
+
+        fn minimal_srs_parameters<C: ConstraintSynthesizer<E::Fr>>(circuit: C) -> SrsParameters {
+            let index =
+                ark_marlin::AHPForR1CS::<E::Fr>::index(circuit).expect("Failed to index circuit");
+            SrsParameters {
+                num_constraints: index.index_info.num_constraints,
+                num_variables: index.index_info.num_variables,
+                degree: index.index_info.num_non_zero,
+            }
+        }
+    }
+
+    impl UpdatableSrsSystem for Marlin<Bls12_381> {
+        type SrsUpdateProof = srs::SrsUpdateProof;
+
+        fn update_srs<R: RngCore + CryptoRng>(
+            srs: &Self::Srs,
+            rng: &mut R,
+        ) -> (Self::Srs, Self::SrsUpdateProof) {
+            srs::update(srs, rng)
+        }
+
+        fn verify_srs_update(
+            old: &Self::Srs,
+            new: &Self::Srs,
+            proof: &Self::SrsUpdateProof,
+        ) -> bool {
+            srs::verify_update(old, new, proof)
+        }
+    }
+
+    impl<E: PairingEngine> ProvingSystem for Plonk<E> {
+        type Field = E::Fr;
+        type Proof = plonk::Proof<E>;
+        type ProvingKey = plonk::ProvingKey<E>;
+        type VerifyingKey = plonk::VerifyingKey<E>;
+
+        fn prove<C: ConstraintSynthesizer<E::Fr>, R: RngCore + CryptoRng>(
+            pk: &Self::ProvingKey,
+            circuit: C,
+            rng: &mut R,
+        ) -> Self::Proof {
+            Plonk::<E>::prove(pk, circuit, rng)
+        }
+
+        fn verify(vk: &Self::VerifyingKey, public_input: &[E::Fr], proof: &Self::Proof) -> bool {
+            Plonk::<E>::verify(vk, public_input, proof)
+        }
+    }
+
+    impl<E: PairingEngine> UniversalSystem for Plonk<E> {
+        type Srs = srs::KzgSrs<E>;
+
+        fn generate_srs<R: RngCore + CryptoRng>(
+            num_constraints: usize,
+            num_variables: usize,
+            degree: usize,
+            rng: &mut R,
+        ) -> Self::Srs {
+            Plonk::<E>::universal_setup(num_constraints, num_variables, degree, rng)
+        }
+
+        fn generate_keys<C: ConstraintSynthesizer<E::Fr>>(
+            circuit: C,
+            srs: &Self::Srs,
+        ) -> (Self::ProvingKey, Self::VerifyingKey) {
+            Plonk::<E>::index(srs, circuit)
+        }
+
+        fn trim_srs<C: ConstraintSynthesizer<E::Fr>>(srs: &Self::Srs, circuit: C) -> Self::Srs {
+            srs::trim(srs, Plonk::<E>::max_degree(circuit))
+        }
+
+        fn minimal_srs_parameters<C: ConstraintSynthesizer<E::Fr>>(circuit: C) -> SrsParameters {
+            let (num_constraints, num_variables, degree) = Plonk::<E>::srs_parameters(circuit);
+            SrsParameters {
+                num_constraints,
+                num_variables,
+                degree,
+            }
+        }
+    }
+
+    impl UpdatableSrsSystem for Plonk<Bls12_381> {
+        type SrsUpdateProof = srs::SrsUpdateProof;
+
+        fn update_srs<R: RngCore + CryptoRng>(
+            srs: &Self::Srs,
+            rng: &mut R,
+        ) -> (Self::Srs, Self::SrsUpdateProof) {
+            srs::update(srs, rng)
+        }
+
+        fn verify_srs_update(
+            old: &Self::Srs,
+            new: &Self::Srs,
+            proof: &Self::SrsUpdateProof,
+        ) -> bool {
+            srs::verify_update(old, new, proof)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "xor"))]
+mod tests {
+    use super::*;
+    use crate::relations::{GetPublicInput, XorRelation};
+
+    fn relation() -> XorRelation {
+        XorRelation {
+            public_xoree: 2,
+            private_xoree: 3,
+            result: 1,
+        }
+    }
+
+    fn check_verification<F: CurveField>(system: SomeProvingSystem, keys: RawKeys) {
+        let input: Vec<F> = relation().public_input();
+        let proof = system.prove::<F, _, _>(relation(), keys.pk, &mut rng(None));
+        assert!(system
+            .verify::<F>(keys.vk.clone(), proof.clone(), serialize(&input))
+            .unwrap());
+
+        let short_input = input[..input.len() - 1].to_vec();
+        assert!(!system
+            .verify::<F>(keys.vk.clone(), proof.clone(), serialize(&short_input))
+            .unwrap());
+
+        let mut wrong_input = input;
+        wrong_input[0] += F::from(1u8);
+        assert!(!system
+            .verify::<F>(keys.vk.clone(), proof.clone(), serialize(&wrong_input))
+            .unwrap());
+
+        assert!(system
+            .verify::<F>(keys.vk, proof[1..].to_vec(), serialize(&wrong_input))
+            .is_err());
+    }
+
+    #[test]
+    fn verifies_groth16_proof() {
+        let system = NonUniversalProvingSystem::Groth16;
+        let keys = system.generate_keys::<CircuitField, _, _>(relation(), &mut rng(None));
+        check_verification::<CircuitField>(SomeProvingSystem::NonUniversal(system), keys);
+    }
+
+    #[test]
+    fn proves_batch_with_single_key() {
+        let system = NonUniversalProvingSystem::Groth16;
+        let keys = system.generate_keys::<CircuitField, _, _>(relation(), &mut rng(None));
+        let other = XorRelation {
+            public_xoree: 5,
+            private_xoree: 3,
+            result: 6,
+        };
+
+        let system = SomeProvingSystem::NonUniversal(system);
+        let proofs = system.prove_batch::<CircuitField, _, _>(
+            vec![(relation(), rng(None)), (other, rng(None))],
+            keys.pk,
+        );
+
+        assert_eq!(proofs.len(), 2);
+        for ((proof, _), relation) in proofs.into_iter().zip([relation(), other]) {
+            let input: Vec<CircuitField> = relation.public_input();
+            assert!(system
+                .verify::<CircuitField>(
+                    keys.vk.clone(),
+                    proof.expect("Failed to prove"),
+                    serialize(&input)
+                )
+                .unwrap());
+        }
+    }
+
+    #[test]
+    fn verifies_gm17_proof() {
+        let system = NonUniversalProvingSystem::Gm17;
+        let keys = system.generate_keys::<CircuitField, _, _>(relation(), &mut rng(None));
+        check_verification::<CircuitField>(SomeProvingSystem::NonUniversal(system), keys);
+    }
+
+    #[test]
+    fn verifies_marlin_proof() {
+        let system = UniversalProvingSystem::Marlin;
+        let srs = system.generate_srs::<CircuitField, _>(100, 100, 100, &mut rng(None));
+        let keys = system.generate_keys::<CircuitField, _>(relation(), srs);
+        check_verification::<CircuitField>(SomeProvingSystem::Universal(system), keys);
+    }
+
+    #[test]
+    fn verifies_plonk_proof() {
+        let system = UniversalProvingSystem::Plonk;
+        let srs = system.generate_srs::<CircuitField, _>(100, 100, 100, &mut rng(None));
+        let keys = system.generate_keys::<CircuitField, _>(relation(), srs);
+        check_verification::<CircuitField>(SomeProvingSystem::Universal(system), keys);
+    }
+
+    #[test]
+    fn trimmed_plonk_srs_serves_relation() {
+        let system = UniversalProvingSystem::Plonk;
+        let srs = system.generate_srs::<CircuitField, _>(100, 100, 100, &mut rng(None));
+        let trimmed = system.trim_srs::<CircuitField, _>(relation(), srs.clone());
+        assert!(trimmed.len() < srs.len());
+
+        let keys = system.generate_keys::<CircuitField, _>(relation(), trimmed);
+        check_verification::<CircuitField>(SomeProvingSystem::Universal(system), keys);
+    }
+
+    #[test]
+    fn trimmed_marlin_srs_serves_relation() {
+        let system = UniversalProvingSystem::Marlin;
+        let srs = system.generate_srs::<CircuitField, _>(100, 100, 100, &mut rng(None));
+        let trimmed = system.trim_srs::<CircuitField, _>(relation(), srs.clone());
+        assert!(trimmed.len() < srs.len());
+
+        let keys = system.generate_keys::<CircuitField, _>(relation(), trimmed);
+        check_verification::<CircuitField>(SomeProvingSystem::Universal(system), keys);
+    }
+
+    #[test]
+    fn minimal_marlin_srs_serves_relation() {
+        let system = UniversalProvingSystem::Marlin;
+        let SrsParameters {
+            num_constraints,
+            num_variables,
+            degree,
+        } = system.minimal_srs_parameters::<CircuitField, _>(relation());
+        let srs = system.generate_srs::<CircuitField, _>(
+            num_constraints,
+            num_variables,
+            degree,
+            &mut rng(None),
+        );
+
+        let keys = system.generate_keys::<CircuitField, _>(relation(), srs);
+        check_verification::<CircuitField>(SomeProvingSystem::Universal(system), keys);
+    }
+
+    #[test]
+    fn updated_marlin_srs_serves_relation() {
+        let system = UniversalProvingSystem::Marlin;
+        let srs = system.generate_srs::<CircuitField, _>(100, 100, 100, &mut rng(None));
+        let update = system.update_srs(srs.clone(), &mut rng(None));
+        assert!(system.verify_srs_update(srs, update.srs.clone(), update.proof));
+
+        let keys = system.generate_keys::<CircuitField, _>(relation(), update.srs);
+        check_verification::<CircuitField>(SomeProvingSystem::Universal(system), keys);
+    }
+
+    #[test]
+    fn verifies_groth16_proof_over_bn254() {
+        let system = NonUniversalProvingSystem::Groth16;
+        let keys = system.generate_keys::<ark_bn254::Fr, _, _>(relation(), &mut rng(None));
+        check_verification::<ark_bn254::Fr>(SomeProvingSystem::NonUniversal(system), keys);
+    }
+
+    #[test]
+    fn verifies_gm17_proof_over_bn254() {
+        let system = NonUniversalProvingSystem::Gm17;
+        let keys = system.generate_keys::<ark_bn254::Fr, _, _>(relation(), &mut rng(None));
+        check_verification::<ark_bn254::Fr>(SomeProvingSystem::NonUniversal(system), keys);
+    }
+
+    #[test]
+    fn verifies_marlin_proof_over_bn254() {
+        let system = UniversalProvingSystem::Marlin;
+        let srs = system.generate_srs::<ark_bn254::Fr, _>(100, 100, 100, &mut rng(None));
+        let keys = system.generate_keys::<ark_bn254::Fr, _>(relation(), srs);
+        check_verification::<ark_bn254::Fr>(SomeProvingSystem::Universal(system), keys);
+    }
+}
+
+#[cfg(test)]
+mod rng_tests {
+    use super::*;
+
+    #[test]
+    fn seeded_rng_is_reproducible() {
+        let seed = [7u8; 32];
+        assert_eq!(rng(Some(seed)).next_u64(), rng(Some(seed)).next_u64());
+    }
+
+    #[test]
+    fn unseeded_rng_is_not_reproducible() {
+        let mut first = [0u8; 32];
+        let mut second = [0u8; 32];
+        rng(None).fill_bytes(&mut first);
+        rng(None).fill_bytes(&mut second);
+        assert_ne!(first, second);
     }
 }
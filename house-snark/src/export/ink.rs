@@ -0,0 +1,132 @@
+//! Rust module verifying Groth16 proofs, suitable for embedding in ink! contracts.
+//!
+//! The module consists of a short header, which fixes the curve and embeds the raw verifying key,
+//! followed by `ink_verifier.rs`, which is plain Rust code relying only on arkworks crates.
+
+use ark_ec::PairingEngine;
+use ark_serialize::CanonicalDeserialize;
+
+use crate::environment::Curve;
+
+/// Code shared by all generated modules.
+const VERIFIER: &str = include_str!("ink_verifier.rs");
+
+/// Number of verifying key bytes per line of the generated module.
+const BYTES_PER_LINE: usize = 16;
+
+fn check_verifying_key<E: PairingEngine>(vk: &[u8]) {
+    ark_groth16::VerifyingKey::<E>::deserialize(vk)
+        .expect("Failed to deserialize Groth16 verifying key");
+}
+
+/// Source code of a Rust module verifying proofs against the raw Groth16 verifying key `vk`
+/// over `curve`.
+pub fn verifier_module(vk: &[u8], curve: Curve) -> String {
+    let (engine, field) = match curve {
+        Curve::Bls12_381 => {
+            check_verifying_key::<ark_bls12_381::Bls12_381>(vk);
+            ("ark_bls12_381::Bls12_381", "ark_bls12_381::Fr")
+        }
+        Curve::Bn254 => {
+            check_verifying_key::<ark_bn254::Bn254>(vk);
+            ("ark_bn254::Bn254", "ark_bn254::Fr")
+        }
+    };
+
+    let vk_lines: String = vk
+        .chunks(BYTES_PER_LINE)
+        .map(|chunk| {
+            let bytes = chunk
+                .iter()
+                .map(|byte| format!("0x{:02x},", byte))
+                .collect::<Vec<_>>();
+            format!("    {}\n", bytes.join(" "))
+        })
+        .collect();
+
+    format!(
+        "// Generated by house-snark from a Groth16 verifying key over {}.\n\
+         \n\
+         type Curve = {};\n\
+         type Field = {};\n\
+         \n\
+         const VERIFYING_KEY: &[u8] = &[\n\
+         {}];\n\
+         \n\
+         {}",
+        curve.id(),
+        engine,
+        field,
+        vk_lines,
+        VERIFIER
+    )
+}
+
+#[cfg(all(test, feature = "xor"))]
+mod tests {
+    use super::*;
+    use crate::{
+        environment::{rng, CircuitField, NonUniversalProvingSystem, SomeProvingSystem},
+        relations::{GetPublicInput, XorRelation},
+        serialization::serialize,
+    };
+
+    /// The shared part of generated modules, compiled as plain Rust.
+    #[allow(dead_code)]
+    mod verifier {
+        type Curve = ark_bls12_381::Bls12_381;
+        type Field = ark_bls12_381::Fr;
+
+        const VERIFYING_KEY: &[u8] = &[];
+
+        include!("ink_verifier.rs");
+    }
+
+    /// Reads the bytes of `VERIFYING_KEY` back from the generated module.
+    fn embedded_verifying_key(module: &str) -> Vec<u8> {
+        let start = module.find("&[\n").expect("Missing verifying key") + 3;
+        let end = start + module[start..].find("];").expect("Missing verifying key");
+        module[start..end]
+            .split(',')
+            .map(str::trim)
+            .filter(|byte| !byte.is_empty())
+            .map(|byte| u8::from_str_radix(byte.trim_start_matches("0x"), 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn generated_module_verifies_house_snark_proof() {
+        let relation = XorRelation {
+            public_xoree: 2,
+            private_xoree: 3,
+            result: 1,
+        };
+        let system = NonUniversalProvingSystem::Groth16;
+        let keys = system.generate_keys::<CircuitField, _, _>(relation, &mut rng(None));
+        let proof = SomeProvingSystem::NonUniversal(system).prove::<CircuitField, _, _>(
+            relation,
+            keys.pk,
+            &mut rng(None),
+        );
+        let mut public_input: Vec<CircuitField> = relation.public_input();
+
+        let module = verifier_module(&keys.vk, Curve::Bls12_381);
+        assert!(module.ends_with(VERIFIER));
+        let vk = embedded_verifying_key(&module);
+        assert_eq!(vk, keys.vk);
+
+        assert!(verifier::verify_with_key(
+            &vk,
+            &proof,
+            &serialize(&public_input)
+        ));
+
+        public_input[0] += CircuitField::from(1u8);
+        assert!(!verifier::verify_with_key(
+            &vk,
+            &proof,
+            &serialize(&public_input)
+        ));
+        assert!(!verifier::verify_with_key(&vk, &proof[1..], &[]));
+    }
+}
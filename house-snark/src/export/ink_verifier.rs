@@ -0,0 +1,26 @@
+// Verification of Groth16 proofs against the embedded verifying key. Works both with `std` and in
+// `no_std` environments (like ink! contracts), as long as `ark-groth16`, `ark-serialize`, `ark-std`
+// and the crate of the curve are available.
+
+use ark_groth16::{prepare_verifying_key, verify_proof, Proof, VerifyingKey};
+use ark_serialize::CanonicalDeserialize;
+use ark_std::vec::Vec;
+
+/// Verifies `proof` against `public_input` (both serialized exactly like house-snark artifacts)
+/// using the embedded verifying key.
+pub fn verify(proof: &[u8], public_input: &[u8]) -> bool {
+    verify_with_key(VERIFYING_KEY, proof, public_input)
+}
+
+/// Like `verify`, but uses the serialized verifying key `vk` instead of the embedded one.
+pub fn verify_with_key(vk: &[u8], proof: &[u8], public_input: &[u8]) -> bool {
+    let (vk, proof, public_input) = match (
+        VerifyingKey::<Curve>::deserialize(vk),
+        Proof::<Curve>::deserialize(proof),
+        Vec::<Field>::deserialize(public_input),
+    ) {
+        (Ok(vk), Ok(proof), Ok(public_input)) => (vk, proof, public_input),
+        _ => return false,
+    };
+    verify_proof(&prepare_verifying_key(&vk), &proof, &public_input).unwrap_or(false)
+}
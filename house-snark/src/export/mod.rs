@@ -0,0 +1,64 @@
+//! Exporting verifiers of house-snark proofs for other execution environments.
+
+use ark_serialize::CanonicalDeserialize;
+use clap::ValueEnum;
+
+use crate::environment::Curve;
+
+mod ink;
+mod solidity;
+
+/// Supported formats of exported verifiers.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, ValueEnum)]
+pub enum VerifierFormat {
+    /// Standalone Solidity contract checking Groth16 proofs over BN254 on an EVM chain.
+    Solidity,
+    /// Rust module with the verifying key embedded, which can be compiled into ink! contracts.
+    Ink,
+}
+
+impl VerifierFormat {
+    /// Extension of the file to which the verifier should be saved.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            VerifierFormat::Solidity => "sol",
+            VerifierFormat::Ink => "rs",
+        }
+    }
+
+    /// Curve of the verifying key, unless specified otherwise.
+    pub fn default_curve(&self) -> Curve {
+        match self {
+            VerifierFormat::Solidity => Curve::Bn254,
+            VerifierFormat::Ink => Curve::Bls12_381,
+        }
+    }
+
+    /// Generates verifier source code for a raw Groth16 verifying key over `curve`.
+    pub fn export(&self, verifying_key: Vec<u8>, curve: Curve) -> String {
+        match self {
+            VerifierFormat::Solidity => {
+                assert_eq!(
+                    curve,
+                    Curve::Bn254,
+                    "Solidity verifier is available only over BN254"
+                );
+                let vk =
+                    ark_groth16::VerifyingKey::<ark_bn254::Bn254>::deserialize(&*verifying_key)
+                        .expect("Failed to deserialize Groth16 verifying key over BN254");
+                solidity::verifier_contract(&vk)
+            }
+            VerifierFormat::Ink => ink::verifier_module(&verifying_key, curve),
+        }
+    }
+}
+
+/// Encodes a raw Groth16 proof over BN254 together with its raw public input as calldata of
+/// `verifyProof` function of the exported Solidity verifier.
+pub fn solidity_calldata(proof: Vec<u8>, public_input: Vec<u8>) -> Vec<u8> {
+    let proof = ark_groth16::Proof::<ark_bn254::Bn254>::deserialize(&*proof)
+        .expect("Failed to deserialize Groth16 proof over BN254");
+    let public_input = Vec::<ark_bn254::Fr>::deserialize(&*public_input)
+        .expect("Failed to deserialize public input over BN254");
+    solidity::calldata(&proof, &public_input)
+}
@@ -0,0 +1,270 @@
+//! Solidity verifier of Groth16 proofs over BN254.
+//!
+//! The contract relies on the EVM precompiles for BN254 addition (`0x06`), scalar multiplication
+//! (`0x07`) and pairing check (`0x08`). These expect coordinates as big-endian 32-byte words, with
+//! `Fq2` elements given imaginary part first.
+
+use ark_bn254::{Bn254, Fq, Fr, G1Affine, G2Affine};
+use ark_ff::{BigInteger, PrimeField};
+use ark_groth16::{Proof, VerifyingKey};
+use sha3::{Digest, Keccak256};
+
+const TEMPLATE: &str = r#"// SPDX-License-Identifier: Apache-2.0
+// Generated by house-snark.
+pragma solidity ^0.8.0;
+
+/// Verifier of Groth16 proofs over BN254 for a fixed verifying key.
+contract Verifier {
+    /// Modulus of the base field.
+    uint256 constant Q = 21888242871839275222246405745257275088696311157297823662689037894645226208583;
+    /// Modulus of the scalar field. Public inputs must be smaller.
+    uint256 constant R = 21888242871839275222246405745257275088548364400416034343698204186575808495617;
+
+<%CONSTANTS%>
+    /// Checks that `e(-a, b) * e(alpha, beta) * e(vk_x, gamma) * e(c, delta) == 1`, where
+    /// `vk_x = IC_0 + input[0] * IC_1 + ... + input[n - 1] * IC_n`.
+    ///
+    /// Coordinates of `b` are given imaginary part first.
+    function verifyProof(
+        uint256[2] calldata a,
+        uint256[2][2] calldata b,
+        uint256[2] calldata c,
+        uint256[<%NUM_INPUTS%>] calldata input
+    ) external view returns (bool) {
+        for (uint256 i = 0; i < input.length; i++) {
+            require(input[i] < R, "Public input is not reduced");
+        }
+
+        uint256[2] memory vkX = [IC_0_X, IC_0_Y];
+<%ACCUMULATE_INPUTS%>
+        uint256[2] memory negA = [a[0], (Q - (a[1] % Q)) % Q];
+
+        uint256[24] memory pairingInput = [
+            negA[0], negA[1], b[0][0], b[0][1], b[1][0], b[1][1],
+            ALPHA_X, ALPHA_Y, BETA_X_IM, BETA_X_RE, BETA_Y_IM, BETA_Y_RE,
+            vkX[0], vkX[1], GAMMA_X_IM, GAMMA_X_RE, GAMMA_Y_IM, GAMMA_Y_RE,
+            c[0], c[1], DELTA_X_IM, DELTA_X_RE, DELTA_Y_IM, DELTA_Y_RE
+        ];
+        uint256[1] memory result;
+        bool success;
+        assembly {
+            success := staticcall(gas(), 0x08, pairingInput, 0x300, result, 0x20)
+        }
+        return success && result[0] == 1;
+    }
+
+    function add(uint256[2] memory p, uint256[2] memory q) internal view returns (uint256[2] memory r) {
+        uint256[4] memory addInput = [p[0], p[1], q[0], q[1]];
+        bool success;
+        assembly {
+            success := staticcall(gas(), 0x06, addInput, 0x80, r, 0x40)
+        }
+        require(success, "Point addition failed");
+    }
+
+    function mul(uint256[2] memory p, uint256 s) internal view returns (uint256[2] memory r) {
+        uint256[3] memory mulInput = [p[0], p[1], s];
+        bool success;
+        assembly {
+            success := staticcall(gas(), 0x07, mulInput, 0x60, r, 0x40)
+        }
+        require(success, "Scalar multiplication failed");
+    }
+}
+"#;
+
+/// Big-endian 32-byte word encoding `element`.
+fn word<F: PrimeField>(element: F) -> Vec<u8> {
+    element.into_repr().to_bytes_be()
+}
+
+/// EVM encoding of a `G1` point: `[x, y]`, with the point at infinity being `[0, 0]`.
+fn g1_words(point: &G1Affine) -> [Fq; 2] {
+    if point.infinity {
+        [Fq::from(0u8); 2]
+    } else {
+        [point.x, point.y]
+    }
+}
+
+/// EVM encoding of a `G2` point: `[x_im, x_re, y_im, y_re]`, with the point at infinity being all
+/// zeros.
+fn g2_words(point: &G2Affine) -> [Fq; 4] {
+    if point.infinity {
+        [Fq::from(0u8); 4]
+    } else {
+        [point.x.c1, point.x.c0, point.y.c1, point.y.c0]
+    }
+}
+
+fn constant(name: &str, value: Fq) -> String {
+    format!(
+        "    uint256 constant {} = 0x{};\n",
+        name,
+        hex::encode(word(value))
+    )
+}
+
+fn g1_constants(name: &str, point: &G1Affine) -> String {
+    let [x, y] = g1_words(point);
+    constant(&format!("{}_X", name), x) + &constant(&format!("{}_Y", name), y)
+}
+
+fn g2_constants(name: &str, point: &G2Affine) -> String {
+    let [x_im, x_re, y_im, y_re] = g2_words(point);
+    [
+        ("X_IM", x_im),
+        ("X_RE", x_re),
+        ("Y_IM", y_im),
+        ("Y_RE", y_re),
+    ]
+    .into_iter()
+    .map(|(suffix, value)| constant(&format!("{}_{}", name, suffix), value))
+    .collect()
+}
+
+/// Source code of a Solidity contract verifying proofs against `vk`.
+pub fn verifier_contract(vk: &VerifyingKey<Bn254>) -> String {
+    let num_inputs = vk.gamma_abc_g1.len() - 1;
+    assert!(
+        num_inputs > 0,
+        "Solidity verifier requires at least one public input"
+    );
+
+    let mut constants = g1_constants("ALPHA", &vk.alpha_g1)
+        + &g2_constants("BETA", &vk.beta_g2)
+        + &g2_constants("GAMMA", &vk.gamma_g2)
+        + &g2_constants("DELTA", &vk.delta_g2);
+    for (i, point) in vk.gamma_abc_g1.iter().enumerate() {
+        constants += &g1_constants(&format!("IC_{}", i), point);
+    }
+
+    let accumulate_inputs: String = (0..num_inputs)
+        .map(|i| {
+            format!(
+                "        vkX = add(vkX, mul([IC_{}_X, IC_{}_Y], input[{}]));\n",
+                i + 1,
+                i + 1,
+                i
+            )
+        })
+        .collect();
+
+    TEMPLATE
+        .replace("<%CONSTANTS%>", &constants)
+        .replace("<%NUM_INPUTS%>", &num_inputs.to_string())
+        .replace("<%ACCUMULATE_INPUTS%>", &accumulate_inputs)
+}
+
+/// Selector of `verifyProof` for a verifier with `num_inputs` public inputs.
+fn selector(num_inputs: usize) -> [u8; 4] {
+    let signature = format!(
+        "verifyProof(uint256[2],uint256[2][2],uint256[2],uint256[{}])",
+        num_inputs
+    );
+    let hash = Keccak256::digest(signature.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// ABI-encoded call of `verifyProof` with `proof` and `public_input`.
+pub fn calldata(proof: &Proof<Bn254>, public_input: &[Fr]) -> Vec<u8> {
+    let mut calldata = selector(public_input.len()).to_vec();
+    let points = g1_words(&proof.a)
+        .into_iter()
+        .chain(g2_words(&proof.b))
+        .chain(g1_words(&proof.c));
+    for coordinate in points {
+        calldata.extend(word(coordinate));
+    }
+    for input in public_input {
+        calldata.extend(word(*input));
+    }
+    calldata
+}
+
+#[cfg(all(test, feature = "xor"))]
+mod tests {
+    use ark_bn254::Fq2;
+    use ark_ec::{AffineCurve, PairingEngine, ProjectiveCurve};
+    use ark_ff::One;
+
+    use super::*;
+    use crate::{
+        environment::{rng, Curve, NonUniversalProvingSystem, SomeProvingSystem},
+        export::{solidity_calldata, VerifierFormat},
+        relations::{GetPublicInput, XorRelation},
+        serialization::serialize,
+    };
+
+    /// Reads a `uint256` constant from the contract source.
+    fn read_constant(contract: &str, name: &str) -> Fq {
+        let prefix = format!("uint256 constant {} = 0x", name);
+        let value = contract
+            .lines()
+            .find_map(|line| line.trim().strip_prefix(&prefix))
+            .unwrap_or_else(|| panic!("Missing constant {}", name));
+        Fq::from_be_bytes_mod_order(&hex::decode(value.trim_end_matches(';')).unwrap())
+    }
+
+    fn read_g1(contract: &str, name: &str) -> G1Affine {
+        let coordinate = |suffix: &str| read_constant(contract, &format!("{}_{}", name, suffix));
+        G1Affine::new(coordinate("X"), coordinate("Y"), false)
+    }
+
+    fn read_g2(contract: &str, name: &str) -> G2Affine {
+        let coordinate = |suffix: &str| read_constant(contract, &format!("{}_{}", name, suffix));
+        G2Affine::new(
+            Fq2::new(coordinate("X_RE"), coordinate("X_IM")),
+            Fq2::new(coordinate("Y_RE"), coordinate("Y_IM")),
+            false,
+        )
+    }
+
+    /// Evaluates the pairing equation of `verifyProof` from `contract` on `calldata`, just like
+    /// the precompiles would.
+    fn run_verifier(contract: &str, calldata: &[u8]) -> bool {
+        let words = calldata[4..].chunks(32).collect::<Vec<_>>();
+        let fq = |i: usize| Fq::from_be_bytes_mod_order(words[i]);
+
+        let a = G1Affine::new(fq(0), fq(1), false);
+        let b = G2Affine::new(Fq2::new(fq(3), fq(2)), Fq2::new(fq(5), fq(4)), false);
+        let c = G1Affine::new(fq(6), fq(7), false);
+
+        let mut vk_x = read_g1(contract, "IC_0").into_projective();
+        for (i, input) in words[8..].iter().enumerate() {
+            let input = Fr::from_be_bytes_mod_order(input);
+            vk_x += read_g1(contract, &format!("IC_{}", i + 1)).mul(input.into_repr());
+        }
+
+        let product = Bn254::pairing(-a, b)
+            * Bn254::pairing(read_g1(contract, "ALPHA"), read_g2(contract, "BETA"))
+            * Bn254::pairing(vk_x.into_affine(), read_g2(contract, "GAMMA"))
+            * Bn254::pairing(c, read_g2(contract, "DELTA"));
+        product.is_one()
+    }
+
+    #[test]
+    fn exported_verifier_accepts_house_snark_proof() {
+        let relation = XorRelation {
+            public_xoree: 2,
+            private_xoree: 3,
+            result: 1,
+        };
+        let system = NonUniversalProvingSystem::Groth16;
+        let keys = system.generate_keys::<Fr, _, _>(relation, &mut rng(None));
+        let proof = SomeProvingSystem::NonUniversal(system).prove::<Fr, _, _>(
+            relation,
+            keys.pk,
+            &mut rng(None),
+        );
+        let public_input: Vec<Fr> = relation.public_input();
+
+        let contract = VerifierFormat::Solidity.export(keys.vk, Curve::Bn254);
+        let mut calldata = solidity_calldata(proof, serialize(&public_input));
+        assert_eq!(calldata.len(), 4 + 32 * (8 + public_input.len()));
+        assert!(run_verifier(&contract, &calldata));
+
+        *calldata.last_mut().unwrap() ^= 1;
+        assert!(!run_verifier(&contract, &calldata));
+    }
+}
@@ -1,21 +1,39 @@
+pub mod envelope;
 mod environment;
+mod pairing_checks;
+mod plonk;
 mod relations;
 pub mod serialization;
+mod srs;
 
-pub use environment::{CircuitField, NonUniversalProvingSystem, RawKeys, SomeProvingSystem};
-#[cfg(feature = "deposit")]
-pub use relations::shielder::DepositRelation;
-#[cfg(feature = "withdraw")]
-pub use relations::shielder::WithdrawRelation;
-#[cfg(any(feature = "deposit", feature = "withdraw"))]
+pub use environment::{
+    CircuitField, Curve, CurveField, NonUniversalProvingSystem, RawKeys, SomeProvingSystem,
+    SrsParameters, UniversalProvingSystem,
+};
+#[cfg(any(
+    feature = "deposit",
+    feature = "join_split",
+    feature = "transfer",
+    feature = "withdraw"
+))]
 pub use relations::shielder::{
-    bytes_from_note, compute_note, note_from_bytes, MerklePath, MerkleRoot, Note, Nullifier,
-    TokenAmount, TokenId, Trapdoor,
+    bytes_from_note, compute_note, compute_nullifier, compute_owner_key, generate_spending_secret,
+    generate_transfer_keys, generate_trapdoor, note_from_bytes, receive_transferred_note,
+    transfer_note, MerklePath, MerkleRoot, Note, Nullifier, OwnerKey, SpendingSecret, TokenAmount,
+    TokenId, TransferKey, TransferSecret, TransferredNote, Trapdoor,
 };
-pub use relations::GetPublicInput;
+#[cfg(feature = "deposit")]
+pub use relations::shielder::{DepositRelation, DepositRelationArgs};
+#[cfg(feature = "join_split")]
+pub use relations::shielder::{JoinSplitRelation, JoinSplitRelationArgs};
+#[cfg(feature = "transfer")]
+pub use relations::shielder::{TransferRelation, TransferRelationArgs};
+#[cfg(feature = "withdraw")]
+pub use relations::shielder::{WithdrawRelation, WithdrawRelationArgs};
 #[cfg(feature = "linear")]
 pub use relations::LinearEqRelation;
-#[cfg(feature = "merkle_tree")]
-pub use relations::MerkleTreeRelation;
 #[cfg(feature = "xor")]
 pub use relations::XorRelation;
+pub use relations::{ConstraintStatistics, GetPublicInput, Relation, Witness, WitnessTemplate};
+#[cfg(feature = "merkle_tree")]
+pub use relations::{MerkleTreeLeaf, MerkleTreeRelation, MerkleTreeRelationArgs};
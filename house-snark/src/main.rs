@@ -1,24 +1,62 @@
 extern crate core;
 
+use anyhow::{anyhow, Result};
+use ark_serialize::CanonicalDeserialize;
 use clap::Parser;
 
 use crate::{
+    aggregation::{aggregate, generate_aggregation_srs, verify_aggregate},
+    batch::prove_batch,
+    ceremony::Ceremony,
     config::{
-        Cli, Command, GenerateKeysCmd, GenerateKeysFromSrsCmd, GenerateProofCmd, GenerateSrsCmd,
+        AggregateCmd, CeremonyCmd, CeremonyFileCmd, CeremonyInitCmd, CeremonyVerifyCmd, Cli,
+        Command, ExportCalldataCmd, ExportVerifierCmd, GenerateAggregationSrsCmd, GenerateKeysCmd,
+        GenerateKeysFromSrsCmd, GenerateProofCmd, GenerateSrsCmd, InspectCmd, InspectRelationCmd,
+        ProveBatchCmd, SrsCmd, SrsContributeCmd, SrsTrimCmd, SrsVerifyUpdateCmd, StripCmd,
+        VerifyAggregateCmd, VerifyCmd, WitnessTemplateCmd,
     },
-    environment::CircuitField,
+    envelope::{pack, unpack, ArtifactKind, Origin},
+    environment::{rng, CircuitField, Curve, NonUniversalProvingSystem, SrsParameters},
+    export::solidity_calldata,
     rains_of_castamere::kill_all_snarks,
-    relations::GetPublicInput,
+    relations::{GetPublicInput, Relation},
     serialization::{
-        read_proving_key, read_srs, save_keys, save_proving_artifacts, save_srs, serialize,
+        env_id, public_input_json, read_artifact, read_ceremony, read_proof, read_proving_key,
+        read_public_input, read_srs, read_srs_update_proof, read_verifying_key,
+        save_aggregate_proof, save_aggregation_srs, save_ceremony, save_keys,
+        save_proving_artifacts, save_public_input_json, save_srs, save_srs_update, serialize,
     },
 };
 
+/// Evaluates `$body` with `$field` being the scalar field of `$curve`.
+macro_rules! over_curve {
+    ($curve:expr, $field:ident => $body:expr) => {
+        match $curve {
+            Curve::Bls12_381 => {
+                type $field = ark_bls12_381::Fr;
+                $body
+            }
+            Curve::Bn254 => {
+                type $field = ark_bn254::Fr;
+                $body
+            }
+        }
+    };
+}
+
+mod aggregation;
+mod batch;
+mod ceremony;
 mod config;
+mod envelope;
 mod environment;
+mod export;
+mod pairing_checks;
+mod plonk;
 mod rains_of_castamere;
 mod relations;
 mod serialization;
+mod srs;
 
 fn setup_eyre() {
     if std::env::var("RUST_LIB_BACKTRACE").is_err() {
@@ -31,43 +69,366 @@ fn main() {
     setup_eyre();
     env_logger::init();
 
-    let cli: Cli = Cli::parse();
+    if let Err(e) = run(Cli::parse()) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1)
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let envelope = cli.envelope;
     match cli.command {
         Command::GenerateSrs(GenerateSrsCmd {
             system,
+            curve,
             num_constraints,
             num_variables,
             degree,
+            seed,
         }) => {
-            let srs = system.generate_srs(num_constraints, num_variables, degree);
-            save_srs(&srs, &system.id());
+            let srs = over_curve!(curve, F => system.generate_srs::<F, _>(
+                num_constraints,
+                num_variables,
+                degree,
+                &mut rng(seed)
+            ));
+            let origin = envelope.then(|| Origin::of_system(system.id(), curve));
+            save_srs(
+                &pack(origin.as_ref(), ArtifactKind::Srs, srs),
+                &env_id(&system.id(), curve),
+            );
         }
         Command::GenerateKeysFromSrs(GenerateKeysFromSrsCmd {
             relation,
             system,
+            curve,
             srs_file,
         }) => {
-            let srs = read_srs(srs_file);
-            let keys = system.generate_keys(relation.clone(), srs);
-            save_keys(&relation.id(), &system.id(), &keys.pk, &keys.vk);
+            let srs = read_srs(srs_file, &system.id(), curve)?;
+            let keys = over_curve!(curve, F => system.generate_keys::<F, _>(relation.clone(), srs));
+            let origin = envelope.then(|| Origin::of_relation(system.id(), curve, &relation));
+            let pk = pack(origin.as_ref(), ArtifactKind::ProvingKey, keys.pk);
+            let vk = pack(origin.as_ref(), ArtifactKind::VerifyingKey, keys.vk);
+            save_keys(&relation.id(), &env_id(&system.id(), curve), &pk, &vk);
         }
-        Command::GenerateKeys(GenerateKeysCmd { relation, system }) => {
-            let keys = system.generate_keys(relation.clone());
-            save_keys(&relation.id(), &system.id(), &keys.pk, &keys.vk);
+        Command::GenerateKeys(GenerateKeysCmd {
+            relation,
+            system,
+            curve,
+            seed,
+        }) => {
+            let keys = over_curve!(curve, F => system.generate_keys::<F, _, _>(
+                relation.clone(),
+                &mut rng(seed)
+            ));
+            let origin = envelope.then(|| Origin::of_relation(system.id(), curve, &relation));
+            let pk = pack(origin.as_ref(), ArtifactKind::ProvingKey, keys.pk);
+            let vk = pack(origin.as_ref(), ArtifactKind::VerifyingKey, keys.vk);
+            save_keys(&relation.id(), &env_id(&system.id(), curve), &pk, &vk);
         }
         Command::GenerateProof(GenerateProofCmd {
             relation,
             system,
+            curve,
             proving_key_file,
+            seed,
+            public_input_json: save_json,
+        }) => {
+            let proving_key = read_proving_key(proving_key_file, &system.id(), curve, &relation)?;
+            let (proof, public_input, json) = over_curve!(curve, F => (
+                system.prove::<F, _, _>(relation.clone(), proving_key, &mut rng(seed)),
+                serialize(&GetPublicInput::<F>::public_input(&relation)),
+                save_json.then(|| {
+                    public_input_json(&GetPublicInput::<F>::named_public_input(&relation))
+                }),
+            ));
+            let origin = envelope.then(|| Origin::of_relation(system.id(), curve, &relation));
+            let proof = pack(origin.as_ref(), ArtifactKind::Proof, proof);
+            let public_input = pack(origin.as_ref(), ArtifactKind::PublicInput, public_input);
+            save_proving_artifacts(
+                &relation.id(),
+                &env_id(&system.id(), curve),
+                &proof,
+                &public_input,
+            );
+            if let Some(json) = json {
+                save_public_input_json(&relation.id(), &env_id(&system.id(), curve), &json);
+            }
+        }
+        Command::ProveBatch(ProveBatchCmd { manifest }) => {
+            let summary = prove_batch(manifest, envelope);
+            println!("{}", summary);
+            if !summary.all_succeeded() {
+                std::process::exit(1)
+            }
+        }
+        Command::Verify(VerifyCmd {
+            system,
+            curve,
+            verifying_key_file,
+            proof_file,
+            public_input_file,
+        }) => {
+            let vk = read_verifying_key(verifying_key_file, &system.id(), curve)?;
+            let proof = read_proof(proof_file, &system.id(), curve)?;
+            let public_input = read_public_input(public_input_file, &system.id(), curve)?;
+            match over_curve!(curve, F => system.verify::<F>(vk, proof, public_input)) {
+                Ok(true) => println!("Proof is correct"),
+                Ok(false) => {
+                    eprintln!("Proof is incorrect");
+                    std::process::exit(1)
+                }
+                Err(e) => {
+                    eprintln!("Cannot verify proof: {}", e);
+                    std::process::exit(1)
+                }
+            }
+        }
+        Command::GenerateAggregationSrs(GenerateAggregationSrsCmd {
+            curve,
+            max_proofs,
+            seed,
+        }) => {
+            let groth16_id = NonUniversalProvingSystem::Groth16.id();
+            let srs = over_curve!(curve, F => generate_aggregation_srs::<F, _>(
+                max_proofs,
+                &mut rng(seed)
+            ));
+            let origin = envelope.then(|| Origin::of_system(groth16_id.clone(), curve));
+            save_aggregation_srs(
+                &pack(origin.as_ref(), ArtifactKind::Srs, srs),
+                &env_id(&groth16_id, curve),
+            );
+        }
+        Command::Aggregate(AggregateCmd {
+            curve,
+            srs_file,
+            verifying_key_file,
+            proof_files,
+            public_input_files,
+        }) => {
+            let groth16_id = NonUniversalProvingSystem::Groth16.id();
+            let srs = read_srs(srs_file, &groth16_id, curve)?;
+            let vk = read_verifying_key(verifying_key_file, &groth16_id, curve)?;
+            let proofs = proof_files
+                .into_iter()
+                .map(|file| read_proof(file, &groth16_id, curve))
+                .collect::<Result<_>>()?;
+            let public_inputs = public_input_files
+                .into_iter()
+                .map(|file| read_public_input(file, &groth16_id, curve))
+                .collect::<Result<_>>()?;
+            let proof = over_curve!(curve, F => aggregate::<F>(srs, vk, proofs, public_inputs));
+            let origin = envelope.then(|| Origin::of_system(groth16_id.clone(), curve));
+            save_aggregate_proof(
+                &pack(origin.as_ref(), ArtifactKind::Proof, proof),
+                &env_id(&groth16_id, curve),
+            );
+        }
+        Command::VerifyAggregate(VerifyAggregateCmd {
+            curve,
+            srs_file,
+            verifying_key_file,
+            aggregate_proof_file,
+            public_input_files,
+        }) => {
+            let groth16_id = NonUniversalProvingSystem::Groth16.id();
+            let srs = read_srs(srs_file, &groth16_id, curve)?;
+            let vk = read_verifying_key(verifying_key_file, &groth16_id, curve)?;
+            let proof = read_proof(aggregate_proof_file, &groth16_id, curve)?;
+            let public_inputs = public_input_files
+                .into_iter()
+                .map(|file| read_public_input(file, &groth16_id, curve))
+                .collect::<Result<_>>()?;
+            if over_curve!(curve, F => verify_aggregate::<F>(srs, vk, proof, public_inputs)) {
+                println!("Aggregate proof is correct")
+            } else {
+                eprintln!("Aggregate proof is incorrect");
+                std::process::exit(1)
+            }
+        }
+        Command::Inspect(InspectCmd { artifact_file }) => {
+            let bytes = read_artifact(artifact_file)?;
+            match unpack(&bytes)? {
+                (Some(header), _) => println!("{}", header),
+                (None, raw) => println!("No envelope (raw artifact of {} bytes)", raw.len()),
+            }
+        }
+        Command::InspectRelation(InspectRelationCmd { relation, system }) => {
+            println!("{}", relation.constraint_statistics());
+            let SrsParameters {
+                num_constraints,
+                num_variables,
+                degree,
+            } = system.minimal_srs_parameters::<CircuitField, _>(relation);
+            println!(
+                "Minimal {} SRS: --num-constraints {} --num-variables {} --degree {}",
+                system.id(),
+                num_constraints,
+                num_variables,
+                degree
+            );
+        }
+        Command::WitnessTemplate(WitnessTemplateCmd { relation, format }) => {
+            println!("{}", Relation::witness_template(&relation, format));
+        }
+        Command::Strip(StripCmd {
+            artifact_file,
+            output_file,
+        }) => {
+            let bytes = read_artifact(artifact_file.clone())?;
+            let (_, raw) = unpack(&bytes)?;
+            let output_file =
+                output_file.unwrap_or_else(|| artifact_file.with_extension("raw.bytes"));
+            std::fs::write(output_file, raw).expect("Failed to save raw artifact");
+        }
+        Command::ExportVerifier(ExportVerifierCmd {
+            format,
+            curve,
+            verifying_key_file,
+            output_file,
+        }) => {
+            let curve = curve.unwrap_or_else(|| format.default_curve());
+            let groth16_id = NonUniversalProvingSystem::Groth16.id();
+            let vk = read_verifying_key(verifying_key_file.clone(), &groth16_id, curve)?;
+            let output_file = output_file
+                .unwrap_or_else(|| verifying_key_file.with_extension(format.extension()));
+            std::fs::write(output_file, format.export(vk, curve)).expect("Failed to save verifier");
+        }
+        Command::ExportCalldata(ExportCalldataCmd {
+            proof_file,
+            public_input_file,
         }) => {
-            let proving_key = read_proving_key(proving_key_file);
-            let proof = system.prove(relation.clone(), proving_key);
-            let public_input = serialize(&relation.public_input());
-            save_proving_artifacts(&relation.id(), &system.id(), &proof, &public_input);
+            let groth16_id = NonUniversalProvingSystem::Groth16.id();
+            let proof = read_proof(proof_file, &groth16_id, Curve::Bn254)?;
+            let public_input = read_public_input(public_input_file, &groth16_id, Curve::Bn254)?;
+            println!("0x{}", hex::encode(solidity_calldata(proof, public_input)));
+        }
+        Command::Srs(SrsCmd::Contribute(SrsContributeCmd { system, srs_file })) => {
+            // SRS updates are supported only over BLS12-381.
+            let curve = Curve::Bls12_381;
+            let srs = read_srs(srs_file, &system.id(), curve)?;
+            let update = system.update_srs(srs, &mut rng(None));
+            let origin = envelope.then(|| Origin::of_system(system.id(), curve));
+            let path = save_srs_update(
+                &pack(origin.as_ref(), ArtifactKind::Srs, update.srs),
+                &update.proof,
+                &env_id(&system.id(), curve),
+            );
+            println!("Updated SRS saved to {:?}", path);
+        }
+        Command::Srs(SrsCmd::VerifyUpdate(SrsVerifyUpdateCmd {
+            system,
+            old_srs_file,
+            new_srs_file,
+            update_proof_file,
+        })) => {
+            let old_srs = read_srs(old_srs_file, &system.id(), Curve::Bls12_381)?;
+            let new_srs = read_srs(new_srs_file, &system.id(), Curve::Bls12_381)?;
+            let proof = read_srs_update_proof(update_proof_file)?;
+            if system.verify_srs_update(old_srs, new_srs, proof) {
+                println!("SRS update is correct")
+            } else {
+                eprintln!("SRS update is incorrect");
+                std::process::exit(1)
+            }
+        }
+        Command::Srs(SrsCmd::Trim(SrsTrimCmd {
+            relation,
+            system,
+            curve,
+            srs_file,
+        })) => {
+            let srs = read_srs(srs_file, &system.id(), curve)?;
+            let trimmed = over_curve!(curve, F => system.trim_srs::<F, _>(relation.clone(), srs));
+            let origin = envelope.then(|| Origin::of_relation(system.id(), curve, &relation));
+            save_srs(
+                &pack(origin.as_ref(), ArtifactKind::Srs, trimmed),
+                &format!("{}.{}", relation.id(), env_id(&system.id(), curve)),
+            );
+        }
+        Command::Ceremony(cmd) => {
+            // The ceremony produces Groth16 keys over BLS12-381.
+            let groth16_env_id = env_id(&NonUniversalProvingSystem::Groth16.id(), Curve::Bls12_381);
+            match cmd {
+                CeremonyCmd::Init(CeremonyInitCmd { relation }) => {
+                    let ceremony = Ceremony::init(relation.id(), relation)?;
+                    save_ceremony(
+                        ceremony.relation_id(),
+                        &groth16_env_id,
+                        &serialize(&ceremony),
+                    );
+                }
+                CeremonyCmd::Contribute(CeremonyFileCmd { ceremony_file }) => {
+                    let mut ceremony = read_ceremony_state(ceremony_file)?;
+                    let hash = ceremony.contribute(&mut rng(None));
+                    save_ceremony(
+                        ceremony.relation_id(),
+                        &groth16_env_id,
+                        &serialize(&ceremony),
+                    );
+                    println!(
+                        "Contribution #{} accepted, transcript hash: 0x{}",
+                        ceremony.num_contributions(),
+                        hex::encode(hash)
+                    );
+                }
+                CeremonyCmd::Prepare(CeremonyVerifyCmd {
+                    ceremony_file,
+                    relation,
+                }) => {
+                    let mut ceremony = read_ceremony_state(ceremony_file)?;
+                    ceremony.prepare(&relation.id(), relation)?;
+                    save_ceremony(
+                        ceremony.relation_id(),
+                        &groth16_env_id,
+                        &serialize(&ceremony),
+                    );
+                    println!(
+                        "Ceremony prepared, transcript hash: 0x{}",
+                        hex::encode(ceremony.transcript_hash())
+                    );
+                }
+                CeremonyCmd::VerifyTranscript(CeremonyVerifyCmd {
+                    ceremony_file,
+                    relation,
+                }) => {
+                    let ceremony = read_ceremony_state(ceremony_file)?;
+                    match ceremony.verify_transcript(&relation.id(), relation) {
+                        Ok(_) => println!(
+                            "Transcript with {} contributions is valid, hash: 0x{}",
+                            ceremony.num_contributions(),
+                            hex::encode(ceremony.transcript_hash())
+                        ),
+                        Err(e) => {
+                            eprintln!("Transcript is invalid: {}", e);
+                            std::process::exit(1)
+                        }
+                    }
+                }
+                CeremonyCmd::Export(CeremonyVerifyCmd {
+                    ceremony_file,
+                    relation,
+                }) => {
+                    let ceremony = read_ceremony_state(ceremony_file)?;
+                    if let Err(e) = ceremony.verify_transcript(&relation.id(), relation) {
+                        eprintln!("Transcript is invalid: {}", e);
+                        std::process::exit(1)
+                    }
+                    let keys = ceremony.export_keys()?;
+                    save_keys(ceremony.relation_id(), &groth16_env_id, &keys.pk, &keys.vk);
+                }
+            }
         }
         Command::RedWedding => match kill_all_snarks() {
             Ok(_) => println!("Cleaning succeeded"),
             Err(e) => eprintln!("Cleaning failed: {:?}", e),
         },
     }
+    Ok(())
+}
+
+fn read_ceremony_state(ceremony_file: std::path::PathBuf) -> Result<Ceremony> {
+    Ceremony::deserialize(&*read_ceremony(ceremony_file)?)
+        .map_err(|e| anyhow!("Failed to deserialize ceremony: {}", e))
 }
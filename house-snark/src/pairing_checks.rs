@@ -0,0 +1,129 @@
+//! Pairing-based checks shared by the setup ceremonies.
+
+use ark_bls12_381::{Bls12_381, Fq2, G1Affine, G1Projective, G2Affine};
+use ark_ec::{msm::VariableBaseMSM, AffineCurve, PairingEngine, ProjectiveCurve};
+use ark_ff::{PrimeField, UniformRand, Zero};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write};
+use ark_std::rand::{rngs::StdRng, CryptoRng, Rng, RngCore, SeedableRng};
+use blake2::{Blake2s, Digest};
+
+use crate::{environment::CircuitField, serialization::serialize};
+
+/// Proof of knowledge of a secret scalar `x`, bound to a context (e.g. transcript) hash.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KnowledgeProof {
+    /// Random point in G1.
+    s: G1Affine,
+    /// `s` multiplied by `x`.
+    s_x: G1Affine,
+    /// Point derived from the context hash, `s` and `s_x`, multiplied by `x`.
+    r_x: G2Affine,
+}
+
+impl KnowledgeProof {
+    pub fn prove<R: RngCore + CryptoRng>(
+        x: CircuitField,
+        context_hash: &[u8; 32],
+        rng: &mut R,
+    ) -> Self {
+        let s = G1Projective::rand(rng).into_affine();
+        let s_x = s.mul(x).into_affine();
+        let r_x = hash_to_g2(context_hash, &s, &s_x).mul(x).into_affine();
+        KnowledgeProof { s, s_x, r_x }
+    }
+
+    /// Checks the proof. On success, returns `(r, r * x)`, so that the caller can check whether
+    /// some other pair of points is related by `x` as well (see `same_ratio`).
+    pub fn verify(&self, context_hash: &[u8; 32]) -> Option<(G2Affine, G2Affine)> {
+        if self.s.is_zero() || self.s_x.is_zero() {
+            return None;
+        }
+        let r = hash_to_g2(context_hash, &self.s, &self.s_x);
+        same_ratio((self.s, self.s_x), (r, self.r_x)).then_some((r, self.r_x))
+    }
+}
+
+/// Checks whether `g1.1 / g1.0 == g2.1 / g2.0` (in the exponent).
+pub fn same_ratio(g1: (G1Affine, G1Affine), g2: (G2Affine, G2Affine)) -> bool {
+    Bls12_381::pairing(g1.0, g2.1) == Bls12_381::pairing(g1.1, g2.0)
+}
+
+/// Combines both point vectors with the same random coefficients.
+///
+/// If `second[i] == first[i] * x` for some `x` and all `i`, then the combinations are related by
+/// `x` as well. Otherwise, with overwhelming probability, they are not.
+pub fn random_combinations<G: AffineCurve<ScalarField = CircuitField>>(
+    first: &[G],
+    second: &[G],
+) -> (G, G) {
+    let mut rng = crate::environment::rng(None);
+    let coefficients = (0..first.len())
+        .map(|_| CircuitField::rand(&mut rng).into_repr())
+        .collect::<Vec<_>>();
+    (
+        VariableBaseMSM::multi_scalar_mul(first, &coefficients).into_affine(),
+        VariableBaseMSM::multi_scalar_mul(second, &coefficients).into_affine(),
+    )
+}
+
+/// Derives a point in G2 with unknown discrete logarithm from the context hash and the pair.
+fn hash_to_g2(context_hash: &[u8; 32], s: &G1Affine, s_x: &G1Affine) -> G2Affine {
+    let mut hasher = Blake2s::new();
+    hasher.update(context_hash);
+    hasher.update(serialize(s));
+    hasher.update(serialize(s_x));
+    let mut rng = StdRng::from_seed(hasher.finalize().into());
+
+    // Sampling a scalar and multiplying the generator would reveal the discrete logarithm, so we
+    // look for a random point on the curve instead.
+    loop {
+        let x = Fq2::rand(&mut rng);
+        let greatest = rng.gen::<bool>();
+        if let Some(point) = G2Affine::get_point_from_x(x, greatest) {
+            let point = point.scale_by_cofactor();
+            if !point.is_zero() {
+                return point.into_affine();
+            }
+        }
+    }
+}
+
+impl CanonicalSerialize for KnowledgeProof {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.s.serialize(&mut writer)?;
+        self.s_x.serialize(&mut writer)?;
+        self.r_x.serialize(&mut writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.s.serialized_size() + self.s_x.serialized_size() + self.r_x.serialized_size()
+    }
+}
+
+impl CanonicalDeserialize for KnowledgeProof {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        Ok(KnowledgeProof {
+            s: G1Affine::deserialize(&mut reader)?,
+            s_x: G1Affine::deserialize(&mut reader)?,
+            r_x: G2Affine::deserialize(&mut reader)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::rng;
+
+    #[test]
+    fn knowledge_proof_is_bound_to_context() {
+        let x = CircuitField::rand(&mut rng(None));
+        let proof = KnowledgeProof::prove(x, &[1; 32], &mut rng(None));
+
+        let (r, r_x) = proof.verify(&[1; 32]).expect("Proof should be valid");
+        let g = G1Affine::prime_subgroup_generator();
+        assert!(same_ratio((g, g.mul(x).into_affine()), (r, r_x)));
+
+        assert!(proof.verify(&[2; 32]).is_none());
+    }
+}
@@ -0,0 +1,474 @@
+//! Translation of R1CS into PLONK gates.
+//!
+//! Every R1CS constraint `<A, z> * <B, z> = <C, z>` becomes a multiplication gate preceded by
+//! addition gates that sum up the linear combinations. Values of R1CS variables and of all the
+//! partial sums are carried by wires; a wire used in many places is glued together by the
+//! permutation argument.
+
+use std::iter::repeat;
+
+use ark_ff::{FftField, Field, One, PrimeField, Zero};
+use ark_poly::{EvaluationDomain, Evaluations};
+use ark_relations::r1cs::{
+    ConstraintMatrices, ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, SynthesisMode,
+};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write};
+
+use super::{Domain, Poly};
+
+/// Index of a wire.
+///
+/// The first wires carry R1CS variables (in the order of the full R1CS assignment), the remaining
+/// ones carry partial sums of linear combinations.
+pub type Wire = usize;
+
+/// Wire carrying the constant `1`. Gate inputs that are not used are connected to it as well.
+pub const ONE: Wire = 0;
+
+/// Single gate: `q_m * a * b + q_l * a + q_r * b + q_o * c + q_c + public_input = 0`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gate<F: PrimeField> {
+    q_m: F,
+    q_l: F,
+    q_r: F,
+    q_o: F,
+    q_c: F,
+    a: Wire,
+    b: Wire,
+    c: Wire,
+}
+
+impl<F: PrimeField> Gate<F> {
+    fn unused() -> Self {
+        Gate {
+            q_m: F::zero(),
+            q_l: F::zero(),
+            q_r: F::zero(),
+            q_o: F::zero(),
+            q_c: F::zero(),
+            a: ONE,
+            b: ONE,
+            c: ONE,
+        }
+    }
+}
+
+/// Circuit expressed with gates.
+///
+/// The first `num_public` gates bind public inputs (the `i`-th one to the `i`-th row of the
+/// public input polynomial), the next one forces `ONE` to carry `1` and then go the gates
+/// of the consecutive R1CS constraints.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Circuit<F: PrimeField> {
+    /// Number of R1CS variables (including the constant `1`).
+    num_variables: usize,
+    /// Number of public inputs (excluding the constant `1`).
+    num_public: usize,
+    num_wires: usize,
+    gates: Vec<Gate<F>>,
+}
+
+/// Selector and permutation polynomials of a circuit.
+pub struct Preprocessed<F: PrimeField> {
+    /// `q_m`, `q_l`, `q_r`, `q_o` and `q_c`.
+    pub selectors: Vec<Poly<F>>,
+    /// Permutation polynomials of the `a`, `b` and `c` columns.
+    pub sigmas: Vec<Poly<F>>,
+    /// Values of `sigmas` over the domain.
+    pub sigma_evaluations: Vec<Vec<F>>,
+}
+
+impl<F: PrimeField> Circuit<F> {
+    /// Translates `circuit` into gates.
+    pub fn compile<C: ConstraintSynthesizer<F>>(circuit: C) -> Self {
+        let cs = synthesize(circuit, SynthesisMode::Setup);
+        Self::from_matrices(
+            &cs.to_matrices()
+                .expect("Failed to build constraint matrices"),
+        )
+    }
+
+    /// Translates `circuit` into gates and computes values of all the wires.
+    pub fn compile_with_wire_values<C: ConstraintSynthesizer<F>>(circuit: C) -> (Self, Vec<F>) {
+        let cs = synthesize(
+            circuit,
+            SynthesisMode::Prove {
+                construct_matrices: true,
+            },
+        );
+        assert!(
+            cs.is_satisfied().expect("Failed to check constraints"),
+            "Circuit is not satisfied"
+        );
+        let compiled = Self::from_matrices(
+            &cs.to_matrices()
+                .expect("Failed to build constraint matrices"),
+        );
+
+        let assignment = {
+            let cs = cs.borrow().expect("Constraint system is still in use");
+            [cs.instance_assignment.as_slice(), &cs.witness_assignment].concat()
+        };
+        let wire_values = compiled.wire_values(assignment);
+        (compiled, wire_values)
+    }
+
+    /// R1CS size of `circuit`: number of constraints, number of variables and the largest number
+    /// of non-zero entries in a constraint matrix.
+    pub fn r1cs_size<C: ConstraintSynthesizer<F>>(circuit: C) -> (usize, usize, usize) {
+        let matrices = synthesize(circuit, SynthesisMode::Setup)
+            .to_matrices()
+            .expect("Failed to build constraint matrices");
+        (
+            matrices.num_constraints,
+            matrices.num_instance_variables + matrices.num_witness_variables,
+            matrices
+                .a_num_non_zero
+                .max(matrices.b_num_non_zero)
+                .max(matrices.c_num_non_zero),
+        )
+    }
+
+    /// Upper bound on the number of gates of a circuit with the given R1CS size.
+    pub fn max_num_gates(
+        num_constraints: usize,
+        num_variables: usize,
+        num_non_zero: usize,
+    ) -> usize {
+        // Binding public inputs and `ONE` takes at most one gate per variable. Each constraint
+        // takes a multiplication gate and, for every linear combination, at most one addition gate
+        // per term (or a single one for an empty combination).
+        num_variables + 4 * num_constraints + 3 * num_non_zero
+    }
+
+    pub fn num_public(&self) -> usize {
+        self.num_public
+    }
+
+    /// Smallest domain with room for all the gates.
+    pub fn domain(&self) -> Domain<F> {
+        Domain::new(self.gates.len()).expect("Circuit is too large")
+    }
+
+    /// Computes selector and permutation polynomials over `domain`.
+    pub fn preprocess(&self, domain: Domain<F>) -> Preprocessed<F> {
+        let n = domain.size();
+        let selector_column = |selector: fn(&Gate<F>) -> F| {
+            self.gates
+                .iter()
+                .map(selector)
+                .chain(repeat(F::zero()))
+                .take(n)
+                .collect::<Vec<_>>()
+        };
+        let selectors = [
+            selector_column(|gate| gate.q_m),
+            selector_column(|gate| gate.q_l),
+            selector_column(|gate| gate.q_r),
+            selector_column(|gate| gate.q_o),
+            selector_column(|gate| gate.q_c),
+        ]
+        .into_iter()
+        .map(|column| interpolate(column, domain))
+        .collect();
+
+        let sigma_evaluations = self.permutation(domain);
+        let sigmas = sigma_evaluations
+            .iter()
+            .map(|column| interpolate(column.clone(), domain))
+            .collect();
+
+        Preprocessed {
+            selectors,
+            sigmas,
+            sigma_evaluations,
+        }
+    }
+
+    /// Wires connected to the `a`, `b` and `c` inputs of the consecutive gates, padded to `n`.
+    pub fn wire_columns(&self, n: usize) -> Vec<Vec<Wire>> {
+        let column = |wire: fn(&Gate<F>) -> Wire| {
+            self.gates
+                .iter()
+                .map(wire)
+                .chain(repeat(ONE))
+                .take(n)
+                .collect::<Vec<_>>()
+        };
+        vec![
+            column(|gate| gate.a),
+            column(|gate| gate.b),
+            column(|gate| gate.c),
+        ]
+    }
+
+    fn from_matrices(matrices: &ConstraintMatrices<F>) -> Self {
+        let num_variables = matrices.num_instance_variables + matrices.num_witness_variables;
+        let mut circuit = Circuit {
+            num_variables,
+            num_public: matrices.num_instance_variables - 1,
+            num_wires: num_variables,
+            gates: vec![],
+        };
+
+        for input in 1..=circuit.num_public {
+            circuit.gates.push(Gate {
+                q_l: F::one(),
+                a: input,
+                ..Gate::unused()
+            });
+        }
+        circuit.gates.push(Gate {
+            q_l: F::one(),
+            q_c: -F::one(),
+            a: ONE,
+            ..Gate::unused()
+        });
+
+        for ((a, b), c) in matrices.a.iter().zip(&matrices.b).zip(&matrices.c) {
+            let a = circuit.sum(a);
+            let b = circuit.sum(b);
+            let c = circuit.sum(c);
+            circuit.gates.push(Gate {
+                q_m: F::one(),
+                q_o: -F::one(),
+                a,
+                b,
+                c,
+                ..Gate::unused()
+            });
+        }
+        circuit
+    }
+
+    /// Adds gates computing the linear combination `terms`. Returns the wire carrying the result.
+    fn sum(&mut self, terms: &[(F, usize)]) -> Wire {
+        let zero = F::zero();
+        let (mut result, rest) = match terms {
+            [] => return self.addition(zero, ONE, zero, ONE),
+            [(coefficient, variable)] if coefficient.is_one() => return *variable,
+            [(coefficient, variable)] => return self.addition(*coefficient, *variable, zero, ONE),
+            [(c_1, v_1), (c_2, v_2), rest @ ..] => (self.addition(*c_1, *v_1, *c_2, *v_2), rest),
+        };
+        for (coefficient, variable) in rest {
+            result = self.addition(F::one(), result, *coefficient, *variable);
+        }
+        result
+    }
+
+    /// Adds gate computing `q_l * a + q_r * b` on a new wire.
+    fn addition(&mut self, q_l: F, a: Wire, q_r: F, b: Wire) -> Wire {
+        let c = self.num_wires;
+        self.num_wires += 1;
+        self.gates.push(Gate {
+            q_l,
+            q_r,
+            q_o: -F::one(),
+            a,
+            b,
+            c,
+            ..Gate::unused()
+        });
+        c
+    }
+
+    /// Extends the R1CS `assignment` with values of the wires carrying partial sums.
+    fn wire_values(&self, assignment: Vec<F>) -> Vec<F> {
+        assert_eq!(
+            assignment.len(),
+            self.num_variables,
+            "Assignment doesn't match the circuit"
+        );
+        let mut values = assignment;
+        // New wires are introduced only by addition gates, in the order of gates.
+        for gate in &self.gates {
+            if gate.c == values.len() {
+                values.push(gate.q_l * values[gate.a] + gate.q_r * values[gate.b]);
+            }
+        }
+        values
+    }
+
+    /// For each position `(column, row)`, returns the label of the next position connected to the
+    /// same wire. Position `(j, i)` is labelled with `k_j * omega^i` (see `coset_shifts`).
+    fn permutation(&self, domain: Domain<F>) -> Vec<Vec<F>> {
+        let n = domain.size();
+        let elements = domain.elements().collect::<Vec<_>>();
+        let shifts = coset_shifts::<F>();
+        let label = |(column, row): (usize, usize)| shifts[column] * elements[row];
+
+        let mut positions = vec![vec![]; self.num_wires];
+        for (column, wires) in self.wire_columns(n).iter().enumerate() {
+            for (row, wire) in wires.iter().enumerate() {
+                positions[*wire].push((column, row));
+            }
+        }
+
+        let mut sigmas = vec![vec![F::zero(); n]; 3];
+        for cycle in positions {
+            for (i, (column, row)) in cycle.iter().enumerate() {
+                sigmas[*column][*row] = label(cycle[(i + 1) % cycle.len()]);
+            }
+        }
+        sigmas
+    }
+}
+
+/// Shifts of the domain labelling positions in the `a`, `b` and `c` columns. They lie in
+/// different cosets of any domain, since the multiplicative generator has a huge order.
+pub fn coset_shifts<F: PrimeField>() -> [F; 3] {
+    let generator = F::multiplicative_generator();
+    [F::one(), generator, generator.square()]
+}
+
+/// Polynomial of degree less than `domain.size()` taking `evaluations` over `domain`.
+pub fn interpolate<F: PrimeField>(evaluations: Vec<F>, domain: Domain<F>) -> Poly<F> {
+    Evaluations::from_vec_and_domain(evaluations, domain).interpolate()
+}
+
+fn synthesize<F: PrimeField, C: ConstraintSynthesizer<F>>(
+    circuit: C,
+    mode: SynthesisMode,
+) -> ConstraintSystemRef<F> {
+    let cs = ConstraintSystem::new_ref();
+    cs.set_mode(mode);
+    circuit
+        .generate_constraints(cs.clone())
+        .expect("Failed to synthesize circuit");
+    cs.finalize();
+    cs
+}
+
+impl<F: PrimeField> CanonicalSerialize for Gate<F> {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.q_m.serialize(&mut writer)?;
+        self.q_l.serialize(&mut writer)?;
+        self.q_r.serialize(&mut writer)?;
+        self.q_o.serialize(&mut writer)?;
+        self.q_c.serialize(&mut writer)?;
+        self.a.serialize(&mut writer)?;
+        self.b.serialize(&mut writer)?;
+        self.c.serialize(&mut writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        5 * self.q_m.serialized_size() + 3 * self.a.serialized_size()
+    }
+}
+
+impl<F: PrimeField> CanonicalDeserialize for Gate<F> {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        Ok(Gate {
+            q_m: F::deserialize(&mut reader)?,
+            q_l: F::deserialize(&mut reader)?,
+            q_r: F::deserialize(&mut reader)?,
+            q_o: F::deserialize(&mut reader)?,
+            q_c: F::deserialize(&mut reader)?,
+            a: Wire::deserialize(&mut reader)?,
+            b: Wire::deserialize(&mut reader)?,
+            c: Wire::deserialize(&mut reader)?,
+        })
+    }
+}
+
+impl<F: PrimeField> CanonicalSerialize for Circuit<F> {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.num_variables.serialize(&mut writer)?;
+        self.num_public.serialize(&mut writer)?;
+        self.num_wires.serialize(&mut writer)?;
+        self.gates.serialize(&mut writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.num_variables.serialized_size()
+            + self.num_public.serialized_size()
+            + self.num_wires.serialized_size()
+            + self.gates.serialized_size()
+    }
+}
+
+impl<F: PrimeField> CanonicalDeserialize for Circuit<F> {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        let circuit = Circuit {
+            num_variables: usize::deserialize(&mut reader)?,
+            num_public: usize::deserialize(&mut reader)?,
+            num_wires: usize::deserialize(&mut reader)?,
+            gates: Vec::<Gate<F>>::deserialize(&mut reader)?,
+        };
+        let wires_in_range = circuit.gates.iter().all(|gate| {
+            [gate.a, gate.b, gate.c]
+                .iter()
+                .all(|w| *w < circuit.num_wires)
+        });
+        if circuit.num_public >= circuit.num_variables || !wires_in_range {
+            return Err(SerializationError::InvalidData);
+        }
+        Ok(circuit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_relations::r1cs::{LinearCombination, SynthesisError, Variable};
+
+    use super::*;
+    use crate::environment::CircuitField;
+
+    /// `x * (2w + 3) = y` with public `x` and `y`.
+    #[derive(Clone)]
+    struct AffineProduct;
+
+    impl ConstraintSynthesizer<CircuitField> for AffineProduct {
+        fn generate_constraints(
+            self,
+            cs: ConstraintSystemRef<CircuitField>,
+        ) -> Result<(), SynthesisError> {
+            let x = cs.new_input_variable(|| Ok(CircuitField::from(5u8)))?;
+            let y = cs.new_input_variable(|| Ok(CircuitField::from(35u8)))?;
+            let w = cs.new_witness_variable(|| Ok(CircuitField::from(2u8)))?;
+            let sum = LinearCombination::from((CircuitField::from(2u8), w))
+                + (CircuitField::from(3u8), Variable::One);
+            cs.enforce_constraint(x.into(), sum, y.into())
+        }
+    }
+
+    #[test]
+    fn wire_values_satisfy_gates() {
+        let (circuit, values) = Circuit::compile_with_wire_values(AffineProduct);
+        assert_eq!(circuit, Circuit::compile(AffineProduct));
+        assert_eq!(circuit.num_public(), 2);
+        assert_eq!(values.len(), circuit.num_wires);
+
+        for (i, gate) in circuit.gates.iter().enumerate() {
+            let (a, b, c) = (values[gate.a], values[gate.b], values[gate.c]);
+            let public_input = if i < circuit.num_public {
+                -values[i + 1]
+            } else {
+                CircuitField::zero()
+            };
+            let result = gate.q_m * a * b + gate.q_l * a + gate.q_r * b + gate.q_o * c + gate.q_c;
+            assert!((result + public_input).is_zero(), "Gate {} is violated", i);
+        }
+    }
+
+    #[test]
+    fn permutation_consists_of_wire_cycles() {
+        let circuit = Circuit::compile(AffineProduct);
+        let domain = circuit.domain();
+        let n = domain.size();
+        let shifts = coset_shifts::<CircuitField>();
+        let elements = domain.elements().collect::<Vec<_>>();
+        let columns = circuit.wire_columns(n);
+
+        let sigmas = circuit.permutation(domain);
+        for column in 0..3 {
+            for row in 0..n {
+                let next = (0..3)
+                    .flat_map(|j| (0..n).map(move |i| (j, i)))
+                    .find(|(j, i)| shifts[*j] * elements[*i] == sigmas[column][row])
+                    .expect("Label should point at some position");
+                assert_eq!(columns[column][row], columns[next.0][next.1]);
+            }
+        }
+    }
+}
@@ -0,0 +1,657 @@
+//! PLONK-style universal proving system with KZG commitments.
+//!
+//! Relations are translated from R1CS into PLONK gates (see `circuit`). Proving follows the
+//! original PLONK protocol (blinded wire and permutation polynomials, quotient polynomial), but
+//! trades proof size for simplicity: the quotient polynomial is committed as a whole and the
+//! verifier gets evaluations of all the committed polynomials instead of using linearization.
+//!
+//! The SRS has exactly the same format as the Marlin one, so it can be updated and trimmed in the
+//! same way (see `srs`).
+
+mod circuit;
+
+use std::marker::PhantomData;
+
+use ark_ec::{msm::VariableBaseMSM, AffineCurve, PairingEngine, ProjectiveCurve};
+use ark_ff::{batch_inversion, Field, One, PrimeField, UniformRand, Zero};
+use ark_poly::{
+    univariate::DensePolynomial, EvaluationDomain, Polynomial, Radix2EvaluationDomain, UVPolynomial,
+};
+use ark_poly_commit::{marlin_pc::MarlinKZG10, PolynomialCommitment};
+use ark_relations::r1cs::ConstraintSynthesizer;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write};
+use ark_std::rand::{CryptoRng, RngCore};
+use blake2::{Blake2s, Digest};
+use circuit::{coset_shifts, interpolate, Circuit};
+
+use crate::{serialization::serialize, srs::KzgSrs};
+
+type Domain<F> = Radix2EvaluationDomain<F>;
+type Poly<F> = DensePolynomial<F>;
+
+/// Number of polynomials opened at the evaluation challenge: `a`, `b`, `c`, `z`, `t`, five
+/// selectors and three permutation polynomials.
+const NUM_OPENINGS: usize = 13;
+
+/// The proving system (with API mirroring `ark_marlin::Marlin`).
+pub struct Plonk<E: PairingEngine>(PhantomData<E>);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VerifyingKey<E: PairingEngine> {
+    domain_size: usize,
+    num_public: usize,
+    /// Commitments to `q_m`, `q_l`, `q_r`, `q_o` and `q_c`.
+    selectors: Vec<E::G1Affine>,
+    /// Commitments to the permutation polynomials.
+    sigmas: Vec<E::G1Affine>,
+    g: E::G1Affine,
+    h: E::G2Affine,
+    beta_h: E::G2Affine,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProvingKey<E: PairingEngine> {
+    vk: VerifyingKey<E>,
+    circuit: Circuit<E::Fr>,
+    /// Powers of `beta` in G1, enough to commit to the quotient polynomial.
+    powers_of_g: Vec<E::G1Affine>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Proof<E: PairingEngine> {
+    /// Commitments to `a`, `b` and `c`.
+    wires: Vec<E::G1Affine>,
+    /// Commitment to the permutation accumulator.
+    z: E::G1Affine,
+    /// Commitment to the quotient polynomial.
+    t: E::G1Affine,
+    /// Values of the opened polynomials (in the order described at `NUM_OPENINGS`) at `zeta`.
+    evaluations: Vec<E::Fr>,
+    /// Value of `z` at `zeta * omega`.
+    shifted_z_evaluation: E::Fr,
+    /// Batched KZG opening proof at `zeta`.
+    opening: E::G1Affine,
+    /// KZG opening proof of `z` at `zeta * omega`.
+    shifted_opening: E::G1Affine,
+}
+
+impl<E: PairingEngine> Plonk<E> {
+    /// Generates SRS for circuits of R1CS size up to the given one.
+    pub fn universal_setup<R: RngCore + CryptoRng>(
+        num_constraints: usize,
+        num_variables: usize,
+        num_non_zero: usize,
+        rng: &mut R,
+    ) -> KzgSrs<E> {
+        let num_gates =
+            Circuit::<E::Fr>::max_num_gates(num_constraints, num_variables, num_non_zero);
+        let domain = Domain::<E::Fr>::new(num_gates).expect("Requested SRS is too large");
+        MarlinKZG10::<E, Poly<E::Fr>>::setup(max_degree(domain), None, rng)
+            .expect("Failed to generate SRS")
+    }
+
+    /// R1CS size of `circuit`, i.e. the smallest arguments of `universal_setup` that fit it.
+    pub fn srs_parameters<C: ConstraintSynthesizer<E::Fr>>(circuit: C) -> (usize, usize, usize) {
+        Circuit::<E::Fr>::r1cs_size(circuit)
+    }
+
+    /// Largest polynomial degree that has to be committed to for `circuit`.
+    pub fn max_degree<C: ConstraintSynthesizer<E::Fr>>(circuit: C) -> usize {
+        max_degree(Circuit::<E::Fr>::compile(circuit).domain())
+    }
+
+    /// Generates proving and verifying key for `circuit`.
+    pub fn index<C: ConstraintSynthesizer<E::Fr>>(
+        srs: &KzgSrs<E>,
+        circuit: C,
+    ) -> (ProvingKey<E>, VerifyingKey<E>) {
+        let circuit = Circuit::<E::Fr>::compile(circuit);
+        let domain = circuit.domain();
+        let max_degree = max_degree(domain);
+        assert!(
+            max_degree < srs.powers_of_g.len(),
+            "SRS is too small for the circuit (degree {} is needed)",
+            max_degree
+        );
+        let powers_of_g = srs.powers_of_g[..=max_degree].to_vec();
+
+        let preprocessed = circuit.preprocess(domain);
+        let vk = VerifyingKey {
+            domain_size: domain.size(),
+            num_public: circuit.num_public(),
+            selectors: commit_all(&powers_of_g, &preprocessed.selectors),
+            sigmas: commit_all(&powers_of_g, &preprocessed.sigmas),
+            g: srs.powers_of_g[0],
+            h: srs.h,
+            beta_h: srs.beta_h,
+        };
+        let pk = ProvingKey {
+            vk: vk.clone(),
+            circuit,
+            powers_of_g,
+        };
+        (pk, vk)
+    }
+
+    /// Generates proof for `circuit` using `pk`.
+    pub fn prove<C: ConstraintSynthesizer<E::Fr>, R: RngCore + CryptoRng>(
+        pk: &ProvingKey<E>,
+        circuit: C,
+        rng: &mut R,
+    ) -> Proof<E> {
+        let (circuit, wire_values) = Circuit::<E::Fr>::compile_with_wire_values(circuit);
+        assert_eq!(
+            circuit, pk.circuit,
+            "Proving key has been generated for a different circuit"
+        );
+        let domain = circuit.domain();
+        let n = domain.size();
+        let omega = domain.group_gen;
+        let public_input = &wire_values[1..=circuit.num_public()];
+        let preprocessed = circuit.preprocess(domain);
+        let mut transcript = Transcript::new(&pk.vk, public_input);
+
+        // Round 1: wire polynomials.
+        let wire_evaluations = circuit
+            .wire_columns(n)
+            .iter()
+            .map(|column| column.iter().map(|wire| wire_values[*wire]).collect())
+            .collect::<Vec<Vec<_>>>();
+        let wires = wire_evaluations
+            .iter()
+            .map(|evaluations| blind(interpolate(evaluations.clone(), domain), 2, n, rng))
+            .collect::<Vec<_>>();
+        let wire_commitments = commit_all(&pk.powers_of_g, &wires);
+        transcript.append(&wire_commitments);
+        let beta = transcript.challenge();
+        let gamma = transcript.challenge();
+
+        // Round 2: permutation accumulator.
+        let shifts = coset_shifts::<E::Fr>();
+        let elements = domain.elements().collect::<Vec<_>>();
+        let mut numerators = vec![E::Fr::one(); n];
+        let mut denominators = vec![E::Fr::one(); n];
+        for (column, (values, sigmas)) in wire_evaluations
+            .iter()
+            .zip(&preprocessed.sigma_evaluations)
+            .enumerate()
+        {
+            for (row, (value, sigma)) in values.iter().zip(sigmas).enumerate() {
+                numerators[row] *= *value + beta * shifts[column] * elements[row] + gamma;
+                denominators[row] *= *value + beta * sigma + gamma;
+            }
+        }
+        batch_inversion(&mut denominators);
+        let z_evaluations = numerators
+            .iter()
+            .zip(&denominators)
+            .scan(E::Fr::one(), |accumulator, (numerator, denominator)| {
+                let current = *accumulator;
+                *accumulator *= *numerator * denominator;
+                Some(current)
+            })
+            .collect();
+        let z = blind(interpolate(z_evaluations, domain), 3, n, rng);
+        let z_commitment = commit(&pk.powers_of_g, &z);
+        transcript.append(&z_commitment);
+        let alpha = transcript.challenge();
+
+        // Round 3: quotient polynomial.
+        let (a, b, c) = (&wires[0], &wires[1], &wires[2]);
+        let selectors = &preprocessed.selectors;
+        let (q_m, q_l, q_r, q_o, q_c) = (
+            &selectors[0],
+            &selectors[1],
+            &selectors[2],
+            &selectors[3],
+            &selectors[4],
+        );
+        let sigmas = &preprocessed.sigmas;
+
+        let mut public_input_evaluations = vec![E::Fr::zero(); n];
+        for (evaluation, input) in public_input_evaluations.iter_mut().zip(public_input) {
+            *evaluation = -*input;
+        }
+        let public_input_poly = interpolate(public_input_evaluations, domain);
+
+        let gate = [
+            &(q_m * &(a * b)),
+            &(q_l * a),
+            &(q_r * b),
+            &(q_o * c),
+            q_c,
+            &public_input_poly,
+        ]
+        .into_iter()
+        .fold(Poly::zero(), |sum, term| &sum + term);
+
+        let mut permutation_numerator = z.clone();
+        let mut permutation_denominator = shift(&z, omega);
+        for (column, wire) in wires.iter().enumerate() {
+            let identity = Poly::from_coefficients_vec(vec![gamma, beta * shifts[column]]);
+            permutation_numerator = &permutation_numerator * &(wire + &identity);
+            let sigma = &(&sigmas[column] * beta) + &Poly::from_coefficients_vec(vec![gamma]);
+            permutation_denominator = &permutation_denominator * &(wire + &sigma);
+        }
+        let permutation = &permutation_numerator - &permutation_denominator;
+
+        let mut first_lagrange = vec![E::Fr::zero(); n];
+        first_lagrange[0] = E::Fr::one();
+        let boundary = &interpolate(first_lagrange, domain)
+            * &(&z - &Poly::from_coefficients_vec(vec![E::Fr::one()]));
+
+        let numerator = &(&gate + &(&permutation * alpha)) + &(&boundary * alpha.square());
+        let (t, remainder) = numerator
+            .divide_by_vanishing_poly(domain)
+            .expect("Failed to divide by the vanishing polynomial");
+        assert!(remainder.is_zero(), "Circuit is not satisfied");
+        let t_commitment = commit(&pk.powers_of_g, &t);
+        transcript.append(&t_commitment);
+        let zeta = transcript.challenge();
+
+        // Round 4: evaluations.
+        let opened = [a, b, c, &z, &t]
+            .into_iter()
+            .chain(selectors)
+            .chain(sigmas)
+            .collect::<Vec<_>>();
+        let evaluations = opened
+            .iter()
+            .map(|poly| poly.evaluate(&zeta))
+            .collect::<Vec<_>>();
+        let shifted_z_evaluation = z.evaluate(&(zeta * omega));
+        transcript.append(&evaluations);
+        transcript.append(&shifted_z_evaluation);
+        let v = transcript.challenge();
+
+        // Round 5: opening proofs.
+        let mut combined = Poly::zero();
+        let mut power = E::Fr::one();
+        for poly in opened {
+            combined = &combined + &(poly * power);
+            power *= v;
+        }
+
+        Proof {
+            wires: wire_commitments,
+            z: z_commitment,
+            t: t_commitment,
+            evaluations,
+            shifted_z_evaluation,
+            opening: commit(&pk.powers_of_g, &divide_by_linear(&combined, zeta)),
+            shifted_opening: commit(&pk.powers_of_g, &divide_by_linear(&z, zeta * omega)),
+        }
+    }
+
+    /// Verifies `proof` against `public_input` using `vk`.
+    pub fn verify(vk: &VerifyingKey<E>, public_input: &[E::Fr], proof: &Proof<E>) -> bool {
+        if public_input.len() != vk.num_public
+            || proof.wires.len() != 3
+            || proof.evaluations.len() != NUM_OPENINGS
+        {
+            return false;
+        }
+        let domain = match Domain::<E::Fr>::new(vk.domain_size) {
+            Some(domain) if domain.size() == vk.domain_size => domain,
+            _ => return false,
+        };
+
+        let mut transcript = Transcript::new(vk, public_input);
+        transcript.append(&proof.wires);
+        let beta = transcript.challenge();
+        let gamma = transcript.challenge();
+        transcript.append(&proof.z);
+        let alpha = transcript.challenge();
+        transcript.append(&proof.t);
+        let zeta = transcript.challenge();
+        transcript.append(&proof.evaluations);
+        transcript.append(&proof.shifted_z_evaluation);
+        let v = transcript.challenge();
+        transcript.append(&proof.opening);
+        transcript.append(&proof.shifted_opening);
+        let u = transcript.challenge();
+
+        // Check the main identity at `zeta`.
+        let e = &proof.evaluations;
+        let (a, b, c, z, t) = (e[0], e[1], e[2], e[3], e[4]);
+        let (q_m, q_l, q_r, q_o, q_c) = (e[5], e[6], e[7], e[8], e[9]);
+        let sigmas = [e[10], e[11], e[12]];
+        let shifts = coset_shifts::<E::Fr>();
+
+        let lagrange = domain.evaluate_all_lagrange_coefficients(zeta);
+        let public_input_evaluation = public_input
+            .iter()
+            .zip(&lagrange)
+            .fold(E::Fr::zero(), |sum, (input, l)| sum - *input * l);
+
+        let gate = q_m * a * b + q_l * a + q_r * b + q_o * c + q_c + public_input_evaluation;
+        let mut permutation_numerator = z;
+        let mut permutation_denominator = proof.shifted_z_evaluation;
+        for (column, wire) in [a, b, c].into_iter().enumerate() {
+            permutation_numerator *= wire + beta * shifts[column] * zeta + gamma;
+            permutation_denominator *= wire + beta * sigmas[column] + gamma;
+        }
+        let boundary = lagrange[0] * (z - E::Fr::one());
+
+        let numerator = gate
+            + alpha * (permutation_numerator - permutation_denominator)
+            + alpha.square() * boundary;
+        if numerator != t * domain.evaluate_vanishing_polynomial(zeta) {
+            return false;
+        }
+
+        // Check the openings: `e(W, beta * H) = e(C - y * G + x * W, H)` for an opening proof `W`
+        // of a commitment `C` to value `y` at `x`. Both checks are combined with `u`.
+        let commitments = proof
+            .wires
+            .iter()
+            .chain([&proof.z, &proof.t])
+            .chain(&vk.selectors)
+            .chain(&vk.sigmas)
+            .copied()
+            .collect::<Vec<_>>();
+        let powers_of_v = (0..NUM_OPENINGS)
+            .scan(E::Fr::one(), |power, _| {
+                let current = *power;
+                *power *= v;
+                Some(current)
+            })
+            .collect::<Vec<_>>();
+        let combined_commitment = msm(&commitments, &powers_of_v);
+        let combined_evaluation = e
+            .iter()
+            .zip(&powers_of_v)
+            .fold(E::Fr::zero(), |sum, (y, power)| sum + *y * power);
+        let omega = domain.group_gen;
+
+        let at_zeta = combined_commitment - vk.g.mul(combined_evaluation) + proof.opening.mul(zeta);
+        let at_shifted_zeta = proof.z.into_projective() - vk.g.mul(proof.shifted_z_evaluation)
+            + proof.shifted_opening.mul(zeta * omega);
+
+        let lhs = proof.opening.into_projective() + proof.shifted_opening.mul(u);
+        let rhs = at_zeta + at_shifted_zeta.into_affine().mul(u);
+        E::pairing(lhs.into_affine(), vk.beta_h) == E::pairing(rhs.into_affine(), vk.h)
+    }
+}
+
+/// Largest polynomial degree committed to for circuits over `domain`: blinded wires have degree
+/// `n + 1` and the accumulator `n + 2`, so the quotient has degree `3n + 5`.
+fn max_degree<F: PrimeField>(domain: Domain<F>) -> usize {
+    3 * domain.size() + 5
+}
+
+/// Adds `blinder(X) * Z_H(X)` for a random `blinder` of degree `num_blinders - 1`. This doesn't
+/// change the values of `poly` over the domain (of size `n`).
+fn blind<F: PrimeField, R: RngCore + CryptoRng>(
+    poly: Poly<F>,
+    num_blinders: usize,
+    n: usize,
+    rng: &mut R,
+) -> Poly<F> {
+    let mut coefficients = poly.coeffs;
+    coefficients.resize(n + num_blinders, F::zero());
+    for i in 0..num_blinders {
+        let blinder = F::rand(rng);
+        coefficients[i] -= blinder;
+        coefficients[n + i] += blinder;
+    }
+    Poly::from_coefficients_vec(coefficients)
+}
+
+/// Computes `poly(factor * X)`.
+fn shift<F: PrimeField>(poly: &Poly<F>, factor: F) -> Poly<F> {
+    let mut power = F::one();
+    let coefficients = poly
+        .coeffs
+        .iter()
+        .map(|coefficient| {
+            let shifted = *coefficient * power;
+            power *= factor;
+            shifted
+        })
+        .collect();
+    Poly::from_coefficients_vec(coefficients)
+}
+
+/// Computes `(poly(X) - poly(point)) / (X - point)`.
+fn divide_by_linear<F: PrimeField>(poly: &Poly<F>, point: F) -> Poly<F> {
+    let mut quotient = vec![F::zero(); poly.coeffs.len().saturating_sub(1)];
+    let mut carry = F::zero();
+    for i in (1..poly.coeffs.len()).rev() {
+        carry = carry * point + poly.coeffs[i];
+        quotient[i - 1] = carry;
+    }
+    Poly::from_coefficients_vec(quotient)
+}
+
+fn commit<G: AffineCurve>(powers_of_g: &[G], poly: &Poly<G::ScalarField>) -> G {
+    assert!(
+        poly.coeffs.len() <= powers_of_g.len(),
+        "Polynomial degree exceeds the proving key"
+    );
+    msm(&powers_of_g[..poly.coeffs.len()], &poly.coeffs).into_affine()
+}
+
+fn commit_all<G: AffineCurve>(powers_of_g: &[G], polys: &[Poly<G::ScalarField>]) -> Vec<G> {
+    polys.iter().map(|poly| commit(powers_of_g, poly)).collect()
+}
+
+fn msm<G: AffineCurve>(bases: &[G], scalars: &[G::ScalarField]) -> G::Projective {
+    let scalars = scalars.iter().map(|s| s.into_repr()).collect::<Vec<_>>();
+    VariableBaseMSM::multi_scalar_mul(bases, &scalars)
+}
+
+/// Fiat-Shamir transcript.
+struct Transcript<E: PairingEngine>(Blake2s, PhantomData<E>);
+
+impl<E: PairingEngine> Transcript<E> {
+    fn new(vk: &VerifyingKey<E>, public_input: &[E::Fr]) -> Self {
+        let mut hasher = Blake2s::new();
+        hasher.update(serialize(vk));
+        hasher.update(serialize(&public_input.to_vec()));
+        Transcript(hasher, PhantomData)
+    }
+
+    fn append<T: CanonicalSerialize>(&mut self, item: &T) {
+        self.0.update(serialize(item));
+    }
+
+    fn challenge(&mut self) -> E::Fr {
+        let digest = self.0.clone().finalize();
+        self.0.update(&digest);
+        E::Fr::from_le_bytes_mod_order(&digest)
+    }
+}
+
+impl<E: PairingEngine> CanonicalSerialize for VerifyingKey<E> {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.domain_size.serialize(&mut writer)?;
+        self.num_public.serialize(&mut writer)?;
+        self.selectors.serialize(&mut writer)?;
+        self.sigmas.serialize(&mut writer)?;
+        self.g.serialize(&mut writer)?;
+        self.h.serialize(&mut writer)?;
+        self.beta_h.serialize(&mut writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.domain_size.serialized_size()
+            + self.num_public.serialized_size()
+            + self.selectors.serialized_size()
+            + self.sigmas.serialized_size()
+            + self.g.serialized_size()
+            + self.h.serialized_size()
+            + self.beta_h.serialized_size()
+    }
+}
+
+impl<E: PairingEngine> CanonicalDeserialize for VerifyingKey<E> {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        let vk = VerifyingKey {
+            domain_size: usize::deserialize(&mut reader)?,
+            num_public: usize::deserialize(&mut reader)?,
+            selectors: Vec::<E::G1Affine>::deserialize(&mut reader)?,
+            sigmas: Vec::<E::G1Affine>::deserialize(&mut reader)?,
+            g: E::G1Affine::deserialize(&mut reader)?,
+            h: E::G2Affine::deserialize(&mut reader)?,
+            beta_h: E::G2Affine::deserialize(&mut reader)?,
+        };
+        if vk.selectors.len() != 5 || vk.sigmas.len() != 3 {
+            return Err(SerializationError::InvalidData);
+        }
+        Ok(vk)
+    }
+}
+
+impl<E: PairingEngine> CanonicalSerialize for ProvingKey<E> {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.vk.serialize(&mut writer)?;
+        self.circuit.serialize(&mut writer)?;
+        self.powers_of_g.serialize(&mut writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.vk.serialized_size()
+            + self.circuit.serialized_size()
+            + self.powers_of_g.serialized_size()
+    }
+}
+
+impl<E: PairingEngine> CanonicalDeserialize for ProvingKey<E> {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        Ok(ProvingKey {
+            vk: VerifyingKey::<E>::deserialize(&mut reader)?,
+            circuit: Circuit::<E::Fr>::deserialize(&mut reader)?,
+            powers_of_g: Vec::<E::G1Affine>::deserialize(&mut reader)?,
+        })
+    }
+}
+
+impl<E: PairingEngine> CanonicalSerialize for Proof<E> {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.wires.serialize(&mut writer)?;
+        self.z.serialize(&mut writer)?;
+        self.t.serialize(&mut writer)?;
+        self.evaluations.serialize(&mut writer)?;
+        self.shifted_z_evaluation.serialize(&mut writer)?;
+        self.opening.serialize(&mut writer)?;
+        self.shifted_opening.serialize(&mut writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.wires.serialized_size()
+            + self.z.serialized_size()
+            + self.t.serialized_size()
+            + self.evaluations.serialized_size()
+            + self.shifted_z_evaluation.serialized_size()
+            + self.opening.serialized_size()
+            + self.shifted_opening.serialized_size()
+    }
+}
+
+impl<E: PairingEngine> CanonicalDeserialize for Proof<E> {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        Ok(Proof {
+            wires: Vec::<E::G1Affine>::deserialize(&mut reader)?,
+            z: E::G1Affine::deserialize(&mut reader)?,
+            t: E::G1Affine::deserialize(&mut reader)?,
+            evaluations: Vec::<E::Fr>::deserialize(&mut reader)?,
+            shifted_z_evaluation: E::Fr::deserialize(&mut reader)?,
+            opening: E::G1Affine::deserialize(&mut reader)?,
+            shifted_opening: E::G1Affine::deserialize(&mut reader)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_bls12_381::Bls12_381;
+
+    use super::*;
+    use crate::{
+        environment::{rng, CircuitField},
+        relations::GetPublicInput,
+    };
+
+    type Plonk = super::Plonk<Bls12_381>;
+
+    fn check_round_trip<C>(circuit: C)
+    where
+        C: ConstraintSynthesizer<CircuitField> + GetPublicInput<CircuitField> + Clone,
+    {
+        let (num_constraints, num_variables, num_non_zero) = Plonk::srs_parameters(circuit.clone());
+        let srs =
+            Plonk::universal_setup(num_constraints, num_variables, num_non_zero, &mut rng(None));
+        let (pk, vk) = Plonk::index(&srs, circuit.clone());
+
+        let input = circuit.public_input();
+        let proof = Plonk::prove(&pk, circuit, &mut rng(None));
+        assert!(Plonk::verify(&vk, &input, &proof));
+
+        let deserialized = Proof::deserialize(&*serialize(&proof)).unwrap();
+        assert!(Plonk::verify(&vk, &input, &deserialized));
+
+        let mut wrong_input = input;
+        match wrong_input.first_mut() {
+            Some(first) => *first += CircuitField::one(),
+            None => wrong_input.push(CircuitField::one()),
+        }
+        assert!(!Plonk::verify(&vk, &wrong_input, &proof));
+    }
+
+    #[test]
+    fn divides_by_linear_factor() {
+        let poly = Poly::<CircuitField>::from_coefficients_vec(
+            vec![3u8, 1, 4, 1, 5].into_iter().map(Into::into).collect(),
+        );
+        let point = CircuitField::from(7u8);
+        let quotient = divide_by_linear(&poly, point);
+        let divisor = Poly::from_coefficients_vec(vec![-point, CircuitField::one()]);
+        let remainder = Poly::from_coefficients_vec(vec![poly.evaluate(&point)]);
+        assert_eq!(&(&quotient * &divisor) + &remainder, poly);
+    }
+
+    #[cfg(feature = "xor")]
+    #[test]
+    fn xor_round_trip() {
+        check_round_trip(crate::relations::XorRelation {
+            public_xoree: 2,
+            private_xoree: 3,
+            result: 1,
+        });
+    }
+
+    #[cfg(feature = "linear")]
+    #[test]
+    fn linear_round_trip() {
+        check_round_trip(crate::relations::LinearEqRelation {
+            a: 2,
+            x: 7,
+            b: 5,
+            y: 19,
+        });
+    }
+
+    #[cfg(feature = "merkle_tree")]
+    #[test]
+    fn merkle_tree_round_trip() {
+        let leaves = (1..=8u64).map(CircuitField::from).collect();
+        check_round_trip(crate::relations::MerkleTreeRelation::new(leaves, 2, None));
+    }
+
+    #[cfg(feature = "deposit")]
+    #[test]
+    fn deposit_round_trip() {
+        use crate::relations::{DepositRelation, DepositRelationArgs, WitnessTemplate};
+
+        check_round_trip(DepositRelation::from(DepositRelationArgs::template()));
+    }
+
+    #[cfg(feature = "withdraw")]
+    #[test]
+    fn withdraw_round_trip() {
+        use crate::relations::{WithdrawRelation, WithdrawRelationArgs, WitnessTemplate};
+
+        check_round_trip(WithdrawRelation::from(WithdrawRelationArgs::template()));
+    }
+}
@@ -1,18 +1,55 @@
-use std::{env, fs, path::PathBuf};
+use std::{env, fs, path::Path};
 
-fn is_snark_output(path: PathBuf) -> bool {
-    let ext = path.extension();
-    ext.is_some() && ext.unwrap() == "bytes"
+/// Endings of the names of files that house-snark saves by default: binary artifacts, named public
+/// input (`public_input.json`) and verifiers exported from verifying keys (`.vk.bytes` with the
+/// extension of the verifier format).
+const SNARK_OUTPUT_ENDINGS: [&str; 4] = [".bytes", ".public_input.json", ".vk.sol", ".vk.rs"];
+
+fn is_snark_output(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map_or(false, |name| {
+                SNARK_OUTPUT_ENDINGS
+                    .iter()
+                    .any(|ending| name.ends_with(ending))
+            })
 }
 
 pub fn kill_all_snarks() -> anyhow::Result<()> {
     let cur_dir = env::current_dir()?;
     for file in fs::read_dir(cur_dir)? {
         let path = file?.path();
-        if is_snark_output(path.clone()) {
+        if is_snark_output(&path) {
             log::info!("Removing {:?}", path);
             fs::remove_file(path)?;
         }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_snark_outputs_are_recognized() {
+        let dir = env::temp_dir().join("house-snark-red-wedding");
+        fs::create_dir_all(&dir).unwrap();
+
+        for (name, is_output) in [
+            ("xor.groth16.bls12_381.pk.bytes", true),
+            ("xor.groth16.bls12_381.public_input.json", true),
+            ("xor.groth16.bn254.vk.sol", true),
+            ("xor.groth16.bls12_381.vk.rs", true),
+            ("manifest.json", false),
+            ("main.rs", false),
+            ("Verifier.sol", false),
+        ] {
+            let path = dir.join(name);
+            fs::write(&path, b"").unwrap();
+            assert_eq!(is_snark_output(&path), is_output, "{}", name);
+        }
+    }
+}
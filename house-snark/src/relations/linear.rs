@@ -6,27 +6,33 @@ use ark_r1cs_std::{
 use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
 use ark_serialize::CanonicalSerialize;
 use clap::Args;
+use serde::{Deserialize, Serialize};
 
-use crate::relations::GetPublicInput;
+use crate::relations::{witness::WitnessTemplate, GetPublicInput};
 
 /// Linear equation relation: a*x + b = y
 ///
 /// Relation with:
 ///  - 1 private witness (x)
 ///  - 3 constants        (a, b, y)
-#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Args)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct LinearEqRelation {
     /// constant (a slope)
     #[clap(long, default_value = "2")]
+    #[serde(with = "crate::relations::witness::integer")]
     pub a: u32,
     /// private witness
     #[clap(long, default_value = "7")]
+    #[serde(with = "crate::relations::witness::integer")]
     pub x: u32,
     /// constant(an intercept)
     #[clap(long, default_value = "5")]
+    #[serde(with = "crate::relations::witness::integer")]
     pub b: u32,
     /// constant
     #[clap(long, default_value = "19")]
+    #[serde(with = "crate::relations::witness::integer")]
     pub y: u32,
 }
 
@@ -50,6 +56,17 @@ impl<Field: PrimeField> ConstraintSynthesizer<Field> for LinearEqRelation {
     }
 }
 
+impl WitnessTemplate for LinearEqRelation {
+    fn template() -> Self {
+        LinearEqRelation {
+            a: 2,
+            x: 7,
+            b: 5,
+            y: 19,
+        }
+    }
+}
+
 impl<CircuitField: PrimeField + CanonicalSerialize> GetPublicInput<CircuitField>
     for LinearEqRelation
 {
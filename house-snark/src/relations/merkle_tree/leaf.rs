@@ -0,0 +1,174 @@
+//! Leaves of the Merkle tree: field elements or arbitrary byte strings.
+//!
+//! In the circuit, every leaf is a field element. Byte strings are mapped to field elements with
+//! Blake2s, so that leaves of any length can be used. Trees are padded with a reserved element
+//! (see [`padding_leaf`]) that no leaf can map to.
+
+use std::{
+    fmt::{Display, Formatter},
+    fs,
+    path::Path,
+    str::FromStr,
+};
+
+use anyhow::{anyhow, Error, Result};
+use ark_ff::{FpParameters, PrimeField};
+use blake2::{Blake2s, Digest};
+use num_bigint::BigUint;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::relations::types::CircuitField;
+
+/// Domain separator of the byte string leaves.
+const LEAF_DOMAIN: &[u8] = b"house-snark-merkle-leaf";
+/// Domain separator of the padding leaf.
+const PADDING_DOMAIN: &[u8] = b"house-snark-merkle-padding";
+
+/// Hash of `bytes` under `domain`, mapped to a field element.
+fn hash_to_field(domain: &[u8], bytes: &[u8]) -> CircuitField {
+    let mut hasher = Blake2s::new();
+    hasher.update(domain);
+    hasher.update(bytes);
+    CircuitField::from_le_bytes_mod_order(&hasher.finalize())
+}
+
+/// The field element filling the tree up to a power of two. It is rejected as a leaf, so that
+/// padding cannot be proven to be a member of the tree.
+pub fn padding_leaf() -> CircuitField {
+    hash_to_field(PADDING_DOMAIN, &[])
+}
+
+/// A single leaf, as given by the user.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Leaf {
+    /// A field element, given as a decimal number.
+    Element(CircuitField),
+    /// An arbitrary byte string, given as a `0x`-prefixed hex string.
+    Bytes(Vec<u8>),
+}
+
+impl Leaf {
+    /// The field element representing this leaf in the tree (and in the circuit).
+    pub fn to_field_element(&self) -> CircuitField {
+        match self {
+            Leaf::Element(element) => *element,
+            Leaf::Bytes(bytes) => hash_to_field(LEAF_DOMAIN, bytes),
+        }
+    }
+}
+
+impl FromStr for Leaf {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if let Some(hex) = s.strip_prefix("0x") {
+            return hex::decode(hex)
+                .map(Leaf::Bytes)
+                .map_err(|e| anyhow!("{} is not hex-encoded: {}", s, e));
+        }
+
+        let value = BigUint::from_str(s).map_err(|e| anyhow!("{} is not a number: {}", s, e))?;
+        let modulus = BigUint::from(<CircuitField as PrimeField>::Params::MODULUS);
+        if value >= modulus {
+            return Err(anyhow!("{} is not smaller than the field modulus", s));
+        }
+        let element = CircuitField::from_le_bytes_mod_order(&value.to_bytes_le());
+        if element == padding_leaf() {
+            return Err(anyhow!("{} is reserved for padding the tree", s));
+        }
+        Ok(Leaf::Element(element))
+    }
+}
+
+impl Display for Leaf {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Leaf::Element(element) => write!(f, "{}", BigUint::from(element.into_repr())),
+            Leaf::Bytes(bytes) => write!(f, "0x{}", hex::encode(bytes)),
+        }
+    }
+}
+
+impl From<u64> for Leaf {
+    fn from(value: u64) -> Self {
+        Leaf::Element(CircuitField::from(value))
+    }
+}
+
+impl Serialize for Leaf {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Leaf {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum RawLeaf {
+            Number(u64),
+            Text(String),
+        }
+
+        match RawLeaf::deserialize(deserializer)? {
+            RawLeaf::Number(value) => Ok(Leaf::from(value)),
+            RawLeaf::Text(text) => text.parse().map_err(de::Error::custom),
+        }
+    }
+}
+
+/// Read leaves from `path`: one leaf per line, empty lines are skipped.
+pub fn read_leaves(path: &Path) -> Result<Vec<Leaf>> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read leaves from {:?}: {}", path, e))?;
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(Leaf::from_str)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaves_are_parsed_and_displayed() {
+        for text in ["0", "17", "0x", "0xdeadbeef"] {
+            assert_eq!(Leaf::from_str(text).unwrap().to_string(), text);
+        }
+        assert_eq!(Leaf::from_str("17").unwrap(), Leaf::from(17));
+
+        let modulus = BigUint::from(<CircuitField as PrimeField>::Params::MODULUS);
+        assert!(Leaf::from_str(&modulus.to_string()).is_err());
+        assert!(Leaf::from_str("0xnothex").is_err());
+
+        let padding = BigUint::from(padding_leaf().into_repr());
+        assert!(Leaf::from_str(&padding.to_string()).is_err());
+    }
+
+    #[test]
+    fn byte_leaves_are_hashed() {
+        let leaf = Leaf::Bytes(vec![1, 2, 3]);
+        assert_ne!(
+            leaf.to_field_element(),
+            Leaf::Bytes(vec![1, 2]).to_field_element()
+        );
+        assert_ne!(
+            leaf.to_field_element(),
+            Leaf::from(0x030201).to_field_element()
+        );
+    }
+
+    #[test]
+    fn leaves_are_read_from_file() {
+        let path = std::env::temp_dir().join("house-snark-merkle-leaves.txt");
+        fs::write(&path, "1\n0x0203\n\n4\n").unwrap();
+
+        assert_eq!(
+            read_leaves(&path).unwrap(),
+            vec![Leaf::from(1), Leaf::Bytes(vec![2, 3]), Leaf::from(4)]
+        );
+    }
+}
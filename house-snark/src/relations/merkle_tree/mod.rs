@@ -9,7 +9,9 @@
 
 mod gadgets;
 mod hash_functions;
+mod leaf;
 mod relation;
 mod tree;
 
+pub use leaf::Leaf;
 pub use relation::{MerkleTreeRelation, MerkleTreeRelationArgs};
@@ -1,20 +1,24 @@
+use std::path::PathBuf;
+
 use ark_crypto_primitives::{
     crh::{TwoToOneCRH, TwoToOneCRHGadget},
     PathVar, CRH,
 };
-use ark_r1cs_std::{boolean::Boolean, eq::EqGadget, prelude::AllocVar, uint8::UInt8};
+use ark_r1cs_std::{boolean::Boolean, eq::EqGadget, fields::fp::FpVar, prelude::AllocVar};
 use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
 use clap::Args;
+use serde::{Deserialize, Serialize};
 
 use crate::relations::{
-    byte_to_bits,
     merkle_tree::{
         gadgets::{LeafHashGadget, LeafHashParamsVar, TwoToOneHashGadget, TwoToOneHashParamsVar},
         hash_functions::{LeafHash, TwoToOneHash},
+        leaf::{read_leaves, Leaf},
         tree::{new_tree, MerkleConfig, Root, SimplePath},
     },
     string_to_padded_bytes,
     types::CircuitField,
+    witness::WitnessTemplate,
     GetPublicInput,
 };
 
@@ -25,19 +29,28 @@ pub type RootVar = <TwoToOneHashGadget as TwoToOneCRHGadget<TwoToOneHash, Circui
 pub type SimplePathVar = PathVar<MerkleConfig, LeafHashGadget, TwoToOneHashGadget, CircuitField>;
 
 /// Arguments for creating a MerkeTreeRelation
-#[derive(Clone, Eq, PartialEq, Hash, Debug, Args)]
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct MerkleTreeRelationArgs {
     /// Seed bytes for rng, the more the merrier
     #[clap(long)]
     pub seed: Option<String>,
 
-    /// Tree leaves, used to calculate the tree root
+    /// Tree leaves, used to calculate the tree root. Every leaf is either a field element (given
+    /// as a decimal number) or a byte string (given as a `0x`-prefixed hex string)
     #[clap(long, value_delimiter = ',')]
-    pub leaves: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub leaves: Vec<Leaf>,
+
+    /// File with tree leaves (one per line), used instead of `leaves`
+    #[clap(long, conflicts_with = "leaves")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leaves_file: Option<PathBuf>,
 
-    /// Leaf of which membership is to be proven, must be one of the leaves
+    /// Index of the leaf of which membership is to be proven
     #[clap(long)]
-    pub leaf: u8,
+    #[serde(with = "crate::relations::witness::integer")]
+    pub leaf_index: u64,
 }
 
 /// Relation for checking membership in a Merkle tree.
@@ -52,7 +65,7 @@ pub struct MerkleTreeRelation {
     pub root: Root,
 
     /// Leaf which membership is to be proven (public input).
-    pub leaf: u8,
+    pub leaf: CircuitField,
 
     /// Collision-resistant hash function for leafs (constant parameter).
     pub leaf_crh_params: <LeafHash as CRH>::Parameters,
@@ -62,26 +75,67 @@ pub struct MerkleTreeRelation {
     pub two_to_one_crh_params: <TwoToOneHash as TwoToOneCRH>::Parameters,
 }
 
+impl WitnessTemplate for MerkleTreeRelationArgs {
+    fn template() -> Self {
+        MerkleTreeRelationArgs::template_of_height(3)
+    }
+}
+
+impl MerkleTreeRelationArgs {
+    /// Membership of leaf `1` in a tree of height `height` with leaves `0, 1, ..., 2^height - 1`.
+    pub fn template_of_height(height: u8) -> Self {
+        assert!(
+            (1..64).contains(&height),
+            "Tree height must be between 1 and 63"
+        );
+        MerkleTreeRelationArgs {
+            seed: Some(String::from("deadbeef")),
+            leaves: (0..1u64 << height).map(Leaf::from).collect(),
+            leaves_file: None,
+            leaf_index: 1,
+        }
+    }
+
+    /// Tree leaves, either given directly or read from `leaves_file`.
+    pub fn tree_leaves(&self) -> Vec<Leaf> {
+        match &self.leaves_file {
+            Some(path) => read_leaves(path).expect("Failed to read tree leaves"),
+            None => self.leaves.clone(),
+        }
+    }
+}
+
 impl From<MerkleTreeRelationArgs> for MerkleTreeRelation {
     fn from(item: MerkleTreeRelationArgs) -> Self {
-        let MerkleTreeRelationArgs { seed, leaves, leaf } = item;
-        MerkleTreeRelation::new(leaves, leaf, seed)
+        let leaves = item
+            .tree_leaves()
+            .iter()
+            .map(Leaf::to_field_element)
+            .collect();
+        let leaf_index = item
+            .leaf_index
+            .try_into()
+            .expect("Leaf index does not fit in `usize`");
+        MerkleTreeRelation::new(leaves, leaf_index, item.seed)
     }
 }
 
 impl MerkleTreeRelation {
-    pub fn new(leaves: Vec<u8>, leaf: u8, seed: Option<String>) -> Self {
-        let leaf_idx = leaves
-            .iter()
-            .position(|&element| element == leaf)
-            .expect("Leaf is not in the tree leaves");
+    pub fn new(leaves: Vec<CircuitField>, leaf_index: usize, seed: Option<String>) -> Self {
+        assert!(
+            leaf_index < leaves.len(),
+            "Leaf index {} is out of range (there are {} leaves)",
+            leaf_index,
+            leaves.len()
+        );
+        let leaf = leaves[leaf_index];
 
         let seed = string_to_padded_bytes(seed.unwrap_or_else(|| "".to_owned()));
 
         let (tree, leaf_crh_params, two_to_one_crh_params) = new_tree(leaves, seed);
 
         MerkleTreeRelation {
-            authentication_path: tree.generate_proof(leaf_idx).unwrap(),
+            authentication_path: tree.generate_proof(leaf_index).unwrap(),
             root: tree.root(),
             leaf,
             leaf_crh_params,
@@ -100,20 +154,14 @@ impl ConstraintSynthesizer<CircuitField> for MerkleTreeRelation {
         })?;
 
         let root = RootVar::new_input(ark_relations::ns!(cs, "root_var"), || Ok(&self.root))?;
-        let leaf = UInt8::new_input(ark_relations::ns!(cs, "leaf_var"), || Ok(&self.leaf))?;
+        let leaf = FpVar::new_input(ark_relations::ns!(cs, "leaf_var"), || Ok(&self.leaf))?;
 
         let leaf_crh_params = LeafHashParamsVar::new_constant(cs.clone(), &self.leaf_crh_params)?;
         let two_to_one_crh_params =
             TwoToOneHashParamsVar::new_constant(cs, &self.two_to_one_crh_params)?;
 
-        let leaf_bytes = vec![leaf; 1];
-
-        let is_member = path.verify_membership(
-            &leaf_crh_params,
-            &two_to_one_crh_params,
-            &root,
-            &leaf_bytes.as_slice(),
-        )?;
+        let is_member =
+            path.verify_membership(&leaf_crh_params, &two_to_one_crh_params, &root, &leaf)?;
         is_member.enforce_equal(&Boolean::TRUE)?;
 
         Ok(())
@@ -121,7 +169,99 @@ impl ConstraintSynthesizer<CircuitField> for MerkleTreeRelation {
 }
 
 impl GetPublicInput<CircuitField> for MerkleTreeRelation {
-    fn public_input(&self) -> Vec<CircuitField> {
-        [vec![self.root], byte_to_bits(self.leaf).to_vec()].concat()
+    fn named_public_input(&self) -> Vec<(String, CircuitField)> {
+        vec![
+            (String::from("root"), self.root),
+            (String::from("leaf"), self.leaf),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use ark_ff::Zero;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    use super::*;
+
+    fn is_satisfied(relation: MerkleTreeRelation) -> bool {
+        let cs = ConstraintSystem::new_ref();
+        relation.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn duplicate_and_byte_leaves_are_supported() {
+        let args = MerkleTreeRelationArgs {
+            seed: None,
+            leaves: vec![
+                Leaf::from(7),
+                Leaf::Bytes(
+                    b"some long leaf, definitely longer than a single field element".to_vec(),
+                ),
+                Leaf::from(7),
+            ],
+            leaves_file: None,
+            leaf_index: 2,
+        };
+
+        let relation = MerkleTreeRelation::from(args.clone());
+        assert_eq!(relation.leaf, CircuitField::from(7u64));
+        assert!(is_satisfied(relation));
+
+        let relation = MerkleTreeRelation::from(MerkleTreeRelationArgs {
+            leaf_index: 1,
+            ..args
+        });
+        assert!(is_satisfied(relation));
+    }
+
+    #[test]
+    fn wrong_leaf_is_unsatisfiable() {
+        let relation = MerkleTreeRelation {
+            leaf: CircuitField::from(2u64),
+            ..MerkleTreeRelation::from(MerkleTreeRelationArgs::template())
+        };
+        assert!(!is_satisfied(relation));
+    }
+
+    #[test]
+    fn padding_is_not_a_member() {
+        let leaves = (1..4u64).map(CircuitField::from).collect::<Vec<_>>();
+        let (tree, leaf_crh_params, two_to_one_crh_params) = new_tree(leaves, [0; 32]);
+
+        let relation = MerkleTreeRelation {
+            authentication_path: tree.generate_proof(3).unwrap(),
+            root: tree.root(),
+            leaf: CircuitField::zero(),
+            leaf_crh_params,
+            two_to_one_crh_params,
+        };
+        assert!(!is_satisfied(relation));
+    }
+
+    #[test]
+    fn leaves_can_be_read_from_file() {
+        let path = std::env::temp_dir().join("house-snark-merkle-tree-leaves.txt");
+        let leaves = (0..1000).map(Leaf::from).collect::<Vec<_>>();
+        fs::write(
+            &path,
+            leaves
+                .iter()
+                .map(|leaf| format!("{}\n", leaf))
+                .collect::<String>(),
+        )
+        .unwrap();
+
+        let args = MerkleTreeRelationArgs {
+            seed: None,
+            leaves: vec![],
+            leaves_file: Some(path),
+            leaf_index: 999,
+        };
+        assert_eq!(args.tree_leaves(), leaves);
+        assert!(is_satisfied(MerkleTreeRelation::from(args)));
     }
 }
@@ -6,7 +6,13 @@ use ark_crypto_primitives::{
 use ark_ed_on_bls12_381::EdwardsProjective;
 use ark_std::rand::{prelude::StdRng, SeedableRng};
 
-use crate::relations::merkle_tree::hash_functions::{LeafHash, TwoToOneHash};
+use crate::relations::{
+    merkle_tree::{
+        hash_functions::{LeafHash, TwoToOneHash},
+        leaf::padding_leaf,
+    },
+    types::CircuitField,
+};
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
 pub struct MerkleConfig;
@@ -15,21 +21,24 @@ impl Config for MerkleConfig {
     type TwoToOneHash = TwoToOneHash;
 }
 
-/// A Merkle tree containing some bytes.
+/// A Merkle tree containing some field elements.
 pub type SimpleMerkleTree = MerkleTree<MerkleConfig>;
-/// The root of the byte Merkle tree.
+/// The root of the Merkle tree.
 pub type Root = <TwoToOneHash as TwoToOneCRH>::Output;
-/// A membership proof for a given byte.
+/// A membership proof for a given leaf.
 pub type SimplePath = Path<MerkleConfig>;
 
 /// Creates a merkle tree from a vector of it's leaves
 ///
+/// The leaves are padded with [`padding_leaf`] up to the nearest power of two (but at least 2), as
+/// required by `MerkleTree`. Panics if any of `leaves` is the padding leaf.
+///
 /// Returns a tuple of:
 ///  - the tree
 ///  - the parameters of leaf hashing function
 ///  - the parameters of node combining hashing function
 pub fn new_tree(
-    leaves: Vec<u8>,
+    mut leaves: Vec<CircuitField>,
     seed: [u8; 32],
 ) -> (
     SimpleMerkleTree,
@@ -41,6 +50,12 @@ pub fn new_tree(
     let leaf_crh_params = <LeafHash as CRH>::setup(&mut rng).unwrap();
     let two_to_one_crh_params = <TwoToOneHash as TwoToOneCRH>::setup(&mut rng).unwrap();
 
+    let padding = padding_leaf();
+    assert!(
+        !leaves.contains(&padding),
+        "The padding leaf cannot be a tree leaf"
+    );
+    leaves.resize(leaves.len().next_power_of_two().max(2), padding);
     let tree = SimpleMerkleTree::new(&leaf_crh_params, &two_to_one_crh_params, &leaves).unwrap();
 
     (tree, leaf_crh_params, two_to_one_crh_params)
@@ -2,28 +2,46 @@
 mod linear;
 #[cfg(feature = "merkle_tree")]
 mod merkle_tree;
-#[cfg(any(feature = "deposit", feature = "withdraw"))]
+#[cfg(any(
+    feature = "deposit",
+    feature = "join_split",
+    feature = "transfer",
+    feature = "withdraw"
+))]
 pub mod shielder;
+mod statistics;
 mod types;
+mod witness;
 #[cfg(feature = "xor")]
 mod xor;
 
+use std::{ffi::OsString, path::Path};
+
 use ark_ff::{One, PrimeField, Zero};
-use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef};
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, SynthesisMode,
+};
 use ark_serialize::CanonicalSerialize;
-use clap::Subcommand;
+use blake2::{Blake2s, Digest};
+use clap::{FromArgMatches, Subcommand};
 #[cfg(feature = "linear")]
 pub use linear::LinearEqRelation;
 #[cfg(feature = "merkle_tree")]
-pub use merkle_tree::{MerkleTreeRelation, MerkleTreeRelationArgs};
+pub use merkle_tree::{Leaf as MerkleTreeLeaf, MerkleTreeRelation, MerkleTreeRelationArgs};
 #[cfg(feature = "deposit")]
 pub use shielder::{DepositRelation, DepositRelationArgs};
+#[cfg(feature = "join_split")]
+pub use shielder::{JoinSplitRelation, JoinSplitRelationArgs};
+#[cfg(feature = "transfer")]
+pub use shielder::{TransferRelation, TransferRelationArgs};
 #[cfg(feature = "withdraw")]
 pub use shielder::{WithdrawRelation, WithdrawRelationArgs};
+pub use statistics::ConstraintStatistics;
+pub use witness::{Witness, WitnessFormat, WitnessTemplate};
 #[cfg(feature = "xor")]
 pub use xor::XorRelation;
 
-use crate::relations::types::CircuitField;
+use crate::{relations::types::CircuitField, serialization::serialize};
 
 /// All implemented relations.
 ///
@@ -31,20 +49,23 @@ use crate::relations::types::CircuitField;
 #[derive(Clone, Eq, PartialEq, Hash, Debug, Subcommand)]
 pub enum Relation {
     #[cfg(feature = "xor")]
-    Xor(XorRelation),
+    Xor(Witness<XorRelation>),
     #[cfg(feature = "linear")]
-    LinearEquation(LinearEqRelation),
+    LinearEquation(Witness<LinearEqRelation>),
     #[cfg(feature = "merkle_tree")]
-    MerkleTree(MerkleTreeRelationArgs),
+    MerkleTree(Witness<MerkleTreeRelationArgs>),
     #[cfg(feature = "deposit")]
-    Deposit(DepositRelationArgs),
+    Deposit(Witness<DepositRelationArgs>),
     #[cfg(feature = "withdraw")]
-    Withdraw(WithdrawRelationArgs),
+    Withdraw(Witness<WithdrawRelationArgs>),
+    #[cfg(feature = "join_split")]
+    JoinSplit(Witness<JoinSplitRelationArgs>),
+    #[cfg(feature = "transfer")]
+    Transfer(Witness<TransferRelationArgs>),
 }
 
 impl Relation {
     /// Relation identifier.
-    #[allow(dead_code)]
     pub fn id(&self) -> String {
         match &self {
             #[cfg(feature = "xor")]
@@ -57,16 +78,131 @@ impl Relation {
             Relation::Deposit(_) => String::from("deposit"),
             #[cfg(feature = "withdraw")]
             Relation::Withdraw(_) => String::from("withdraw"),
+            #[cfg(feature = "join_split")]
+            Relation::JoinSplit(_) => String::from("join_split"),
+            #[cfg(feature = "transfer")]
+            Relation::Transfer(_) => String::from("transfer"),
             #[cfg(not(any(
                 feature = "xor",
                 feature = "deposit",
                 feature = "linear",
                 feature = "merkle_tree",
-                feature = "withdraw"
+                feature = "withdraw",
+                feature = "join_split",
+                feature = "transfer"
             )))]
             _ => panic!("No relation available"),
         }
     }
+
+    /// Example witness file for the relation with subcommand `name` (e.g. `linear-equation`).
+    pub fn witness_template(name: &str, format: WitnessFormat) -> String {
+        match name {
+            #[cfg(feature = "xor")]
+            "xor" => format.render(&XorRelation::template()),
+            #[cfg(feature = "linear")]
+            "linear-equation" => format.render(&LinearEqRelation::template()),
+            #[cfg(feature = "merkle_tree")]
+            "merkle-tree" => format.render(&MerkleTreeRelationArgs::template()),
+            #[cfg(feature = "deposit")]
+            "deposit" => format.render(&DepositRelationArgs::template()),
+            #[cfg(feature = "withdraw")]
+            "withdraw" => format.render(&WithdrawRelationArgs::template()),
+            #[cfg(feature = "join_split")]
+            "join-split" => format.render(&JoinSplitRelationArgs::template()),
+            #[cfg(feature = "transfer")]
+            "transfer" => format.render(&TransferRelationArgs::template()),
+            _ => panic!("Unknown relation: {}", name),
+        }
+    }
+
+    /// All relations, with arguments taken from their witness templates.
+    pub fn templates() -> Vec<Relation> {
+        vec![
+            #[cfg(feature = "xor")]
+            Relation::Xor(Witness(XorRelation::template())),
+            #[cfg(feature = "linear")]
+            Relation::LinearEquation(Witness(LinearEqRelation::template())),
+            #[cfg(feature = "merkle_tree")]
+            Relation::MerkleTree(Witness(MerkleTreeRelationArgs::template())),
+            #[cfg(feature = "deposit")]
+            Relation::Deposit(Witness(DepositRelationArgs::template())),
+            #[cfg(feature = "withdraw")]
+            Relation::Withdraw(Witness(WithdrawRelationArgs::template())),
+            #[cfg(feature = "join_split")]
+            Relation::JoinSplit(Witness(JoinSplitRelationArgs::template())),
+            #[cfg(feature = "transfer")]
+            Relation::Transfer(Witness(TransferRelationArgs::template())),
+        ]
+    }
+
+    /// Relation with subcommand `name` (e.g. `linear-equation`) and arguments read from the witness
+    /// file at `path`.
+    pub fn from_witness_file(name: &str, path: &Path) -> Result<Self, String> {
+        let args = [
+            OsString::from("relation"),
+            OsString::from(name),
+            OsString::from("--witness-file"),
+            OsString::from(path),
+        ];
+        let matches = Relation::augment_subcommands(clap::Command::new("relation"))
+            .try_get_matches_from(args)
+            .map_err(|e| e.to_string().trim().to_string())?;
+        Relation::from_arg_matches(&matches).map_err(|e| e.to_string().trim().to_string())
+    }
+
+    /// Hash of the constraint matrices of the relation.
+    ///
+    /// Depends only on the parameters shaping the circuit (like Merkle tree height), but not on
+    /// the actual (public or private) inputs.
+    pub fn parameters_hash(&self) -> [u8; 32] {
+        let matrices = self
+            .synthesize_for_setup()
+            .to_matrices()
+            .expect("Failed to build constraint matrices");
+
+        let mut hasher = Blake2s::new();
+        hasher.update((matrices.num_instance_variables as u64).to_le_bytes());
+        hasher.update((matrices.num_witness_variables as u64).to_le_bytes());
+        for matrix in [matrices.a, matrices.b, matrices.c] {
+            for row in matrix {
+                hasher.update((row.len() as u64).to_le_bytes());
+                for (coefficient, index) in row {
+                    hasher.update(serialize(&coefficient));
+                    hasher.update((index as u64).to_le_bytes());
+                }
+            }
+        }
+        hasher.finalize().into()
+    }
+
+    /// Size of the relation, broken down by namespaces.
+    pub fn constraint_statistics(&self) -> ConstraintStatistics {
+        #[cfg(feature = "std")]
+        let cs = {
+            use ark_relations::r1cs::{ConstraintLayer, TracingMode};
+            use tracing_subscriber::layer::SubscriberExt;
+
+            let subscriber = tracing_subscriber::Registry::default()
+                .with(ConstraintLayer::new(TracingMode::OnlyConstraints));
+            tracing::subscriber::with_default(subscriber, || self.synthesize_for_setup())
+        };
+        #[cfg(not(feature = "std"))]
+        let cs = self.synthesize_for_setup();
+
+        ConstraintStatistics::collect(&cs)
+    }
+
+    /// Generates constraints (without any assignment) and finalizes the constraint system.
+    fn synthesize_for_setup(&self) -> ConstraintSystemRef<CircuitField> {
+        let cs = ConstraintSystem::<CircuitField>::new_ref();
+        cs.set_mode(SynthesisMode::Setup);
+        self.clone()
+            .generate_constraints(cs.clone())
+            .expect("Failed to synthesize relation");
+        cs.finalize();
+        cs
+    }
 }
 
 impl ConstraintSynthesizer<CircuitField> for Relation {
@@ -76,60 +212,131 @@ impl ConstraintSynthesizer<CircuitField> for Relation {
     ) -> ark_relations::r1cs::Result<()> {
         match self {
             #[cfg(feature = "xor")]
-            Relation::Xor(relation @ XorRelation { .. }) => relation.generate_constraints(cs),
+            Relation::Xor(Witness(relation)) => relation.generate_constraints(cs),
             #[cfg(feature = "linear")]
-            Relation::LinearEquation(relation @ LinearEqRelation { .. }) => {
-                relation.generate_constraints(cs)
-            }
+            Relation::LinearEquation(Witness(relation)) => relation.generate_constraints(cs),
             #[cfg(feature = "merkle_tree")]
-            Relation::MerkleTree(args @ MerkleTreeRelationArgs { .. }) => {
+            Relation::MerkleTree(Witness(args)) => {
                 <MerkleTreeRelationArgs as Into<MerkleTreeRelation>>::into(args)
                     .generate_constraints(cs)
             }
             #[cfg(feature = "deposit")]
-            Relation::Deposit(args @ DepositRelationArgs { .. }) => {
+            Relation::Deposit(Witness(args)) => {
                 <DepositRelationArgs as Into<DepositRelation>>::into(args).generate_constraints(cs)
             }
             #[cfg(feature = "withdraw")]
-            Relation::Withdraw(args @ WithdrawRelationArgs { .. }) => {
+            Relation::Withdraw(Witness(args)) => {
                 <WithdrawRelationArgs as Into<WithdrawRelation>>::into(args)
                     .generate_constraints(cs)
             }
+            #[cfg(feature = "join_split")]
+            Relation::JoinSplit(Witness(args)) => {
+                <JoinSplitRelationArgs as Into<JoinSplitRelation>>::into(args)
+                    .generate_constraints(cs)
+            }
+            #[cfg(feature = "transfer")]
+            Relation::Transfer(Witness(args)) => {
+                <TransferRelationArgs as Into<TransferRelation>>::into(args)
+                    .generate_constraints(cs)
+            }
+        }
+    }
+}
+
+/// Only field-generic relations are available over BN254.
+impl ConstraintSynthesizer<ark_bn254::Fr> for Relation {
+    fn generate_constraints(
+        self,
+        cs: ConstraintSystemRef<ark_bn254::Fr>,
+    ) -> ark_relations::r1cs::Result<()> {
+        match self {
+            #[cfg(feature = "xor")]
+            Relation::Xor(Witness(relation)) => relation.generate_constraints(cs),
+            #[cfg(feature = "linear")]
+            Relation::LinearEquation(Witness(relation)) => relation.generate_constraints(cs),
+            #[allow(unreachable_patterns)]
+            relation => panic!(
+                "Relation {} is available only over BLS12-381",
+                relation.id()
+            ),
         }
     }
 }
 
 pub trait GetPublicInput<CircuitField: PrimeField + CanonicalSerialize> {
-    fn public_input(&self) -> Vec<CircuitField> {
+    /// Public input together with the names of its elements, in the order of the circuit.
+    fn named_public_input(&self) -> Vec<(String, CircuitField)> {
         vec![]
     }
+
+    fn public_input(&self) -> Vec<CircuitField> {
+        self.named_public_input()
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect()
+    }
+}
+
+/// Names `values` as consecutive elements of the array `name` (e.g. bits of a number).
+fn name_elements<F>(name: &str, values: impl IntoIterator<Item = F>) -> Vec<(String, F)> {
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(idx, value)| (format!("{}[{}]", name, idx), value))
+        .collect()
 }
 
 impl GetPublicInput<CircuitField> for Relation {
-    fn public_input(&self) -> Vec<CircuitField> {
+    fn named_public_input(&self) -> Vec<(String, CircuitField)> {
         match self {
             #[cfg(feature = "xor")]
-            Relation::Xor(relation @ XorRelation { .. }) => relation.public_input(),
+            Relation::Xor(Witness(relation)) => relation.named_public_input(),
             #[cfg(feature = "linear")]
-            Relation::LinearEquation(relation @ LinearEqRelation { .. }) => relation.public_input(),
+            Relation::LinearEquation(Witness(relation)) => relation.named_public_input(),
             #[cfg(feature = "merkle_tree")]
-            Relation::MerkleTree(args @ MerkleTreeRelationArgs { .. }) => {
+            Relation::MerkleTree(Witness(args)) => {
                 <MerkleTreeRelationArgs as Into<MerkleTreeRelation>>::into(args.to_owned())
-                    .public_input()
+                    .named_public_input()
             }
             #[cfg(feature = "deposit")]
-            Relation::Deposit(args @ DepositRelationArgs { .. }) => {
-                <DepositRelationArgs as Into<DepositRelation>>::into(*args).public_input()
+            Relation::Deposit(Witness(args)) => {
+                <DepositRelationArgs as Into<DepositRelation>>::into(*args).named_public_input()
             }
             #[cfg(feature = "withdraw")]
-            Relation::Withdraw(args @ WithdrawRelationArgs { .. }) => {
+            Relation::Withdraw(Witness(args)) => {
                 <WithdrawRelationArgs as Into<WithdrawRelation>>::into(args.to_owned())
-                    .public_input()
+                    .named_public_input()
+            }
+            #[cfg(feature = "join_split")]
+            Relation::JoinSplit(Witness(args)) => {
+                <JoinSplitRelationArgs as Into<JoinSplitRelation>>::into(args.to_owned())
+                    .named_public_input()
+            }
+            #[cfg(feature = "transfer")]
+            Relation::Transfer(Witness(args)) => {
+                <TransferRelationArgs as Into<TransferRelation>>::into(args.to_owned())
+                    .named_public_input()
             }
         }
     }
 }
 
+impl GetPublicInput<ark_bn254::Fr> for Relation {
+    fn named_public_input(&self) -> Vec<(String, ark_bn254::Fr)> {
+        match self {
+            #[cfg(feature = "xor")]
+            Relation::Xor(Witness(relation)) => relation.named_public_input(),
+            #[cfg(feature = "linear")]
+            Relation::LinearEquation(Witness(relation)) => relation.named_public_input(),
+            #[allow(unreachable_patterns)]
+            relation => panic!(
+                "Relation {} is available only over BLS12-381",
+                relation.id()
+            ),
+        }
+    }
+}
+
 /// Convert `u8` into an 8-tuple of bits over `F` (little endian).
 fn byte_to_bits<F: Zero + One + Copy>(byte: u8) -> [F; 8] {
     let mut bits = [F::zero(); 8];
@@ -5,38 +5,68 @@ use ark_relations::{
     r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
 };
 use clap::Args;
+use serde::{Deserialize, Serialize};
 
 use super::{
-    note::check_note,
-    parser::parse_frontend_note,
+    note::{check_note, check_owner_key, compute_note, compute_owner_key},
+    parser::{parse_frontend_note, parse_frontend_spending_secret, parse_frontend_trapdoor},
     types::{
-        BackendNote, BackendNullifier, BackendTokenAmount, BackendTokenId, BackendTrapdoor, FpVar,
-        FrontendNote, FrontendNullifier, FrontendTokenAmount, FrontendTokenId, FrontendTrapdoor,
+        BackendNote, BackendOwnerKey, BackendSpendingSecret, BackendTokenAmount, BackendTokenId,
+        BackendTrapdoor, FpVar, FrontendNote, FrontendSpendingSecret, FrontendTokenAmount,
+        FrontendTokenId, FrontendTrapdoor,
     },
 };
-use crate::relations::{types::CircuitField, GetPublicInput};
+use crate::relations::{types::CircuitField, witness::WitnessTemplate, GetPublicInput};
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Args)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct DepositRelationArgs {
     // Public inputs.
     #[clap(long, value_parser = parse_frontend_note)]
+    #[serde(with = "super::parser::note_hex")]
     pub note: FrontendNote,
     #[clap(long)]
+    #[serde(with = "crate::relations::witness::integer")]
     pub token_id: FrontendTokenId,
     #[clap(long)]
+    #[serde(with = "crate::relations::witness::integer")]
     pub token_amount: FrontendTokenAmount,
 
     // Private inputs.
-    #[clap(long)]
+    #[clap(long, value_parser = parse_frontend_trapdoor)]
+    #[serde(with = "super::parser::note_hex")]
     pub trapdoor: FrontendTrapdoor,
-    #[clap(long)]
-    pub nullifier: FrontendNullifier,
+    #[clap(long, value_parser = parse_frontend_spending_secret)]
+    #[serde(with = "super::parser::note_hex")]
+    pub spending_secret: FrontendSpendingSecret,
+}
+
+impl WitnessTemplate for DepositRelationArgs {
+    fn template() -> Self {
+        let (token_id, token_amount, trapdoor, spending_secret) =
+            (1, 10, [17, 0, 0, 0], [19, 0, 0, 0]);
+        DepositRelationArgs {
+            note: compute_note(
+                token_id,
+                token_amount,
+                trapdoor,
+                compute_owner_key(spending_secret),
+            ),
+            token_id,
+            token_amount,
+            trapdoor,
+            spending_secret,
+        }
+    }
 }
 
 /// 'Deposit' relation for the Shielder application.
 ///
-/// It expresses the fact that `note` is a prefix of the result of tangling together `token_id`,
-/// `token_amount`, `trapdoor` and `nullifier`.
+/// It expresses the facts that:
+///  - `owner_key` is the result of tangling `spending_secret`,
+///  - `note` is a prefix of the result of tangling together `token_id`, `token_amount`, `trapdoor`
+///    and `owner_key`.
+/// Thus, the depositor proves that they own the new note.
 ///
 /// When providing a public input to proof verification, you should keep the order of variable
 /// declarations in circuit, i.e.: `note`, `token_id`, `token_amount`.
@@ -49,7 +79,9 @@ pub struct DepositRelation {
 
     // Private inputs.
     pub trapdoor: BackendTrapdoor,
-    pub nullifier: BackendNullifier,
+    pub spending_secret: BackendSpendingSecret,
+    /// Derived from `spending_secret`.
+    pub owner_key: BackendOwnerKey,
 }
 
 impl DepositRelation {
@@ -58,14 +90,17 @@ impl DepositRelation {
         token_id: FrontendTokenId,
         token_amount: FrontendTokenAmount,
         trapdoor: FrontendTrapdoor,
-        nullifier: FrontendNullifier,
+        spending_secret: FrontendSpendingSecret,
     ) -> Self {
         Self {
             note: BackendNote::from(BigInteger256::new(note)),
             token_id: BackendTokenId::from(token_id),
             token_amount: BackendTokenAmount::from(token_amount),
-            trapdoor: BackendTrapdoor::from(trapdoor),
-            nullifier: BackendNullifier::from(nullifier),
+            trapdoor: BackendTrapdoor::from(BigInteger256::new(trapdoor)),
+            spending_secret: BackendSpendingSecret::from(BigInteger256::new(spending_secret)),
+            owner_key: BackendOwnerKey::from(BigInteger256::new(compute_owner_key(
+                spending_secret,
+            ))),
         }
     }
 }
@@ -77,9 +112,9 @@ impl From<DepositRelationArgs> for DepositRelation {
             token_id,
             token_amount,
             trapdoor,
-            nullifier,
+            spending_secret,
         } = args;
-        DepositRelation::new(note, token_id, token_amount, trapdoor, nullifier)
+        DepositRelation::new(note, token_id, token_amount, trapdoor, spending_secret)
     }
 }
 
@@ -93,15 +128,22 @@ impl ConstraintSynthesizer<CircuitField> for DepositRelation {
         let token_amount = FpVar::new_input(ns!(cs, "token amount"), || Ok(&self.token_amount))?;
 
         let trapdoor = FpVar::new_witness(ns!(cs, "trapdoor"), || Ok(&self.trapdoor))?;
-        let nullifier = FpVar::new_witness(ns!(cs, "nullifier"), || Ok(&self.nullifier))?;
+        let spending_secret =
+            FpVar::new_witness(ns!(cs, "spending secret"), || Ok(&self.spending_secret))?;
+        let owner_key = FpVar::new_witness(ns!(cs, "owner key"), || Ok(&self.owner_key))?;
 
-        check_note(&token_id, &token_amount, &trapdoor, &nullifier, &note)
+        check_owner_key(&spending_secret, &owner_key)?;
+        check_note(&token_id, &token_amount, &trapdoor, &owner_key, &note)
     }
 }
 
 impl GetPublicInput<CircuitField> for DepositRelation {
-    fn public_input(&self) -> Vec<CircuitField> {
-        vec![self.note, self.token_id, self.token_amount]
+    fn named_public_input(&self) -> Vec<(String, CircuitField)> {
+        vec![
+            (String::from("note"), self.note),
+            (String::from("token_id"), self.token_id),
+            (String::from("token_amount"), self.token_amount),
+        ]
     }
 }
 
@@ -113,16 +155,23 @@ mod tests {
     use ark_snark::SNARK;
 
     use super::*;
-    use crate::relations::shielder::note::compute_note;
+    use crate::relations::shielder::note::{
+        compute_note, generate_spending_secret, generate_trapdoor,
+    };
 
     fn get_circuit_and_input() -> (DepositRelation, [CircuitField; 3]) {
         let token_id: FrontendTokenId = 1;
         let token_amount: FrontendTokenAmount = 10;
-        let trapdoor: FrontendTrapdoor = 17;
-        let nullifier: FrontendNullifier = 19;
-        let note = compute_note(token_id, token_amount, trapdoor, nullifier);
+        let trapdoor: FrontendTrapdoor = [17, 0, 0, 0];
+        let spending_secret: FrontendSpendingSecret = [19, 0, 0, 0];
+        let note = compute_note(
+            token_id,
+            token_amount,
+            trapdoor,
+            compute_owner_key(spending_secret),
+        );
 
-        let circuit = DepositRelation::new(note, token_id, token_amount, trapdoor, nullifier);
+        let circuit = DepositRelation::new(note, token_id, token_amount, trapdoor, spending_secret);
         let input = [
             CircuitField::from(BigInteger256::new(note)),
             CircuitField::from(token_id),
@@ -147,6 +196,49 @@ mod tests {
         assert!(is_satisfied);
     }
 
+    #[test]
+    fn deposit_with_wrong_note_is_unsatisfiable() {
+        let (circuit, _input) = get_circuit_and_input();
+        let wrong_note = compute_note(1, 10, [17, 0, 0, 0], compute_owner_key([20, 0, 0, 0]));
+        let circuit = DepositRelation {
+            note: BackendNote::from(BigInteger256::new(wrong_note)),
+            ..circuit
+        };
+
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn deposit_with_wrong_spending_secret_is_unsatisfiable() {
+        let (circuit, _input) = get_circuit_and_input();
+        let circuit = DepositRelation {
+            spending_secret: BackendSpendingSecret::from(20u64),
+            ..circuit
+        };
+
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn deposit_with_full_field_trapdoor_is_satisfiable() {
+        let mut rng = ark_std::test_rng();
+        let trapdoor = generate_trapdoor(&mut rng);
+        let spending_secret = generate_spending_secret(&mut rng);
+        let note = compute_note(1, 10, trapdoor, compute_owner_key(spending_secret));
+        let circuit = DepositRelation::new(note, 1, 10, trapdoor, spending_secret);
+
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+
+        assert!(cs.is_satisfied().unwrap());
+    }
+
     #[test]
     fn deposit_proving_procedure() {
         let (circuit, input) = get_circuit_and_input();
@@ -0,0 +1,605 @@
+use ark_ff::BigInteger256;
+use ark_r1cs_std::{alloc::AllocVar, eq::EqGadget};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+use clap::Args;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    note::{
+        check_merkle_proof, check_note, check_nullifier, check_owner_key, compute_note,
+        compute_nullifier, compute_owner_key, compute_parent_hash,
+    },
+    parser::{
+        parse_frontend_merkle_path_single, parse_frontend_merkle_root, parse_frontend_note,
+        parse_frontend_nullifier, parse_frontend_spending_secret, parse_frontend_trapdoor,
+        validate_merkle_path_len,
+    },
+    range_check::enforce_fits_in_bits,
+    types::{
+        BackendLeafIndex, BackendMerklePath, BackendMerkleRoot, BackendNote, BackendNullifier,
+        BackendOwnerKey, BackendSpendingSecret, BackendTokenAmount, BackendTokenId,
+        BackendTrapdoor, FpVar, FrontendLeafIndex, FrontendMerklePath, FrontendMerkleRoot,
+        FrontendNote, FrontendNullifier, FrontendSpendingSecret, FrontendTokenAmount,
+        FrontendTokenId, FrontendTrapdoor, TOKEN_AMOUNT_BITS,
+    },
+    CircuitField,
+};
+use crate::relations::{witness::WitnessTemplate, GetPublicInput};
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JoinSplitRelationArgs {
+    // Constant parameters.
+    #[clap(long, default_value = "16")]
+    #[serde(with = "crate::relations::witness::integer")]
+    pub max_path_len: u8,
+
+    // Public inputs.
+    #[clap(long)]
+    #[serde(with = "crate::relations::witness::integer")]
+    pub token_id: FrontendTokenId,
+    #[clap(long, value_parser = parse_frontend_merkle_root)]
+    #[serde(with = "super::parser::note_hex")]
+    pub merkle_root: FrontendMerkleRoot,
+    #[clap(long, value_parser = parse_frontend_nullifier)]
+    #[serde(with = "super::parser::note_hex")]
+    pub old_nullifier_1: FrontendNullifier,
+    #[clap(long, value_parser = parse_frontend_nullifier)]
+    #[serde(with = "super::parser::note_hex")]
+    pub old_nullifier_2: FrontendNullifier,
+    #[clap(long, value_parser = parse_frontend_note)]
+    #[serde(with = "super::parser::note_hex")]
+    pub new_note_1: FrontendNote,
+    #[clap(long, value_parser = parse_frontend_note)]
+    #[serde(with = "super::parser::note_hex")]
+    pub new_note_2: FrontendNote,
+
+    // Private inputs.
+    #[clap(long, value_parser = parse_frontend_spending_secret)]
+    #[serde(with = "super::parser::note_hex")]
+    pub spending_secret: FrontendSpendingSecret,
+
+    #[clap(long, value_parser = parse_frontend_note)]
+    #[serde(with = "super::parser::note_hex")]
+    pub old_note_1: FrontendNote,
+    #[clap(long, value_parser = parse_frontend_trapdoor)]
+    #[serde(with = "super::parser::note_hex")]
+    pub old_trapdoor_1: FrontendTrapdoor,
+    #[clap(long)]
+    #[serde(with = "crate::relations::witness::integer")]
+    pub old_token_amount_1: FrontendTokenAmount,
+    #[clap(long, value_delimiter = ',', value_parser = parse_frontend_merkle_path_single)]
+    #[serde(with = "super::parser::merkle_path_hex")]
+    pub merkle_path_1: FrontendMerklePath,
+    #[clap(long)]
+    #[serde(with = "crate::relations::witness::integer")]
+    pub leaf_index_1: FrontendLeafIndex,
+
+    #[clap(long, value_parser = parse_frontend_note)]
+    #[serde(with = "super::parser::note_hex")]
+    pub old_note_2: FrontendNote,
+    #[clap(long, value_parser = parse_frontend_trapdoor)]
+    #[serde(with = "super::parser::note_hex")]
+    pub old_trapdoor_2: FrontendTrapdoor,
+    #[clap(long)]
+    #[serde(with = "crate::relations::witness::integer")]
+    pub old_token_amount_2: FrontendTokenAmount,
+    #[clap(long, value_delimiter = ',', value_parser = parse_frontend_merkle_path_single)]
+    #[serde(with = "super::parser::merkle_path_hex")]
+    pub merkle_path_2: FrontendMerklePath,
+    #[clap(long)]
+    #[serde(with = "crate::relations::witness::integer")]
+    pub leaf_index_2: FrontendLeafIndex,
+
+    #[clap(long, value_parser = parse_frontend_trapdoor)]
+    #[serde(with = "super::parser::note_hex")]
+    pub new_trapdoor_1: FrontendTrapdoor,
+    #[clap(long)]
+    #[serde(with = "crate::relations::witness::integer")]
+    pub new_token_amount_1: FrontendTokenAmount,
+
+    #[clap(long, value_parser = parse_frontend_trapdoor)]
+    #[serde(with = "super::parser::note_hex")]
+    pub new_trapdoor_2: FrontendTrapdoor,
+    #[clap(long)]
+    #[serde(with = "crate::relations::witness::integer")]
+    pub new_token_amount_2: FrontendTokenAmount,
+}
+
+impl WitnessTemplate for JoinSplitRelationArgs {
+    /// Merging notes at leaves `0` and `3` of a tree with 4 leaves (with 10 and 5 tokens) and
+    /// splitting them into notes with 12 and 3 tokens.
+    fn template() -> Self {
+        let token_id = 1;
+        let spending_secret = [19, 0, 0, 0];
+        let (old_trapdoor_1, old_token_amount_1, leaf_index_1) = ([17, 0, 0, 0], 10, 0);
+        let (old_trapdoor_2, old_token_amount_2, leaf_index_2) = ([23, 0, 0, 0], 5, 3);
+        let (new_trapdoor_1, new_token_amount_1) = ([27, 0, 0, 0], 12);
+        let (new_trapdoor_2, new_token_amount_2) = ([31, 0, 0, 0], 3);
+
+        let owner_key = compute_owner_key(spending_secret);
+        let old_note_1 = compute_note(token_id, old_token_amount_1, old_trapdoor_1, owner_key);
+        let old_note_2 = compute_note(token_id, old_token_amount_2, old_trapdoor_2, owner_key);
+        let leaves = [
+            old_note_1,
+            compute_note(0, 1, [2, 0, 0, 0], [3, 0, 0, 0]),
+            compute_note(4, 5, [6, 0, 0, 0], [7, 0, 0, 0]),
+            old_note_2,
+        ];
+        let parents = [
+            compute_parent_hash(leaves[0], leaves[1]),
+            compute_parent_hash(leaves[2], leaves[3]),
+        ];
+
+        JoinSplitRelationArgs {
+            max_path_len: 2,
+            token_id,
+            merkle_root: compute_parent_hash(parents[0], parents[1]),
+            old_nullifier_1: compute_nullifier(spending_secret, leaf_index_1),
+            old_nullifier_2: compute_nullifier(spending_secret, leaf_index_2),
+            new_note_1: compute_note(token_id, new_token_amount_1, new_trapdoor_1, owner_key),
+            new_note_2: compute_note(token_id, new_token_amount_2, new_trapdoor_2, owner_key),
+            spending_secret,
+            old_note_1,
+            old_trapdoor_1,
+            old_token_amount_1,
+            merkle_path_1: vec![leaves[1], parents[1]],
+            leaf_index_1,
+            old_note_2,
+            old_trapdoor_2,
+            old_token_amount_2,
+            merkle_path_2: vec![leaves[2], parents[0]],
+            leaf_index_2,
+            new_trapdoor_1,
+            new_token_amount_1,
+            new_trapdoor_2,
+            new_token_amount_2,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        validate_merkle_path_len(&self.merkle_path_1, self.max_path_len)?;
+        validate_merkle_path_len(&self.merkle_path_2, self.max_path_len)
+    }
+}
+
+/// 'JoinSplit' relation for the Shielder application: merging and splitting notes without
+/// withdrawing them.
+///
+/// It expresses the facts that `owner_key` is the result of tangling `spending_secret`, and for both
+/// old notes (`i` being `1` or `2`):
+///  - `old_note_i` is a prefix of the result of tangling together `token_id`, `old_token_amount_i`,
+///    `old_trapdoor_i` and `owner_key`,
+///  - `old_nullifier_i` is the result of tangling together `spending_secret` and `leaf_index_i`,
+///  - `merkle_path_i` is a valid Merkle proof for `old_note_i` being present at `leaf_index_i` in
+///    some Merkle tree with `merkle_root` hash in the root,
+///  - `leaf_index_i` is smaller than `2^max_path_len`
+/// and for both new notes:
+///  - `new_note_i` is a prefix of the result of tangling together `token_id`, `new_token_amount_i`,
+///    `new_trapdoor_i` and `owner_key`.
+/// Moreover:
+///  - `old_nullifier_1` and `old_nullifier_2` differ (so that a note cannot be spent twice in the
+///    same transaction),
+///  - all the amounts are 64-bit values and
+///    `old_token_amount_1 + old_token_amount_2 = new_token_amount_1 + new_token_amount_2`.
+/// All notes are of the same token and have the same owner, so the value is conserved per `token_id`
+/// and owner.
+///
+/// When providing a public input to proof verification, you should keep the order of variable
+/// declarations in circuit, i.e.: `token_id`, `merkle_root`, `old_nullifier_1`, `old_nullifier_2`,
+/// `new_note_1`, `new_note_2`.
+#[derive(Clone)]
+pub struct JoinSplitRelation {
+    // Constant parameters.
+    pub max_path_len: u8,
+
+    // Public inputs.
+    pub token_id: BackendTokenId,
+    pub merkle_root: BackendMerkleRoot,
+    pub old_nullifiers: [BackendNullifier; 2],
+    pub new_notes: [BackendNote; 2],
+
+    // Private inputs.
+    pub spending_secret: BackendSpendingSecret,
+    /// Derived from `spending_secret`.
+    pub owner_key: BackendOwnerKey,
+    pub old_notes: [BackendNote; 2],
+    pub old_trapdoors: [BackendTrapdoor; 2],
+    pub old_token_amounts: [BackendTokenAmount; 2],
+    pub merkle_paths: [BackendMerklePath; 2],
+    pub leaf_indices: [BackendLeafIndex; 2],
+    pub new_trapdoors: [BackendTrapdoor; 2],
+    pub new_token_amounts: [BackendTokenAmount; 2],
+}
+
+fn to_backend_merkle_path(merkle_path: FrontendMerklePath) -> BackendMerklePath {
+    merkle_path
+        .into_iter()
+        .map(|node| BackendNote::from(BigInteger256::new(node)))
+        .collect()
+}
+
+impl From<JoinSplitRelationArgs> for JoinSplitRelation {
+    fn from(args: JoinSplitRelationArgs) -> Self {
+        let JoinSplitRelationArgs {
+            max_path_len,
+            token_id,
+            merkle_root,
+            old_nullifier_1,
+            old_nullifier_2,
+            new_note_1,
+            new_note_2,
+            spending_secret,
+            old_note_1,
+            old_trapdoor_1,
+            old_token_amount_1,
+            merkle_path_1,
+            leaf_index_1,
+            old_note_2,
+            old_trapdoor_2,
+            old_token_amount_2,
+            merkle_path_2,
+            leaf_index_2,
+            new_trapdoor_1,
+            new_token_amount_1,
+            new_trapdoor_2,
+            new_token_amount_2,
+        } = args;
+        for merkle_path in [&merkle_path_1, &merkle_path_2] {
+            assert_eq!(
+                merkle_path.len(),
+                max_path_len as usize,
+                "Merkle path length doesn't match the tree height"
+            );
+        }
+
+        Self {
+            max_path_len,
+            token_id: BackendTokenId::from(token_id),
+            merkle_root: BackendMerkleRoot::from(BigInteger256::new(merkle_root)),
+            old_nullifiers: [
+                BackendNullifier::from(BigInteger256::new(old_nullifier_1)),
+                BackendNullifier::from(BigInteger256::new(old_nullifier_2)),
+            ],
+            new_notes: [
+                BackendNote::from(BigInteger256::new(new_note_1)),
+                BackendNote::from(BigInteger256::new(new_note_2)),
+            ],
+            spending_secret: BackendSpendingSecret::from(BigInteger256::new(spending_secret)),
+            owner_key: BackendOwnerKey::from(BigInteger256::new(compute_owner_key(
+                spending_secret,
+            ))),
+            old_notes: [
+                BackendNote::from(BigInteger256::new(old_note_1)),
+                BackendNote::from(BigInteger256::new(old_note_2)),
+            ],
+            old_trapdoors: [
+                BackendTrapdoor::from(BigInteger256::new(old_trapdoor_1)),
+                BackendTrapdoor::from(BigInteger256::new(old_trapdoor_2)),
+            ],
+            old_token_amounts: [
+                BackendTokenAmount::from(old_token_amount_1),
+                BackendTokenAmount::from(old_token_amount_2),
+            ],
+            merkle_paths: [
+                to_backend_merkle_path(merkle_path_1),
+                to_backend_merkle_path(merkle_path_2),
+            ],
+            leaf_indices: [
+                BackendLeafIndex::from(leaf_index_1),
+                BackendLeafIndex::from(leaf_index_2),
+            ],
+            new_trapdoors: [
+                BackendTrapdoor::from(BigInteger256::new(new_trapdoor_1)),
+                BackendTrapdoor::from(BigInteger256::new(new_trapdoor_2)),
+            ],
+            new_token_amounts: [
+                BackendTokenAmount::from(new_token_amount_1),
+                BackendTokenAmount::from(new_token_amount_2),
+            ],
+        }
+    }
+}
+
+impl JoinSplitRelation {
+    /// Check that the `i`-th old note is owned by `spending_secret` (with `owner_key`), is present in
+    /// the tree and matches `nullifier`. Returns its token amount.
+    #[allow(clippy::too_many_arguments)]
+    fn check_old_note(
+        &self,
+        cs: ConstraintSystemRef<CircuitField>,
+        i: usize,
+        token_id: &FpVar,
+        merkle_root: &FpVar,
+        spending_secret: &FpVar,
+        owner_key: &FpVar,
+        nullifier: &FpVar,
+    ) -> Result<FpVar, SynthesisError> {
+        let note = FpVar::new_witness(ns!(cs, "old note"), || Ok(&self.old_notes[i]))?;
+        let token_amount = FpVar::new_witness(ns!(cs, "old token amount"), || {
+            Ok(&self.old_token_amounts[i])
+        })?;
+        let trapdoor = FpVar::new_witness(ns!(cs, "old trapdoor"), || Ok(&self.old_trapdoors[i]))?;
+
+        check_note(token_id, &token_amount, &trapdoor, owner_key, &note)?;
+
+        let leaf_index = FpVar::new_witness(ns!(cs, "leaf index"), || Ok(&self.leaf_indices[i]))?;
+        check_nullifier(spending_secret, &leaf_index, nullifier)?;
+        check_merkle_proof(cs, merkle_root, &note, &leaf_index, &self.merkle_paths[i])?;
+
+        Ok(token_amount)
+    }
+
+    /// Check that `note` is the `i`-th new note, owned by `owner_key`. Returns its token amount.
+    fn check_new_note(
+        &self,
+        cs: ConstraintSystemRef<CircuitField>,
+        i: usize,
+        token_id: &FpVar,
+        owner_key: &FpVar,
+        note: &FpVar,
+    ) -> Result<FpVar, SynthesisError> {
+        let token_amount = FpVar::new_witness(ns!(cs, "new token amount"), || {
+            Ok(&self.new_token_amounts[i])
+        })?;
+        let trapdoor = FpVar::new_witness(ns!(cs, "new trapdoor"), || Ok(&self.new_trapdoors[i]))?;
+
+        check_note(token_id, &token_amount, &trapdoor, owner_key, note)?;
+
+        Ok(token_amount)
+    }
+}
+
+impl ConstraintSynthesizer<CircuitField> for JoinSplitRelation {
+    fn generate_constraints(
+        self,
+        cs: ConstraintSystemRef<CircuitField>,
+    ) -> Result<(), SynthesisError> {
+        //-----------------------
+        // Declare public inputs.
+        //-----------------------
+        let token_id = FpVar::new_input(ns!(cs, "token id"), || Ok(&self.token_id))?;
+        let merkle_root = FpVar::new_input(ns!(cs, "merkle root"), || Ok(&self.merkle_root))?;
+        let old_nullifiers = self
+            .old_nullifiers
+            .iter()
+            .map(|nullifier| FpVar::new_input(ns!(cs, "old nullifier"), || Ok(nullifier)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let new_notes = self
+            .new_notes
+            .iter()
+            .map(|note| FpVar::new_input(ns!(cs, "new note"), || Ok(note)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        //---------------------
+        // Check the ownership.
+        //---------------------
+        let spending_secret =
+            FpVar::new_witness(ns!(cs, "spending secret"), || Ok(&self.spending_secret))?;
+        let owner_key = FpVar::new_witness(ns!(cs, "owner key"), || Ok(&self.owner_key))?;
+
+        check_owner_key(&spending_secret, &owner_key)?;
+
+        //-------------------------------------------
+        // Check the old notes and their Merkle proofs.
+        //-------------------------------------------
+        let old_token_amounts = old_nullifiers
+            .iter()
+            .enumerate()
+            .map(|(i, nullifier)| {
+                self.check_old_note(
+                    cs.clone(),
+                    i,
+                    &token_id,
+                    &merkle_root,
+                    &spending_secret,
+                    &owner_key,
+                    nullifier,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        old_nullifiers[0].enforce_not_equal(&old_nullifiers[1])?;
+
+        //---------------------
+        // Check the new notes.
+        //---------------------
+        let new_token_amounts = new_notes
+            .iter()
+            .enumerate()
+            .map(|(i, note)| self.check_new_note(cs.clone(), i, &token_id, &owner_key, note))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        //----------------------------------
+        // Check the token values soundness.
+        //----------------------------------
+        // All the amounts must be proper `u64` values - otherwise, the sums below could wrap around
+        // the field modulus.
+        for amount in old_token_amounts.iter().chain(&new_token_amounts) {
+            enforce_fits_in_bits(amount, TOKEN_AMOUNT_BITS)?;
+        }
+        let old_sum = &old_token_amounts[0] + &old_token_amounts[1];
+        let new_sum = &new_token_amounts[0] + &new_token_amounts[1];
+        old_sum.enforce_equal(&new_sum)
+    }
+}
+
+impl GetPublicInput<CircuitField> for JoinSplitRelation {
+    fn named_public_input(&self) -> Vec<(String, CircuitField)> {
+        [
+            ("token_id", self.token_id),
+            ("merkle_root", self.merkle_root),
+            ("old_nullifier_1", self.old_nullifiers[0]),
+            ("old_nullifier_2", self.old_nullifiers[1]),
+            ("new_note_1", self.new_notes[0]),
+            ("new_note_2", self.new_notes[1]),
+        ]
+        .into_iter()
+        .map(|(name, value)| (String::from(name), value))
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_bls12_381::Bls12_381;
+    use ark_ff::One;
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+
+    use super::*;
+    use crate::relations::shielder::note::compute_note_from_backend;
+
+    fn is_satisfiable(circuit: JoinSplitRelation) -> bool {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    /// Template with the new notes holding `new_token_amounts` instead.
+    fn with_new_token_amounts(
+        new_token_amounts: [FrontendTokenAmount; 2],
+    ) -> JoinSplitRelationArgs {
+        let args = JoinSplitRelationArgs::template();
+        JoinSplitRelationArgs {
+            new_note_1: compute_note(
+                args.token_id,
+                new_token_amounts[0],
+                args.new_trapdoor_1,
+                compute_owner_key(args.spending_secret),
+            ),
+            new_token_amount_1: new_token_amounts[0],
+            new_note_2: compute_note(
+                args.token_id,
+                new_token_amounts[1],
+                args.new_trapdoor_2,
+                compute_owner_key(args.spending_secret),
+            ),
+            new_token_amount_2: new_token_amounts[1],
+            ..args
+        }
+    }
+
+    #[test]
+    fn join_split_constraints_correctness() {
+        let circuit: JoinSplitRelation = JoinSplitRelationArgs::template().into();
+
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+
+        let is_satisfied = cs.is_satisfied().unwrap();
+        if !is_satisfied {
+            println!("{:?}", cs.which_is_unsatisfied());
+        }
+
+        assert!(is_satisfied);
+    }
+
+    #[test]
+    fn notes_can_be_merged_and_split_arbitrarily() {
+        for new_token_amounts in [[15, 0], [0, 15], [7, 8]] {
+            assert!(is_satisfiable(
+                with_new_token_amounts(new_token_amounts).into()
+            ));
+        }
+    }
+
+    #[test]
+    fn join_split_creating_value_is_unsatisfiable() {
+        assert!(!is_satisfiable(with_new_token_amounts([12, 4]).into()));
+    }
+
+    #[test]
+    fn join_split_destroying_value_is_unsatisfiable() {
+        assert!(!is_satisfiable(with_new_token_amounts([12, 2]).into()));
+    }
+
+    #[test]
+    fn join_split_with_overflowing_new_token_amount_is_unsatisfiable() {
+        // `-1` and `16` sum up to `15` over the field.
+        let circuit = JoinSplitRelation::from(JoinSplitRelationArgs::template());
+        let new_token_amounts = [-BackendTokenAmount::one(), BackendTokenAmount::from(16u64)];
+        let new_notes = [0, 1].map(|i| {
+            BackendNote::from(BigInteger256::new(compute_note_from_backend(
+                circuit.token_id,
+                new_token_amounts[i],
+                circuit.new_trapdoors[i],
+                circuit.owner_key,
+            )))
+        });
+        let circuit = JoinSplitRelation {
+            new_notes,
+            new_token_amounts,
+            ..circuit
+        };
+
+        assert!(!is_satisfiable(circuit));
+    }
+
+    #[test]
+    fn join_split_with_other_token_is_unsatisfiable() {
+        let circuit = JoinSplitRelation {
+            token_id: BackendTokenId::from(2u16),
+            ..JoinSplitRelation::from(JoinSplitRelationArgs::template())
+        };
+
+        assert!(!is_satisfiable(circuit));
+    }
+
+    #[test]
+    fn join_split_with_wrong_spending_secret_is_unsatisfiable() {
+        let circuit = JoinSplitRelation {
+            spending_secret: BackendSpendingSecret::from(20u64),
+            ..JoinSplitRelation::from(JoinSplitRelationArgs::template())
+        };
+
+        assert!(!is_satisfiable(circuit));
+    }
+
+    #[test]
+    fn join_split_with_wrong_merkle_path_is_unsatisfiable() {
+        let mut circuit: JoinSplitRelation = JoinSplitRelationArgs::template().into();
+        circuit.merkle_paths[1].swap(0, 1);
+
+        assert!(!is_satisfiable(circuit));
+    }
+
+    #[test]
+    fn spending_the_same_note_twice_is_unsatisfiable() {
+        // Both old notes are the first one, and the new notes sum up to twice its amount.
+        let args = with_new_token_amounts([17, 3]);
+        let args = JoinSplitRelationArgs {
+            old_nullifier_2: args.old_nullifier_1,
+            old_note_2: args.old_note_1,
+            old_trapdoor_2: args.old_trapdoor_1,
+            old_token_amount_2: args.old_token_amount_1,
+            merkle_path_2: args.merkle_path_1.clone(),
+            leaf_index_2: args.leaf_index_1,
+            ..args
+        };
+
+        assert!(!is_satisfiable(args.into()));
+    }
+
+    #[test]
+    fn join_split_proving_procedure() {
+        let circuit: JoinSplitRelation = JoinSplitRelationArgs::template().into();
+        let input = circuit.public_input();
+
+        let mut rng = ark_std::test_rng();
+        let (pk, vk) =
+            Groth16::<Bls12_381>::circuit_specific_setup(circuit.clone(), &mut rng).unwrap();
+
+        let proof = Groth16::prove(&pk, circuit, &mut rng).unwrap();
+        let valid_proof = Groth16::verify(&vk, &input, &proof).unwrap();
+        assert!(valid_proof);
+
+        let mut input_with_swapped_notes = input.clone();
+        input_with_swapped_notes.swap(4, 5);
+        let valid_proof = Groth16::verify(&vk, &input_with_swapped_notes, &proof).unwrap();
+        assert!(!valid_proof);
+    }
+}
@@ -1,28 +1,48 @@
 //! This module contains two relations that are the core of the Shielder application: `deposit` and
-//! `withdraw`. It also exposes some functions and types that might be useful for input generation.
+//! `withdraw`, together with `join-split` for merging and splitting notes and `transfer` for sending
+//! notes to other users. It also exposes some functions and types that might be useful for input
+//! generation.
 //!
-//! Currently, instead of using some real hash function, we chose to incorporate a simple tangling
-//! algorithm. Essentially, it is a procedure that just mangles a byte sequence.
+//! By default, instead of using some real hash function, we chose to incorporate a simple tangling
+//! algorithm. Essentially, it is a procedure that just mangles a byte sequence. With the `poseidon`
+//! feature enabled, notes and Merkle tree nodes are computed with Poseidon hash instead.
 
 #[allow(dead_code)]
 mod deposit;
 #[allow(dead_code)]
+mod join_split;
+#[allow(dead_code)]
 mod note;
 #[allow(dead_code)]
 mod parser;
+#[cfg(feature = "poseidon")]
+#[allow(dead_code)]
+mod poseidon;
+#[allow(dead_code)]
+mod range_check;
 #[allow(dead_code)]
 mod tangle;
 #[allow(dead_code)]
+mod transfer;
+#[allow(dead_code)]
 mod types;
 #[allow(dead_code)]
 mod withdraw;
 
 pub use deposit::{DepositRelation, DepositRelationArgs};
-pub use note::{bytes_from_note, compute_note, note_from_bytes};
+pub use join_split::{JoinSplitRelation, JoinSplitRelationArgs};
+pub use note::{
+    bytes_from_note, compute_note, compute_nullifier, compute_owner_key, generate_spending_secret,
+    generate_transfer_keys, generate_trapdoor, note_from_bytes, receive_transferred_note,
+    transfer_note, TransferredNote,
+};
+pub use transfer::{TransferRelation, TransferRelationArgs};
 pub use types::{
     FrontendMerklePath as MerklePath, FrontendMerkleRoot as MerkleRoot, FrontendNote as Note,
-    FrontendNullifier as Nullifier, FrontendTokenAmount as TokenAmount, FrontendTokenId as TokenId,
-    FrontendTrapdoor as Trapdoor,
+    FrontendNullifier as Nullifier, FrontendOwnerKey as OwnerKey,
+    FrontendSpendingSecret as SpendingSecret, FrontendTokenAmount as TokenAmount,
+    FrontendTokenId as TokenId, FrontendTransferKey as TransferKey,
+    FrontendTransferSecret as TransferSecret, FrontendTrapdoor as Trapdoor,
 };
 pub use withdraw::{WithdrawRelation, WithdrawRelationArgs};
 
@@ -1,70 +1,604 @@
 //! Module exposing some utilities regarding note generation and verification.
+//!
+//! Notes are bound to a spending secret: every note contains the owner key (the hash of the
+//! secret), and spending it reveals the nullifier - the hash of the secret and the leaf index of
+//! the note. Hence, only the owner of the secret can spend a note, and every note can be spent at
+//! most once.
+//!
+//! Notes and Merkle tree nodes are computed with tangling by default, or with Poseidon hash when
+//! the `poseidon` feature is enabled. Both variants expose the same API.
+//!
+//! Notes can also be created for somebody else (private transfers), given their owner key. The
+//! sender and the recipient share a secret (Diffie-Hellman over Jubjub), from which the trapdoor of
+//! the note is derived. The sender publishes an ephemeral key and the encrypted token amount, so
+//! that the recipient can recover the note and later spend it.
 
-use ark_ff::{BigInteger, BigInteger256};
-use ark_r1cs_std::{eq::EqGadget, ToBytesGadget};
-use ark_relations::r1cs::SynthesisError;
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ed_on_bls12_381::{EdwardsAffine, Fq, Fr as JubjubScalar};
+use ark_ff::{PrimeField, UniformRand, Zero};
+use ark_r1cs_std::alloc::AllocVar;
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSystemRef, SynthesisError},
+};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::{CryptoRng, RngCore};
+use blake2::{Blake2s, Digest};
+#[cfg(feature = "poseidon")]
+pub(super) use poseidon_hashing::{
+    check_merkle_root, check_note, check_nullifier, check_owner_key, compute_parent_hash_in_field,
+    select_merkle_node, to_merkle_node,
+};
+#[cfg(feature = "poseidon")]
+pub use poseidon_hashing::{
+    compute_note, compute_note_from_backend, compute_nullifier, compute_owner_key,
+    compute_parent_hash,
+};
+#[cfg(not(feature = "poseidon"))]
+pub(super) use tangle_hashing::{
+    check_merkle_root, check_note, check_nullifier, check_owner_key, compute_parent_hash_in_field,
+    select_merkle_node, to_merkle_node,
+};
+#[cfg(not(feature = "poseidon"))]
+pub use tangle_hashing::{
+    compute_note, compute_note_from_backend, compute_nullifier, compute_owner_key,
+    compute_parent_hash,
+};
 
 use super::{
-    tangle::{tangle, tangle_in_field},
+    range_check::to_bounded_bits_le,
     types::{
-        ByteVar, FpVar, FrontendNote, FrontendNullifier, FrontendTokenAmount, FrontendTokenId,
-        FrontendTrapdoor,
+        BackendNote, BackendTransferKey, FpVar, FrontendNote, FrontendOwnerKey,
+        FrontendSpendingSecret, FrontendTokenAmount, FrontendTokenId, FrontendTransferKey,
+        FrontendTransferSecret, FrontendTrapdoor,
     },
+    CircuitField,
 };
 
-/// Verify that `note` is indeed the result of tangling `(token_id, token_amount, trapdoor,
-/// nullifier)`.
+/// Notes and Merkle tree nodes computed with tangling.
+#[cfg(not(feature = "poseidon"))]
+mod tangle_hashing {
+    use ark_ff::{BigInteger, BigInteger256, PrimeField};
+    use ark_r1cs_std::{
+        boolean::Boolean, eq::EqGadget, select::CondSelectGadget, R1CSVar, ToBitsGadget,
+        ToBytesGadget,
+    };
+    use ark_relations::{ns, r1cs::SynthesisError};
+
+    use super::note_from_bytes;
+    use crate::relations::shielder::{
+        tangle::{tangle, tangle_in_field},
+        types::{
+            BackendLeafIndex, BackendOwnerKey, BackendSpendingSecret, BackendTokenAmount,
+            BackendTokenId, BackendTrapdoor, ByteVar, FpVar, FrontendLeafIndex, FrontendNote,
+            FrontendNullifier, FrontendOwnerKey, FrontendSpendingSecret, FrontendTokenAmount,
+            FrontendTokenId, FrontendTrapdoor,
+        },
+        CircuitField,
+    };
+
+    /// The in-circuit representation of a Merkle tree node.
+    pub(in crate::relations::shielder) type MerkleNodeVar = Vec<ByteVar>;
+
+    /// Verify that `hash` is indeed the result of tangling `bytes`.
+    fn check_tangled<const SQUASH_FACTOR: usize>(
+        bytes: Vec<ByteVar>,
+        hash: &FpVar,
+    ) -> Result<(), SynthesisError> {
+        let bytes = tangle_in_field::<SQUASH_FACTOR>(bytes)?;
+        for (a, b) in hash.to_bytes()?.iter().zip(bytes.iter()) {
+            a.enforce_equal(b)?;
+        }
+        Ok(())
+    }
+
+    /// Verify that `note` is indeed the result of tangling `(token_id, token_amount, trapdoor,
+    /// owner_key)`.
+    ///
+    /// For circuit use only.
+    pub(in crate::relations::shielder) fn check_note(
+        token_id: &FpVar,
+        token_amount: &FpVar,
+        trapdoor: &FpVar,
+        owner_key: &FpVar,
+        note: &FpVar,
+    ) -> Result<(), SynthesisError> {
+        let _ns = ns!(note.cs(), "note");
+        let bytes: Vec<ByteVar> = [
+            token_id.to_bytes()?,
+            token_amount.to_bytes()?,
+            trapdoor.to_bytes()?,
+            owner_key.to_bytes()?,
+        ]
+        .concat();
+        check_tangled::<4>(bytes, note)
+    }
+
+    /// Verify that `owner_key` is indeed the result of tangling `spending_secret`.
+    ///
+    /// For circuit use only.
+    pub(in crate::relations::shielder) fn check_owner_key(
+        spending_secret: &FpVar,
+        owner_key: &FpVar,
+    ) -> Result<(), SynthesisError> {
+        let _ns = ns!(owner_key.cs(), "owner key");
+        check_tangled::<1>(spending_secret.to_bytes()?, owner_key)
+    }
+
+    /// Verify that `nullifier` is indeed the result of tangling `(spending_secret, leaf_index)`.
+    ///
+    /// For circuit use only.
+    pub(in crate::relations::shielder) fn check_nullifier(
+        spending_secret: &FpVar,
+        leaf_index: &FpVar,
+        nullifier: &FpVar,
+    ) -> Result<(), SynthesisError> {
+        let _ns = ns!(nullifier.cs(), "nullifier");
+        let bytes = [spending_secret.to_bytes()?, leaf_index.to_bytes()?].concat();
+        check_tangled::<2>(bytes, nullifier)
+    }
+
+    /// Lift `hash` (a note or a Merkle path element) to a Merkle tree node.
+    ///
+    /// For circuit use only.
+    pub(in crate::relations::shielder) fn to_merkle_node(
+        hash: &FpVar,
+    ) -> Result<MerkleNodeVar, SynthesisError> {
+        hash.to_bytes()
+    }
+
+    /// Compute parent node as the result of tangling `(left, right)`.
+    ///
+    /// For circuit use only.
+    pub(in crate::relations::shielder) fn compute_parent_hash_in_field(
+        left: &MerkleNodeVar,
+        right: &MerkleNodeVar,
+    ) -> Result<MerkleNodeVar, SynthesisError> {
+        tangle_in_field::<2>([left.clone(), right.clone()].concat())
+    }
+
+    /// Return `true_node` if `cond` is set, `false_node` otherwise.
+    ///
+    /// For circuit use only.
+    pub(in crate::relations::shielder) fn select_merkle_node(
+        cond: &Boolean<CircuitField>,
+        true_node: &MerkleNodeVar,
+        false_node: &MerkleNodeVar,
+    ) -> Result<MerkleNodeVar, SynthesisError> {
+        true_node
+            .iter()
+            .zip(false_node.iter())
+            .map(|(true_byte, false_byte)| {
+                let bits = true_byte
+                    .to_bits_le()?
+                    .iter()
+                    .zip(false_byte.to_bits_le()?.iter())
+                    .map(|(t, f)| Boolean::conditionally_select(cond, t, f))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(ByteVar::from_bits_le(&bits))
+            })
+            .collect()
+    }
+
+    /// Verify that `node` is indeed `root`.
+    ///
+    /// For circuit use only.
+    pub(in crate::relations::shielder) fn check_merkle_root(
+        root: &FpVar,
+        node: &MerkleNodeVar,
+    ) -> Result<(), SynthesisError> {
+        for (a, b) in root.to_bytes()?.iter().zip(node.iter()) {
+            a.enforce_equal(b)?;
+        }
+        Ok(())
+    }
+
+    /// Compute note as the result of tangling `(token_id, token_amount, trapdoor, owner_key)`.
+    ///
+    /// Useful for input preparation and offline note generation.
+    pub fn compute_note(
+        token_id: FrontendTokenId,
+        token_amount: FrontendTokenAmount,
+        trapdoor: FrontendTrapdoor,
+        owner_key: FrontendOwnerKey,
+    ) -> FrontendNote {
+        compute_note_from_backend(
+            BackendTokenId::from(token_id),
+            BackendTokenAmount::from(token_amount),
+            BackendTrapdoor::from(BigInteger256::new(trapdoor)),
+            BackendOwnerKey::from(BigInteger256::new(owner_key)),
+        )
+    }
+
+    /// Same as `compute_note`, but accepts arbitrary field elements (not only frontend values).
+    pub fn compute_note_from_backend(
+        token_id: BackendTokenId,
+        token_amount: BackendTokenAmount,
+        trapdoor: BackendTrapdoor,
+        owner_key: BackendOwnerKey,
+    ) -> FrontendNote {
+        let bytes = [
+            token_id.into_repr().to_bytes_le(),
+            token_amount.into_repr().to_bytes_le(),
+            trapdoor.into_repr().to_bytes_le(),
+            owner_key.into_repr().to_bytes_le(),
+        ]
+        .concat();
+
+        note_from_bytes(tangle::<4>(bytes).as_slice())
+    }
+
+    /// Compute owner key as the result of tangling `spending_secret`.
+    ///
+    /// Useful for input preparation and offline note generation.
+    pub fn compute_owner_key(spending_secret: FrontendSpendingSecret) -> FrontendOwnerKey {
+        let bytes = BackendSpendingSecret::from(BigInteger256::new(spending_secret))
+            .into_repr()
+            .to_bytes_le();
+        note_from_bytes(tangle::<1>(bytes).as_slice())
+    }
+
+    /// Compute nullifier as the result of tangling `(spending_secret, leaf_index)`.
+    ///
+    /// Useful for input preparation and offline note generation.
+    pub fn compute_nullifier(
+        spending_secret: FrontendSpendingSecret,
+        leaf_index: FrontendLeafIndex,
+    ) -> FrontendNullifier {
+        let bytes = [
+            BackendSpendingSecret::from(BigInteger256::new(spending_secret))
+                .into_repr()
+                .to_bytes_le(),
+            BackendLeafIndex::from(leaf_index).into_repr().to_bytes_le(),
+        ]
+        .concat();
+        note_from_bytes(tangle::<2>(bytes).as_slice())
+    }
+
+    pub fn compute_parent_hash(left: FrontendNote, right: FrontendNote) -> FrontendNote {
+        let bytes = [
+            BigInteger256::new(left).to_bytes_le(),
+            BigInteger256::new(right).to_bytes_le(),
+        ]
+        .concat();
+        note_from_bytes(tangle::<2>(bytes).as_slice())
+    }
+}
+
+/// Notes and Merkle tree nodes computed with Poseidon hash.
+#[cfg(feature = "poseidon")]
+mod poseidon_hashing {
+    use ark_ff::{BigInteger256, PrimeField};
+    use ark_r1cs_std::{boolean::Boolean, eq::EqGadget, select::CondSelectGadget, R1CSVar};
+    use ark_relations::{ns, r1cs::SynthesisError};
+
+    use crate::relations::shielder::{
+        poseidon::{poseidon_hash, poseidon_hash_in_field},
+        types::{
+            BackendLeafIndex, BackendOwnerKey, BackendSpendingSecret, BackendTokenAmount,
+            BackendTokenId, BackendTrapdoor, FpVar, FrontendLeafIndex, FrontendNote,
+            FrontendNullifier, FrontendOwnerKey, FrontendSpendingSecret, FrontendTokenAmount,
+            FrontendTokenId, FrontendTrapdoor,
+        },
+        CircuitField,
+    };
+
+    /// The in-circuit representation of a Merkle tree node.
+    pub(in crate::relations::shielder) type MerkleNodeVar = FpVar;
+
+    /// Verify that `note` is indeed the Poseidon hash of `(token_id, token_amount, trapdoor,
+    /// owner_key)`.
+    ///
+    /// For circuit use only.
+    pub(in crate::relations::shielder) fn check_note(
+        token_id: &FpVar,
+        token_amount: &FpVar,
+        trapdoor: &FpVar,
+        owner_key: &FpVar,
+        note: &FpVar,
+    ) -> Result<(), SynthesisError> {
+        let _ns = ns!(note.cs(), "note");
+        let hash = poseidon_hash_in_field(&[
+            token_id.clone(),
+            token_amount.clone(),
+            trapdoor.clone(),
+            owner_key.clone(),
+        ])?;
+        hash.enforce_equal(note)
+    }
+
+    /// Verify that `owner_key` is indeed the Poseidon hash of `spending_secret`.
+    ///
+    /// For circuit use only.
+    pub(in crate::relations::shielder) fn check_owner_key(
+        spending_secret: &FpVar,
+        owner_key: &FpVar,
+    ) -> Result<(), SynthesisError> {
+        let _ns = ns!(owner_key.cs(), "owner key");
+        poseidon_hash_in_field(&[spending_secret.clone()])?.enforce_equal(owner_key)
+    }
+
+    /// Verify that `nullifier` is indeed the Poseidon hash of `(spending_secret, leaf_index)`.
+    ///
+    /// For circuit use only.
+    pub(in crate::relations::shielder) fn check_nullifier(
+        spending_secret: &FpVar,
+        leaf_index: &FpVar,
+        nullifier: &FpVar,
+    ) -> Result<(), SynthesisError> {
+        let _ns = ns!(nullifier.cs(), "nullifier");
+        poseidon_hash_in_field(&[spending_secret.clone(), leaf_index.clone()])?
+            .enforce_equal(nullifier)
+    }
+
+    /// Lift `hash` (a note or a Merkle path element) to a Merkle tree node.
+    ///
+    /// For circuit use only.
+    pub(in crate::relations::shielder) fn to_merkle_node(
+        hash: &FpVar,
+    ) -> Result<MerkleNodeVar, SynthesisError> {
+        Ok(hash.clone())
+    }
+
+    /// Compute parent node as the Poseidon hash of `(left, right)`.
+    ///
+    /// For circuit use only.
+    pub(in crate::relations::shielder) fn compute_parent_hash_in_field(
+        left: &MerkleNodeVar,
+        right: &MerkleNodeVar,
+    ) -> Result<MerkleNodeVar, SynthesisError> {
+        poseidon_hash_in_field(&[left.clone(), right.clone()])
+    }
+
+    /// Return `true_node` if `cond` is set, `false_node` otherwise.
+    ///
+    /// For circuit use only.
+    pub(in crate::relations::shielder) fn select_merkle_node(
+        cond: &Boolean<CircuitField>,
+        true_node: &MerkleNodeVar,
+        false_node: &MerkleNodeVar,
+    ) -> Result<MerkleNodeVar, SynthesisError> {
+        FpVar::conditionally_select(cond, true_node, false_node)
+    }
+
+    /// Verify that `node` is indeed `root`.
+    ///
+    /// For circuit use only.
+    pub(in crate::relations::shielder) fn check_merkle_root(
+        root: &FpVar,
+        node: &MerkleNodeVar,
+    ) -> Result<(), SynthesisError> {
+        root.enforce_equal(node)
+    }
+
+    /// Compute note as the Poseidon hash of `(token_id, token_amount, trapdoor, owner_key)`.
+    ///
+    /// Useful for input preparation and offline note generation.
+    pub fn compute_note(
+        token_id: FrontendTokenId,
+        token_amount: FrontendTokenAmount,
+        trapdoor: FrontendTrapdoor,
+        owner_key: FrontendOwnerKey,
+    ) -> FrontendNote {
+        compute_note_from_backend(
+            BackendTokenId::from(token_id),
+            BackendTokenAmount::from(token_amount),
+            BackendTrapdoor::from(BigInteger256::new(trapdoor)),
+            BackendOwnerKey::from(BigInteger256::new(owner_key)),
+        )
+    }
+
+    /// Same as `compute_note`, but accepts arbitrary field elements (not only frontend values).
+    pub fn compute_note_from_backend(
+        token_id: BackendTokenId,
+        token_amount: BackendTokenAmount,
+        trapdoor: BackendTrapdoor,
+        owner_key: BackendOwnerKey,
+    ) -> FrontendNote {
+        poseidon_hash(&[token_id, token_amount, trapdoor, owner_key])
+            .into_repr()
+            .0
+    }
+
+    /// Compute owner key as the Poseidon hash of `spending_secret`.
+    ///
+    /// Useful for input preparation and offline note generation.
+    pub fn compute_owner_key(spending_secret: FrontendSpendingSecret) -> FrontendOwnerKey {
+        poseidon_hash(&[BackendSpendingSecret::from(BigInteger256::new(
+            spending_secret,
+        ))])
+        .into_repr()
+        .0
+    }
+
+    /// Compute nullifier as the Poseidon hash of `(spending_secret, leaf_index)`.
+    ///
+    /// Useful for input preparation and offline note generation.
+    pub fn compute_nullifier(
+        spending_secret: FrontendSpendingSecret,
+        leaf_index: FrontendLeafIndex,
+    ) -> FrontendNullifier {
+        poseidon_hash(&[
+            BackendSpendingSecret::from(BigInteger256::new(spending_secret)),
+            BackendLeafIndex::from(leaf_index),
+        ])
+        .into_repr()
+        .0
+    }
+
+    pub fn compute_parent_hash(left: FrontendNote, right: FrontendNote) -> FrontendNote {
+        poseidon_hash(&[
+            CircuitField::from(BigInteger256::new(left)),
+            CircuitField::from(BigInteger256::new(right)),
+        ])
+        .into_repr()
+        .0
+    }
+}
+
+/// Verify that `merkle_path` is a valid Merkle proof for `leaf` being present at `leaf_index` in
+/// some Merkle tree with `merkle_root` hash in the root. `leaf_index` must be smaller than
+/// `2^merkle_path.len()`.
 ///
 /// For circuit use only.
-pub(super) fn check_note(
-    token_id: &FpVar,
-    token_amount: &FpVar,
-    trapdoor: &FpVar,
-    nullifier: &FpVar,
-    note: &FpVar,
+pub(super) fn check_merkle_proof(
+    cs: ConstraintSystemRef<CircuitField>,
+    merkle_root: &FpVar,
+    leaf: &FpVar,
+    leaf_index: &FpVar,
+    merkle_path: &[BackendNote],
 ) -> Result<(), SynthesisError> {
-    let bytes: Vec<ByteVar> = [
-        token_id.to_bytes()?,
-        token_amount.to_bytes()?,
-        trapdoor.to_bytes()?,
-        nullifier.to_bytes()?,
-    ]
-    .concat();
-    let bytes = tangle_in_field::<4>(bytes)?;
+    // The i-th bit of `leaf_index` tells whether the node at i-th level is a right child.
+    let direction_bits = to_bounded_bits_le(leaf_index, merkle_path.len())?;
+
+    let mut current_node = to_merkle_node(leaf)?;
+    for (hash, is_right) in merkle_path.iter().zip(direction_bits.iter()) {
+        // Keep the namespace open, so that hashing is attributed to it as well.
+        let _ns = ns!(cs, "merkle path node");
+        let sibling = FpVar::new_witness(cs.clone(), || Ok(hash))?;
+        let sibling = to_merkle_node(&sibling)?;
 
-    for (a, b) in note.to_bytes()?.iter().zip(bytes.iter()) {
-        a.enforce_equal(b)?;
+        let left = select_merkle_node(is_right, &sibling, &current_node)?;
+        let right = select_merkle_node(is_right, &current_node, &sibling)?;
+        current_node = compute_parent_hash_in_field(&left, &right)?;
     }
-    Ok(())
+
+    let _ns = ns!(cs, "merkle root");
+    check_merkle_root(merkle_root, &current_node)
+}
+
+/// Generate a fresh spending secret.
+///
+/// Useful for input preparation and offline note generation.
+pub fn generate_spending_secret<R: RngCore>(rng: &mut R) -> FrontendSpendingSecret {
+    CircuitField::rand(rng).into_repr().0
+}
+
+/// Generate a fresh trapdoor for a note.
+///
+/// Useful for input preparation and offline note generation.
+pub fn generate_trapdoor<R: RngCore>(rng: &mut R) -> FrontendTrapdoor {
+    CircuitField::rand(rng).into_repr().0
+}
+
+/// Domain separator of the secrets derived for private transfers.
+const TRANSFER_DOMAIN: &[u8] = b"shielder-transfer";
+
+/// Note created by a private transfer, together with the data the recipient needs to recover it.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct TransferredNote {
+    pub note: FrontendNote,
+    pub trapdoor: FrontendTrapdoor,
+    /// Has to be published along with the note.
+    pub ephemeral_key: FrontendTransferKey,
+    /// Token amount encrypted for the recipient. Has to be published along with the note.
+    pub encrypted_amount: FrontendTokenAmount,
+}
+
+fn to_bytes32<T: CanonicalSerialize>(t: &T) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    t.serialize(&mut bytes[..]).expect("Failed to serialize");
+    bytes
 }
 
-/// Compute note as the result of tangling `(token_id, token_amount, trapdoor, nullifier)`.
+/// Point of the prime order subgroup with `x` coordinate given by `key`.
+///
+/// The point with the same `x` and the opposite `y` coordinate differs by a point of order 2, so
+/// at most one of them lies in the subgroup. The neutral element is not a valid key.
+fn decompress_transfer_key(key: &FrontendTransferKey) -> Option<EdwardsAffine> {
+    let x = Fq::deserialize(&key[..]).ok()?;
+    [true, false]
+        .into_iter()
+        .filter_map(|greatest| EdwardsAffine::get_point_from_x(x, greatest))
+        .find(|point| !point.is_zero() && point.is_in_correct_subgroup_assuming_on_curve())
+}
+
+fn transfer_key_of(secret: JubjubScalar) -> FrontendTransferKey {
+    let point = EdwardsAffine::prime_subgroup_generator()
+        .mul(secret)
+        .into_affine();
+    to_bytes32(&point.x)
+}
+
+/// Trapdoor of a transferred note, together with the pad encrypting its token amount, derived from
+/// the shared secret `secret * key`.
+fn derive_transfer_secrets(
+    secret: JubjubScalar,
+    key: &FrontendTransferKey,
+) -> Option<(FrontendTrapdoor, FrontendTokenAmount)> {
+    let shared = to_bytes32(&decompress_transfer_key(key)?.mul(secret).into_affine().x);
+
+    let derive = |purpose: u8| {
+        let mut hasher = Blake2s::new();
+        hasher.update(TRANSFER_DOMAIN);
+        hasher.update([purpose]);
+        hasher.update(shared);
+        hasher.finalize()
+    };
+
+    let trapdoor = CircuitField::from_le_bytes_mod_order(&derive(0))
+        .into_repr()
+        .0;
+    let pad = u64::from_le_bytes(derive(1)[..8].try_into().unwrap());
+    Some((trapdoor, pad))
+}
+
+/// Generate a key pair for receiving private transfers.
+///
+/// Useful for input preparation and offline note generation.
+pub fn generate_transfer_keys<R: RngCore + CryptoRng>(
+    rng: &mut R,
+) -> (FrontendTransferSecret, FrontendTransferKey) {
+    let secret = JubjubScalar::rand(rng);
+    (to_bytes32(&secret), transfer_key_of(secret))
+}
+
+/// Create a note with `token_amount` of `token_id` owned by `owner_key`, which can be recovered
+/// with the secret of the `recipient` transfer key. Returns `None` if `recipient` is not a valid
+/// transfer key.
 ///
 /// Useful for input preparation and offline note generation.
-pub fn compute_note(
+pub fn transfer_note<R: RngCore + CryptoRng>(
+    recipient: &FrontendTransferKey,
+    owner_key: FrontendOwnerKey,
     token_id: FrontendTokenId,
     token_amount: FrontendTokenAmount,
-    trapdoor: FrontendTrapdoor,
-    nullifier: FrontendNullifier,
-) -> FrontendNote {
-    let bytes = [
-        BigInteger256::from(token_id as u64).to_bytes_le(),
-        BigInteger256::from(token_amount).to_bytes_le(),
-        BigInteger256::from(trapdoor).to_bytes_le(),
-        BigInteger256::from(nullifier).to_bytes_le(),
-    ]
-    .concat();
+    rng: &mut R,
+) -> Option<TransferredNote> {
+    let ephemeral_secret = JubjubScalar::rand(rng);
+    let (trapdoor, pad) = derive_transfer_secrets(ephemeral_secret, recipient)?;
 
-    note_from_bytes(tangle::<4>(bytes).as_slice())
+    Some(TransferredNote {
+        note: compute_note(token_id, token_amount, trapdoor, owner_key),
+        trapdoor,
+        ephemeral_key: transfer_key_of(ephemeral_secret),
+        encrypted_amount: token_amount ^ pad,
+    })
 }
 
-pub fn compute_parent_hash(left: FrontendNote, right: FrontendNote) -> FrontendNote {
-    let bytes = [
-        BigInteger256::new(left).to_bytes_le(),
-        BigInteger256::new(right).to_bytes_le(),
-    ]
-    .concat();
-    note_from_bytes(tangle::<2>(bytes).as_slice())
+/// Recover a note transferred to the owner of `secret` (and `owner_key`). Returns the token amount
+/// and the trapdoor of the note, or `None` if `note` was not meant for them.
+///
+/// Useful for input preparation and offline note generation.
+pub fn receive_transferred_note(
+    secret: &FrontendTransferSecret,
+    owner_key: FrontendOwnerKey,
+    note: FrontendNote,
+    token_id: FrontendTokenId,
+    ephemeral_key: &FrontendTransferKey,
+    encrypted_amount: FrontendTokenAmount,
+) -> Option<(FrontendTokenAmount, FrontendTrapdoor)> {
+    let secret = JubjubScalar::deserialize(&secret[..]).ok()?;
+    let (trapdoor, pad) = derive_transfer_secrets(secret, ephemeral_key)?;
+    let token_amount = encrypted_amount ^ pad;
+
+    (compute_note(token_id, token_amount, trapdoor, owner_key) == note)
+        .then_some((token_amount, trapdoor))
+}
+
+/// The field element representing `key` in circuits.
+pub(super) fn transfer_key_to_backend(key: &FrontendTransferKey) -> BackendTransferKey {
+    BackendTransferKey::deserialize(&key[..]).expect("Transfer key must be a field element")
 }
 
 /// Create a note from the first 32 bytes of `bytes`.
@@ -92,19 +626,116 @@ pub fn bytes_from_note(note: &FrontendNote) -> Vec<u8> {
 
 #[cfg(test)]
 mod tests {
+    use ark_ff::BigInteger256;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_std::rand::{prelude::StdRng, SeedableRng};
+
     use super::*;
+    use crate::relations::shielder::types::{BackendLeafIndex, BackendSpendingSecret};
+
+    fn owner_keys(rng: &mut StdRng) -> (FrontendSpendingSecret, FrontendOwnerKey) {
+        let spending_secret = generate_spending_secret(rng);
+        (spending_secret, compute_owner_key(spending_secret))
+    }
 
     #[test]
     fn note_conversion() {
         let token_id: FrontendTokenId = 1;
         let token_amount: FrontendTokenAmount = 10;
-        let trapdoor: FrontendTrapdoor = 17;
-        let nullifier: FrontendNullifier = 19;
-        let note = compute_note(token_id, token_amount, trapdoor, nullifier);
+        let trapdoor: FrontendTrapdoor = [17, 0, 0, 0];
+        let owner_key: FrontendOwnerKey = compute_owner_key([19, 0, 0, 0]);
+        let note = compute_note(token_id, token_amount, trapdoor, owner_key);
 
         let bytes = bytes_from_note(&note);
         let note_again = note_from_bytes(&bytes);
 
         assert_eq!(note, note_again);
     }
+
+    #[test]
+    fn nullifiers_of_different_leaves_differ() {
+        let (spending_secret, _) = owner_keys(&mut StdRng::from_seed([0; 32]));
+
+        assert_ne!(
+            compute_nullifier(spending_secret, 0),
+            compute_nullifier(spending_secret, 1)
+        );
+    }
+
+    #[test]
+    fn ownership_gadgets_match_native_hashing() {
+        let (spending_secret, owner_key) = owner_keys(&mut StdRng::from_seed([0; 32]));
+        let leaf_index = 5;
+        let nullifier = compute_nullifier(spending_secret, leaf_index);
+
+        let cs = ConstraintSystem::new_ref();
+        let witness = |value: CircuitField| FpVar::new_witness(cs.clone(), || Ok(value)).unwrap();
+        let spending_secret = witness(BackendSpendingSecret::from(BigInteger256::new(
+            spending_secret,
+        )));
+        let leaf_index = witness(BackendLeafIndex::from(leaf_index));
+
+        check_owner_key(
+            &spending_secret,
+            &witness(CircuitField::from(BigInteger256::new(owner_key))),
+        )
+        .unwrap();
+        check_nullifier(
+            &spending_secret,
+            &leaf_index,
+            &witness(CircuitField::from(BigInteger256::new(nullifier))),
+        )
+        .unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        check_owner_key(&leaf_index, &spending_secret).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn recipient_receives_transferred_note() {
+        let mut rng = StdRng::from_seed([0; 32]);
+        let (secret, key) = generate_transfer_keys(&mut rng);
+        let (_, owner_key) = owner_keys(&mut rng);
+
+        let transferred = transfer_note(&key, owner_key, 1, 10, &mut rng).unwrap();
+        let received = receive_transferred_note(
+            &secret,
+            owner_key,
+            transferred.note,
+            1,
+            &transferred.ephemeral_key,
+            transferred.encrypted_amount,
+        );
+
+        assert_eq!(received, Some((10, transferred.trapdoor)));
+    }
+
+    #[test]
+    fn others_cannot_receive_transferred_note() {
+        let mut rng = StdRng::from_seed([0; 32]);
+        let (_, key) = generate_transfer_keys(&mut rng);
+        let (other_secret, _) = generate_transfer_keys(&mut rng);
+        let (_, owner_key) = owner_keys(&mut rng);
+
+        let transferred = transfer_note(&key, owner_key, 1, 10, &mut rng).unwrap();
+        let received = receive_transferred_note(
+            &other_secret,
+            owner_key,
+            transferred.note,
+            1,
+            &transferred.ephemeral_key,
+            transferred.encrypted_amount,
+        );
+
+        assert_eq!(received, None);
+    }
+
+    #[test]
+    fn invalid_transfer_key_is_rejected() {
+        let mut rng = StdRng::from_seed([0; 32]);
+        let (_, owner_key) = owner_keys(&mut rng);
+
+        assert!(transfer_note(&[0xff; 32], owner_key, 1, 10, &mut rng).is_none());
+    }
 }
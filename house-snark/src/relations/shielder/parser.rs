@@ -1,7 +1,11 @@
-use anyhow::Result;
+use anyhow::{Error, Result};
 
-use super::types::{FrontendAccount, FrontendMerklePathNode, FrontendMerkleRoot, FrontendNote};
-use crate::relations::shielder::note::note_from_bytes;
+use super::types::{
+    FrontendAccount, FrontendMerklePath, FrontendMerklePathNode, FrontendMerkleRoot, FrontendNote,
+    FrontendNullifier, FrontendOwnerKey, FrontendSpendingSecret, FrontendTransferKey,
+    FrontendTrapdoor,
+};
+use crate::relations::{shielder::note::note_from_bytes, witness::bytes32};
 
 pub fn parse_frontend_note(frontend_note: &str) -> Result<FrontendNote> {
     Ok(note_from_bytes(frontend_note.as_bytes()))
@@ -11,12 +15,107 @@ pub fn parse_frontend_merkle_root(frontend_merkle_root: &str) -> Result<Frontend
     Ok(note_from_bytes(frontend_merkle_root.as_bytes()))
 }
 
+pub fn parse_frontend_nullifier(frontend_nullifier: &str) -> Result<FrontendNullifier> {
+    Ok(note_from_bytes(frontend_nullifier.as_bytes()))
+}
+
+pub fn parse_frontend_trapdoor(frontend_trapdoor: &str) -> Result<FrontendTrapdoor> {
+    Ok(note_from_bytes(frontend_trapdoor.as_bytes()))
+}
+
+pub fn parse_frontend_spending_secret(
+    frontend_spending_secret: &str,
+) -> Result<FrontendSpendingSecret> {
+    Ok(note_from_bytes(frontend_spending_secret.as_bytes()))
+}
+
+pub fn parse_frontend_owner_key(frontend_owner_key: &str) -> Result<FrontendOwnerKey> {
+    Ok(note_from_bytes(frontend_owner_key.as_bytes()))
+}
+
 pub fn parse_frontend_account(frontend_account: &str) -> Result<FrontendAccount> {
     Ok(frontend_account.as_bytes().try_into().unwrap())
 }
 
+/// Parse a transfer key given as a hex string (like in witness files).
+pub fn parse_frontend_transfer_key(frontend_transfer_key: &str) -> Result<FrontendTransferKey> {
+    bytes32::decode(frontend_transfer_key).map_err(Error::msg)
+}
+
 pub fn parse_frontend_merkle_path_single(
     frontend_merkle_path_single: &str,
 ) -> Result<FrontendMerklePathNode> {
     Ok(note_from_bytes(frontend_merkle_path_single.as_bytes()))
 }
+
+/// Checks that `merkle_path` has exactly `max_path_len` nodes (one per tree level).
+pub fn validate_merkle_path_len(
+    merkle_path: &FrontendMerklePath,
+    max_path_len: u8,
+) -> std::result::Result<(), String> {
+    if merkle_path.len() != max_path_len as usize {
+        return Err(format!(
+            "Merkle path has {} nodes, but the tree height (`max_path_len`) is {}",
+            merkle_path.len(),
+            max_path_len
+        ));
+    }
+    Ok(())
+}
+
+/// (De)serialization of notes (and other field elements, like Merkle tree nodes, nullifiers,
+/// trapdoors or owner keys) in witness files as hex strings of their bytes.
+pub mod note_hex {
+    use serde::{Deserializer, Serializer};
+
+    use crate::relations::{
+        shielder::{
+            note::{bytes_from_note, note_from_bytes},
+            types::FrontendNote,
+        },
+        witness::bytes32,
+    };
+
+    pub fn serialize<S: Serializer>(note: &FrontendNote, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&bytes32::encode(&bytes_from_note(note)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<FrontendNote, D::Error> {
+        bytes32::deserialize(deserializer).map(|bytes| note_from_bytes(&bytes))
+    }
+}
+
+/// (De)serialization of Merkle paths in witness files as lists of hex strings.
+pub mod merkle_path_hex {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use crate::relations::{
+        shielder::{
+            note::{bytes_from_note, note_from_bytes},
+            types::FrontendMerklePath,
+        },
+        witness::bytes32,
+    };
+
+    pub fn serialize<S: Serializer>(
+        path: &FrontendMerklePath,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(
+            path.iter()
+                .map(|node| bytes32::encode(&bytes_from_note(node))),
+        )
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<FrontendMerklePath, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|node| bytes32::decode(node).map(|bytes| note_from_bytes(&bytes)))
+            .collect::<Result<_, _>>()
+            .map_err(D::Error::custom)
+    }
+}
@@ -0,0 +1,186 @@
+//! This module provides Poseidon hashing over `CircuitField` - a SNARK-friendly hash function that
+//! can replace tangling.
+//!
+//! We use the Poseidon permutation with state of `WIDTH = 3` field elements, `x^5` S-box,
+//! `FULL_ROUNDS = 8` full rounds and `PARTIAL_ROUNDS = 57` partial rounds (128-bit security level
+//! for a 255-bit prime field). On top of it, we build a simple sponge:
+//!  1. The first state element (capacity) is initialized with the input length (for domain
+//!     separation), the rest (rate) with zeros.
+//!  2. The input is absorbed in chunks of `RATE` elements, i.e. every chunk is added to the rate
+//!     part of the state and the state is permuted.
+//!  3. The first rate element is squeezed as the hash.
+//!
+//! Similarly to tangling, this module exposes two semantically equivalent implementations:
+//! `poseidon_hash` (native) and `poseidon_hash_in_field` (gadget).
+
+mod constants;
+
+use ark_ff::{Field, Zero};
+use ark_r1cs_std::fields::FieldVar;
+use ark_relations::r1cs::SynthesisError;
+use constants::{MDS_MATRIX, ROUND_CONSTANTS};
+
+use super::types::FpVar;
+use crate::relations::types::CircuitField;
+
+/// Number of field elements in the state.
+const WIDTH: usize = 3;
+/// Number of field elements absorbed with a single permutation.
+const RATE: usize = WIDTH - 1;
+/// Number of rounds in which S-box is applied to the whole state (half at the beginning, half at
+/// the end).
+const FULL_ROUNDS: usize = 8;
+/// Number of rounds in which S-box is applied only to the first state element.
+const PARTIAL_ROUNDS: usize = 57;
+
+fn is_full_round(round: usize) -> bool {
+    round < FULL_ROUNDS / 2 || round >= FULL_ROUNDS / 2 + PARTIAL_ROUNDS
+}
+
+/// Hash `input` with Poseidon sponge.
+///
+/// For circuit use only.
+pub(super) fn poseidon_hash_in_field(input: &[FpVar]) -> Result<FpVar, SynthesisError> {
+    let mut state = vec![FpVar::zero(); WIDTH];
+    state[0] = FpVar::constant(CircuitField::from(input.len() as u64));
+
+    for chunk in input.chunks(RATE) {
+        for (element, absorbed) in state[1..].iter_mut().zip(chunk) {
+            *element += absorbed;
+        }
+        permute_in_field(&mut state)?;
+    }
+    Ok(state[1].clone())
+}
+
+/// Poseidon permutation.
+///
+/// For circuit use only.
+fn permute_in_field(state: &mut Vec<FpVar>) -> Result<(), SynthesisError> {
+    for (round, constants) in ROUND_CONSTANTS.iter().enumerate() {
+        for (element, constant) in state.iter_mut().zip(constants) {
+            *element += *constant;
+        }
+
+        if is_full_round(round) {
+            for element in state.iter_mut() {
+                *element = sbox_in_field(element)?;
+            }
+        } else {
+            state[0] = sbox_in_field(&state[0])?;
+        }
+
+        *state = MDS_MATRIX
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(state.iter())
+                    .fold(FpVar::zero(), |acc, (m, element)| acc + element * *m)
+            })
+            .collect();
+    }
+    Ok(())
+}
+
+/// Compute `x^5` in circuit (3 constraints).
+fn sbox_in_field(x: &FpVar) -> Result<FpVar, SynthesisError> {
+    Ok(x.square()?.square()? * x)
+}
+
+/// Hash `input` with Poseidon sponge.
+pub fn poseidon_hash(input: &[CircuitField]) -> CircuitField {
+    let mut state = [CircuitField::zero(); WIDTH];
+    state[0] = CircuitField::from(input.len() as u64);
+
+    for chunk in input.chunks(RATE) {
+        for (element, absorbed) in state[1..].iter_mut().zip(chunk) {
+            *element += absorbed;
+        }
+        permute(&mut state);
+    }
+    state[1]
+}
+
+/// Poseidon permutation.
+///
+/// For detailed description, see `permute_in_field`.
+fn permute(state: &mut [CircuitField; WIDTH]) {
+    for (round, constants) in ROUND_CONSTANTS.iter().enumerate() {
+        for (element, constant) in state.iter_mut().zip(constants) {
+            *element += constant;
+        }
+
+        if is_full_round(round) {
+            for element in state.iter_mut() {
+                *element = sbox(*element);
+            }
+        } else {
+            state[0] = sbox(state[0]);
+        }
+
+        let mut mixed = [CircuitField::zero(); WIDTH];
+        for (mixed_element, row) in mixed.iter_mut().zip(MDS_MATRIX.iter()) {
+            *mixed_element = row
+                .iter()
+                .zip(state.iter())
+                .fold(CircuitField::zero(), |acc, (m, element)| acc + *m * element);
+        }
+        *state = mixed;
+    }
+}
+
+fn sbox(x: CircuitField) -> CircuitField {
+    x.square().square() * x
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use ark_r1cs_std::{alloc::AllocVar, R1CSVar};
+    use ark_relations::r1cs::ConstraintSystem;
+
+    use super::*;
+
+    fn input() -> Vec<CircuitField> {
+        [1u64, 10, 17, 19].map(CircuitField::from).to_vec()
+    }
+
+    #[test]
+    fn native_hash_matches_reference_vectors() {
+        // Computed with `scripts/poseidon_reference.py`.
+        let expected = CircuitField::from_str(
+            "45356797118007010687026198206413571229481198788593392098306918479762447659105",
+        )
+        .unwrap();
+        assert_eq!(poseidon_hash(&input()), expected);
+
+        let expected = CircuitField::from_str(
+            "4983107865620167593979558994301555316680864616972858091789066071349538939402",
+        )
+        .unwrap();
+        assert_eq!(poseidon_hash(&input()[..2]), expected);
+    }
+
+    #[test]
+    fn gadget_matches_native_hash() {
+        let cs = ConstraintSystem::new_ref();
+        let input_vars = input()
+            .iter()
+            .map(|x| FpVar::new_witness(cs.clone(), || Ok(x)))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        let hash = poseidon_hash_in_field(&input_vars).unwrap();
+
+        assert!(cs.is_satisfied().unwrap());
+        assert_eq!(hash.value().unwrap(), poseidon_hash(&input()));
+    }
+
+    #[test]
+    fn hash_depends_on_input_length() {
+        let mut padded = input();
+        padded.push(CircuitField::zero());
+        assert_ne!(poseidon_hash(&input()), poseidon_hash(&padded));
+    }
+}
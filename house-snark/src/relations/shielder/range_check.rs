@@ -0,0 +1,44 @@
+//! Gadgets for bounding field elements.
+//!
+//! Whenever we treat field elements as integers (token amounts, leaf indices), we have to make sure
+//! that they are small enough not to wrap around the field modulus.
+
+use ark_r1cs_std::{boolean::Boolean, eq::EqGadget, ToBitsGadget};
+use ark_relations::r1cs::SynthesisError;
+
+use super::{types::FpVar, CircuitField};
+
+/// Decompose `value` into `bits` little-endian bits, enforcing that `value < 2^bits`.
+///
+/// For circuit use only.
+pub(super) fn to_bounded_bits_le(
+    value: &FpVar,
+    bits: usize,
+) -> Result<Vec<Boolean<CircuitField>>, SynthesisError> {
+    let mut value_bits = value.to_bits_le()?;
+    for bit in value_bits.drain(bits..) {
+        bit.enforce_equal(&Boolean::FALSE)?;
+    }
+    Ok(value_bits)
+}
+
+/// Enforce that `value < 2^bits`.
+///
+/// For circuit use only.
+pub(super) fn enforce_fits_in_bits(value: &FpVar, bits: usize) -> Result<(), SynthesisError> {
+    to_bounded_bits_le(value, bits).map(|_| ())
+}
+
+/// Enforce that `a <= b`, provided that both `a` and `b` are already known to be smaller than
+/// `2^bits`.
+///
+/// If `a > b`, then `b - a` wraps around the field modulus and becomes huge.
+///
+/// For circuit use only.
+pub(super) fn enforce_less_or_equal(
+    a: &FpVar,
+    b: &FpVar,
+    bits: usize,
+) -> Result<(), SynthesisError> {
+    enforce_fits_in_bits(&(b - a), bits)
+}
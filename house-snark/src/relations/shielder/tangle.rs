@@ -19,10 +19,11 @@
 //! All the index intervals used here are closed-open, i.e. they are in form `[a, b)`, which means
 //! that we consider indices `a`, `a+1`, ..., `b-1`. We also use 0-based indexing.
 
-use ark_r1cs_std::R1CSVar;
+use ark_r1cs_std::{alloc::AllocVar, boolean::Boolean, eq::EqGadget, R1CSVar, ToBitsGadget};
 use ark_relations::r1cs::SynthesisError;
 
-use super::types::ByteVar;
+use super::types::{ByteVar, FpVar};
+use crate::relations::types::CircuitField;
 
 /// Bottom-level chunk length.
 const BASE_LENGTH: usize = 4;
@@ -35,16 +36,14 @@ pub(super) fn tangle_in_field<const SQUASH_FACTOR: usize>(
 ) -> Result<Vec<ByteVar>, SynthesisError> {
     let number_of_bytes = bytes.len();
     _tangle_in_field(&mut bytes, 0, number_of_bytes)?;
-    Ok(bytes
+    bytes
         .chunks(SQUASH_FACTOR)
         .map(|chunk| {
-            chunk
+            chunk[1..]
                 .iter()
-                .cloned()
-                .reduce(|x, y| x.xor(&y).unwrap())
-                .unwrap()
+                .try_fold(chunk[0].clone(), |acc, byte| acc.xor(byte))
         })
-        .collect())
+        .collect()
 }
 
 /// Recursive and index-bounded implementation of the first step of the `tangle` procedure.
@@ -54,13 +53,7 @@ fn _tangle_in_field(bytes: &mut [ByteVar], low: usize, high: usize) -> Result<()
     if high - low <= BASE_LENGTH {
         let mut i = high - 2;
         loop {
-            bytes[i] = ByteVar::constant(
-                u8::overflowing_add(
-                    bytes[i].value().unwrap_or_default(),
-                    bytes[i + 1].value().unwrap_or_default(),
-                )
-                .0,
-            );
+            bytes[i] = add_bytes(&bytes[i], &bytes[i + 1])?;
             if i == low {
                 break;
             } else {
@@ -84,18 +77,60 @@ fn _tangle_in_field(bytes: &mut [ByteVar], low: usize, high: usize) -> Result<()
 
         // Prefix products.
         for i in low + 1..high {
-            bytes[i] = ByteVar::constant(
-                u8::overflowing_mul(
-                    bytes[i].value().unwrap_or_default(),
-                    bytes[i - 1].value().unwrap_or_default(),
-                )
-                .0,
-            )
+            bytes[i] = mul_bytes(&bytes[i], &bytes[i - 1])?;
         }
     }
     Ok(())
 }
 
+/// Lift `byte` to a field element gadget (interpreting its bits as a little-endian number).
+fn byte_to_field(byte: &ByteVar) -> Result<FpVar, SynthesisError> {
+    Boolean::le_bits_to_fp_var(&byte.to_bits_le()?)
+}
+
+/// Compute `(a + b) mod 256` in circuit.
+///
+/// We introduce a new byte `sum` and a boolean `carry` and enforce that `a + b = sum + 256 * carry`
+/// holds over the field. Since `sum` is allocated bit by bit, this pins it down uniquely.
+fn add_bytes(a: &ByteVar, b: &ByteVar) -> Result<ByteVar, SynthesisError> {
+    let cs = a.cs().or(b.cs());
+    if cs.is_none() {
+        return Ok(ByteVar::constant(u8::wrapping_add(a.value()?, b.value()?)));
+    }
+
+    let sum = ByteVar::new_witness(cs.clone(), || Ok(u8::wrapping_add(a.value()?, b.value()?)))?;
+    let carry = Boolean::new_witness(cs, || Ok(u8::overflowing_add(a.value()?, b.value()?).1))?;
+
+    let lhs = byte_to_field(a)? + byte_to_field(b)?;
+    let rhs = byte_to_field(&sum)? + FpVar::from(carry) * CircuitField::from(256u16);
+    lhs.enforce_equal(&rhs)?;
+
+    Ok(sum)
+}
+
+/// Compute `(a * b) mod 256` in circuit.
+///
+/// We introduce two new bytes: `product` (lower half of `a * b`) and `high` (upper half) and
+/// enforce that `a * b = product + 256 * high` holds over the field. As both of them are proper
+/// bytes, `a * b < 2^16` has exactly one such decomposition.
+fn mul_bytes(a: &ByteVar, b: &ByteVar) -> Result<ByteVar, SynthesisError> {
+    let cs = a.cs().or(b.cs());
+    if cs.is_none() {
+        return Ok(ByteVar::constant(u8::wrapping_mul(a.value()?, b.value()?)));
+    }
+
+    let full_product =
+        || -> Result<u16, SynthesisError> { Ok((a.value()? as u16) * (b.value()? as u16)) };
+    let product = ByteVar::new_witness(cs.clone(), || full_product().map(|p| p as u8))?;
+    let high = ByteVar::new_witness(cs, || full_product().map(|p| (p >> 8) as u8))?;
+
+    let lhs = byte_to_field(a)? * byte_to_field(b)?;
+    let rhs = byte_to_field(&product)? + byte_to_field(&high)? * CircuitField::from(256u16);
+    lhs.enforce_equal(&rhs)?;
+
+    Ok(product)
+}
+
 /// Tangle elements of `bytes`.
 pub fn tangle<const SQUASH_FACTOR: usize>(mut bytes: Vec<u8>) -> Vec<u8> {
     let number_of_bytes = bytes.len();
@@ -134,3 +169,39 @@ fn _tangle(bytes: &mut [u8], low: usize, high: usize) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ark_relations::r1cs::ConstraintSystem;
+
+    use super::*;
+
+    fn bytes() -> Vec<u8> {
+        (0..64u8)
+            .map(|i| i.wrapping_mul(37).wrapping_add(11))
+            .collect()
+    }
+
+    #[test]
+    fn gadget_matches_native_tangling() {
+        let cs = ConstraintSystem::new_ref();
+        let byte_vars = bytes()
+            .iter()
+            .map(|b| ByteVar::new_witness(cs.clone(), || Ok(b)))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        let tangled = tangle_in_field::<2>(byte_vars).unwrap();
+
+        assert!(cs.is_satisfied().unwrap());
+        assert_eq!(tangled.value().unwrap(), tangle::<2>(bytes()));
+        assert!(tangled.iter().all(|byte| !byte.cs().is_none()));
+    }
+
+    #[test]
+    fn gadget_matches_native_tangling_on_constants() {
+        let byte_vars = bytes().into_iter().map(ByteVar::constant).collect();
+        let tangled = tangle_in_field::<2>(byte_vars).unwrap();
+        assert_eq!(tangled.value().unwrap(), tangle::<2>(bytes()));
+    }
+}
@@ -0,0 +1,561 @@
+use std::ops::Add;
+
+use ark_ff::BigInteger256;
+use ark_r1cs_std::{alloc::AllocVar, eq::EqGadget};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+use ark_std::rand::{prelude::StdRng, SeedableRng};
+use clap::Args;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    note::{
+        check_merkle_proof, check_note, check_nullifier, check_owner_key, compute_note,
+        compute_nullifier, compute_owner_key, compute_parent_hash, generate_transfer_keys,
+        transfer_key_to_backend, transfer_note,
+    },
+    parser::{
+        parse_frontend_merkle_path_single, parse_frontend_merkle_root, parse_frontend_note,
+        parse_frontend_nullifier, parse_frontend_owner_key, parse_frontend_spending_secret,
+        parse_frontend_transfer_key, parse_frontend_trapdoor, validate_merkle_path_len,
+    },
+    range_check::enforce_fits_in_bits,
+    types::{
+        BackendLeafIndex, BackendMerklePath, BackendMerkleRoot, BackendNote, BackendNullifier,
+        BackendOwnerKey, BackendSpendingSecret, BackendTokenAmount, BackendTokenId,
+        BackendTransferKey, BackendTrapdoor, FpVar, FrontendLeafIndex, FrontendMerklePath,
+        FrontendMerkleRoot, FrontendNote, FrontendNullifier, FrontendOwnerKey,
+        FrontendSpendingSecret, FrontendTokenAmount, FrontendTokenId, FrontendTransferKey,
+        FrontendTrapdoor, TOKEN_AMOUNT_BITS,
+    },
+    CircuitField,
+};
+use crate::relations::{witness::WitnessTemplate, GetPublicInput};
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TransferRelationArgs {
+    // Constant parameters.
+    #[clap(long, default_value = "16")]
+    #[serde(with = "crate::relations::witness::integer")]
+    pub max_path_len: u8,
+
+    // Public inputs.
+    #[clap(long)]
+    #[serde(with = "crate::relations::witness::integer")]
+    pub token_id: FrontendTokenId,
+    #[clap(long, value_parser = parse_frontend_merkle_root)]
+    #[serde(with = "super::parser::note_hex")]
+    pub merkle_root: FrontendMerkleRoot,
+    #[clap(long, value_parser = parse_frontend_nullifier)]
+    #[serde(with = "super::parser::note_hex")]
+    pub old_nullifier: FrontendNullifier,
+    #[clap(long, value_parser = parse_frontend_note)]
+    #[serde(with = "super::parser::note_hex")]
+    pub new_note: FrontendNote,
+    #[clap(long, value_parser = parse_frontend_note)]
+    #[serde(with = "super::parser::note_hex")]
+    pub change_note: FrontendNote,
+    #[clap(long, value_parser = parse_frontend_transfer_key)]
+    #[serde(with = "crate::relations::witness::bytes32")]
+    pub ephemeral_key: FrontendTransferKey,
+    #[clap(long)]
+    #[serde(with = "crate::relations::witness::integer")]
+    pub encrypted_amount: FrontendTokenAmount,
+
+    // Private inputs.
+    #[clap(long, value_parser = parse_frontend_spending_secret)]
+    #[serde(with = "super::parser::note_hex")]
+    pub spending_secret: FrontendSpendingSecret,
+    #[clap(long, value_parser = parse_frontend_note)]
+    #[serde(with = "super::parser::note_hex")]
+    pub old_note: FrontendNote,
+    #[clap(long, value_parser = parse_frontend_trapdoor)]
+    #[serde(with = "super::parser::note_hex")]
+    pub old_trapdoor: FrontendTrapdoor,
+    #[clap(long)]
+    #[serde(with = "crate::relations::witness::integer")]
+    pub whole_token_amount: FrontendTokenAmount,
+    #[clap(long, value_delimiter = ',', value_parser = parse_frontend_merkle_path_single)]
+    #[serde(with = "super::parser::merkle_path_hex")]
+    pub merkle_path: FrontendMerklePath,
+    #[clap(long)]
+    #[serde(with = "crate::relations::witness::integer")]
+    pub leaf_index: FrontendLeafIndex,
+
+    #[clap(long)]
+    #[serde(with = "crate::relations::witness::integer")]
+    pub token_amount: FrontendTokenAmount,
+    #[clap(long, value_parser = parse_frontend_trapdoor)]
+    #[serde(with = "super::parser::note_hex")]
+    pub new_trapdoor: FrontendTrapdoor,
+    #[clap(long, value_parser = parse_frontend_owner_key)]
+    #[serde(with = "super::parser::note_hex")]
+    pub recipient_owner_key: FrontendOwnerKey,
+
+    #[clap(long)]
+    #[serde(with = "crate::relations::witness::integer")]
+    pub change_token_amount: FrontendTokenAmount,
+    #[clap(long, value_parser = parse_frontend_trapdoor)]
+    #[serde(with = "super::parser::note_hex")]
+    pub change_trapdoor: FrontendTrapdoor,
+}
+
+impl WitnessTemplate for TransferRelationArgs {
+    /// Transfer of 7 tokens (out of 10) from a note at leaf `1` of a tree with 4 leaves to a
+    /// recipient with fixed keys. The remaining 3 tokens go to a change note.
+    fn template() -> Self {
+        let token_id = 1;
+        let spending_secret = [19, 0, 0, 0];
+        let (old_trapdoor, whole_token_amount) = ([17, 0, 0, 0], 10);
+        let (change_trapdoor, change_token_amount) = ([27, 0, 0, 0], 3);
+        let token_amount = 7;
+        let leaf_index = 1;
+
+        let (_, recipient) = generate_transfer_keys(&mut StdRng::from_seed([1; 32]));
+        let recipient_owner_key = compute_owner_key(RECIPIENT_SPENDING_SECRET);
+        let transferred = transfer_note(
+            &recipient,
+            recipient_owner_key,
+            token_id,
+            token_amount,
+            &mut StdRng::from_seed([2; 32]),
+        )
+        .expect("Generated transfer key is valid");
+
+        let owner_key = compute_owner_key(spending_secret);
+        let old_note = compute_note(token_id, whole_token_amount, old_trapdoor, owner_key);
+        let sibling = compute_note(0, 1, [2, 0, 0, 0], [3, 0, 0, 0]);
+        let uncle = compute_note(4, 5, [6, 0, 0, 0], [7, 0, 0, 0]);
+        let merkle_root = compute_parent_hash(compute_parent_hash(sibling, old_note), uncle);
+
+        TransferRelationArgs {
+            max_path_len: 2,
+            token_id,
+            merkle_root,
+            old_nullifier: compute_nullifier(spending_secret, leaf_index),
+            new_note: transferred.note,
+            change_note: compute_note(token_id, change_token_amount, change_trapdoor, owner_key),
+            ephemeral_key: transferred.ephemeral_key,
+            encrypted_amount: transferred.encrypted_amount,
+            spending_secret,
+            old_note,
+            old_trapdoor,
+            whole_token_amount,
+            merkle_path: vec![sibling, uncle],
+            leaf_index,
+            token_amount,
+            new_trapdoor: transferred.trapdoor,
+            recipient_owner_key,
+            change_token_amount,
+            change_trapdoor,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        validate_merkle_path_len(&self.merkle_path, self.max_path_len)
+    }
+}
+
+/// Spending secret of the recipient in the witness template.
+const RECIPIENT_SPENDING_SECRET: FrontendSpendingSecret = [23, 0, 0, 0];
+
+/// 'Transfer' relation for the Shielder application: sending a part of a note to another user
+/// without leaving the shielded pool.
+///
+/// It expresses the facts that:
+///  - `owner_key` is the result of tangling `spending_secret`,
+///  - `old_note` is a prefix of the result of tangling together `token_id`, `whole_token_amount`,
+///    `old_trapdoor` and `owner_key`,
+///  - `old_nullifier` is the result of tangling together `spending_secret` and `leaf_index`,
+///  - `merkle_path` is a valid Merkle proof for `old_note` being present at `leaf_index` in some
+///    Merkle tree with `merkle_root` hash in the root,
+///  - `leaf_index` is smaller than `2^max_path_len`,
+///  - `new_note` is a prefix of the result of tangling together `token_id`, `token_amount`,
+///    `new_trapdoor` and `recipient_owner_key`,
+///  - `change_note` is a prefix of the result of tangling together `token_id`,
+///    `change_token_amount`, `change_trapdoor` and `owner_key`,
+///  - all the amounts are 64-bit values and `token_amount + change_token_amount =
+///    whole_token_amount`.
+/// `new_note` is owned by the recipient. Its trapdoor is derived from the secret shared between
+/// the sender and the recipient's transfer key (see `transfer_note`). The circuit does not check
+/// this derivation - a recipient verifies it natively, when recovering the note from
+/// `ephemeral_key` and `encrypted_amount` (see `receive_transferred_note`). These two are
+/// artificial inputs, which are treated as public inputs just to bind them to the proof (so that
+/// nobody can replace them and lock the tokens out of the recipient's reach).
+///
+/// When providing a public input to proof verification, you should keep the order of variable
+/// declarations in circuit, i.e.: `token_id`, `merkle_root`, `old_nullifier`, `new_note`,
+/// `change_note`, `ephemeral_key`, `encrypted_amount`.
+#[derive(Clone)]
+pub struct TransferRelation {
+    // Constant parameters.
+    pub max_path_len: u8,
+
+    // Public inputs.
+    pub token_id: BackendTokenId,
+    pub merkle_root: BackendMerkleRoot,
+    pub old_nullifier: BackendNullifier,
+    pub new_note: BackendNote,
+    pub change_note: BackendNote,
+    pub ephemeral_key: BackendTransferKey,
+    pub encrypted_amount: BackendTokenAmount,
+
+    // Private inputs.
+    pub spending_secret: BackendSpendingSecret,
+    /// Derived from `spending_secret`.
+    pub owner_key: BackendOwnerKey,
+    pub old_note: BackendNote,
+    pub old_trapdoor: BackendTrapdoor,
+    pub whole_token_amount: BackendTokenAmount,
+    pub merkle_path: BackendMerklePath,
+    pub leaf_index: BackendLeafIndex,
+    pub token_amount: BackendTokenAmount,
+    pub new_trapdoor: BackendTrapdoor,
+    pub recipient_owner_key: BackendOwnerKey,
+    pub change_token_amount: BackendTokenAmount,
+    pub change_trapdoor: BackendTrapdoor,
+}
+
+impl From<TransferRelationArgs> for TransferRelation {
+    fn from(args: TransferRelationArgs) -> Self {
+        let TransferRelationArgs {
+            max_path_len,
+            token_id,
+            merkle_root,
+            old_nullifier,
+            new_note,
+            change_note,
+            ephemeral_key,
+            encrypted_amount,
+            spending_secret,
+            old_note,
+            old_trapdoor,
+            whole_token_amount,
+            merkle_path,
+            leaf_index,
+            token_amount,
+            new_trapdoor,
+            recipient_owner_key,
+            change_token_amount,
+            change_trapdoor,
+        } = args;
+        assert_eq!(
+            merkle_path.len(),
+            max_path_len as usize,
+            "Merkle path length doesn't match the tree height"
+        );
+
+        Self {
+            max_path_len,
+            token_id: BackendTokenId::from(token_id),
+            merkle_root: BackendMerkleRoot::from(BigInteger256::new(merkle_root)),
+            old_nullifier: BackendNullifier::from(BigInteger256::new(old_nullifier)),
+            new_note: BackendNote::from(BigInteger256::new(new_note)),
+            change_note: BackendNote::from(BigInteger256::new(change_note)),
+            ephemeral_key: transfer_key_to_backend(&ephemeral_key),
+            encrypted_amount: BackendTokenAmount::from(encrypted_amount),
+            spending_secret: BackendSpendingSecret::from(BigInteger256::new(spending_secret)),
+            owner_key: BackendOwnerKey::from(BigInteger256::new(compute_owner_key(
+                spending_secret,
+            ))),
+            old_note: BackendNote::from(BigInteger256::new(old_note)),
+            old_trapdoor: BackendTrapdoor::from(BigInteger256::new(old_trapdoor)),
+            whole_token_amount: BackendTokenAmount::from(whole_token_amount),
+            merkle_path: merkle_path
+                .into_iter()
+                .map(|node| BackendNote::from(BigInteger256::new(node)))
+                .collect(),
+            leaf_index: BackendLeafIndex::from(leaf_index),
+            token_amount: BackendTokenAmount::from(token_amount),
+            new_trapdoor: BackendTrapdoor::from(BigInteger256::new(new_trapdoor)),
+            recipient_owner_key: BackendOwnerKey::from(BigInteger256::new(recipient_owner_key)),
+            change_token_amount: BackendTokenAmount::from(change_token_amount),
+            change_trapdoor: BackendTrapdoor::from(BigInteger256::new(change_trapdoor)),
+        }
+    }
+}
+
+impl ConstraintSynthesizer<CircuitField> for TransferRelation {
+    fn generate_constraints(
+        self,
+        cs: ConstraintSystemRef<CircuitField>,
+    ) -> Result<(), SynthesisError> {
+        //-----------------------
+        // Declare public inputs.
+        //-----------------------
+        let token_id = FpVar::new_input(ns!(cs, "token id"), || Ok(&self.token_id))?;
+        let merkle_root = FpVar::new_input(ns!(cs, "merkle root"), || Ok(&self.merkle_root))?;
+        let old_nullifier = FpVar::new_input(ns!(cs, "old nullifier"), || Ok(&self.old_nullifier))?;
+        let new_note = FpVar::new_input(ns!(cs, "new note"), || Ok(&self.new_note))?;
+        let change_note = FpVar::new_input(ns!(cs, "change note"), || Ok(&self.change_note))?;
+
+        //------------------------------------------------------------------
+        // Baking `ephemeral_key` and `encrypted_amount` into the circuit.
+        //------------------------------------------------------------------
+        let _ephemeral_key =
+            FpVar::new_input(ns!(cs, "ephemeral key"), || Ok(&self.ephemeral_key))?;
+        let _encrypted_amount =
+            FpVar::new_input(ns!(cs, "encrypted amount"), || Ok(&self.encrypted_amount))?;
+
+        //---------------------------------------
+        // Check the ownership of the old note.
+        //---------------------------------------
+        let spending_secret =
+            FpVar::new_witness(ns!(cs, "spending secret"), || Ok(&self.spending_secret))?;
+        let owner_key = FpVar::new_witness(ns!(cs, "owner key"), || Ok(&self.owner_key))?;
+
+        check_owner_key(&spending_secret, &owner_key)?;
+
+        //------------------------------------------
+        // Check the old note and its Merkle proof.
+        //------------------------------------------
+        let old_note = FpVar::new_witness(ns!(cs, "old note"), || Ok(&self.old_note))?;
+        let whole_token_amount = FpVar::new_witness(ns!(cs, "whole token amount"), || {
+            Ok(&self.whole_token_amount)
+        })?;
+        let old_trapdoor = FpVar::new_witness(ns!(cs, "old trapdoor"), || Ok(&self.old_trapdoor))?;
+
+        check_note(
+            &token_id,
+            &whole_token_amount,
+            &old_trapdoor,
+            &owner_key,
+            &old_note,
+        )?;
+
+        let leaf_index = FpVar::new_witness(ns!(cs, "leaf index"), || Ok(&self.leaf_index))?;
+        check_nullifier(&spending_secret, &leaf_index, &old_nullifier)?;
+        check_merkle_proof(
+            cs.clone(),
+            &merkle_root,
+            &old_note,
+            &leaf_index,
+            &self.merkle_path,
+        )?;
+
+        //-----------------------------------
+        // Check the recipient's new note.
+        //-----------------------------------
+        let token_amount = FpVar::new_witness(ns!(cs, "token amount"), || Ok(&self.token_amount))?;
+        let new_trapdoor = FpVar::new_witness(ns!(cs, "new trapdoor"), || Ok(&self.new_trapdoor))?;
+        let recipient_owner_key = FpVar::new_witness(ns!(cs, "recipient owner key"), || {
+            Ok(&self.recipient_owner_key)
+        })?;
+
+        check_note(
+            &token_id,
+            &token_amount,
+            &new_trapdoor,
+            &recipient_owner_key,
+            &new_note,
+        )?;
+
+        //-----------------------
+        // Check the change note.
+        //-----------------------
+        let change_token_amount = FpVar::new_witness(ns!(cs, "change token amount"), || {
+            Ok(&self.change_token_amount)
+        })?;
+        let change_trapdoor =
+            FpVar::new_witness(ns!(cs, "change trapdoor"), || Ok(&self.change_trapdoor))?;
+
+        check_note(
+            &token_id,
+            &change_token_amount,
+            &change_trapdoor,
+            &owner_key,
+            &change_note,
+        )?;
+
+        //----------------------------------
+        // Check the token values soundness.
+        //----------------------------------
+        // All the amounts must be proper `u64` values - otherwise, the sum below could wrap around
+        // the field modulus.
+        for amount in [&whole_token_amount, &token_amount, &change_token_amount] {
+            enforce_fits_in_bits(amount, TOKEN_AMOUNT_BITS)?;
+        }
+
+        let token_sum = token_amount.add(change_token_amount);
+        token_sum.enforce_equal(&whole_token_amount)
+    }
+}
+
+impl GetPublicInput<CircuitField> for TransferRelation {
+    fn named_public_input(&self) -> Vec<(String, CircuitField)> {
+        [
+            ("token_id", self.token_id),
+            ("merkle_root", self.merkle_root),
+            ("old_nullifier", self.old_nullifier),
+            ("new_note", self.new_note),
+            ("change_note", self.change_note),
+            ("ephemeral_key", self.ephemeral_key),
+            ("encrypted_amount", self.encrypted_amount),
+        ]
+        .into_iter()
+        .map(|(name, value)| (String::from(name), value))
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_bls12_381::Bls12_381;
+    use ark_ff::One;
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+
+    use super::*;
+    use crate::relations::shielder::{
+        note::{compute_note_from_backend, receive_transferred_note},
+        WithdrawRelation, WithdrawRelationArgs,
+    };
+
+    fn is_satisfiable<C: ConstraintSynthesizer<CircuitField>>(circuit: C) -> bool {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn transfer_constraints_correctness() {
+        let circuit: TransferRelation = TransferRelationArgs::template().into();
+
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+
+        let is_satisfied = cs.is_satisfied().unwrap();
+        if !is_satisfied {
+            println!("{:?}", cs.which_is_unsatisfied());
+        }
+
+        assert!(is_satisfied);
+    }
+
+    #[test]
+    fn transfer_creating_value_is_unsatisfiable() {
+        let args = TransferRelationArgs::template();
+        let args = TransferRelationArgs {
+            change_note: compute_note(
+                args.token_id,
+                4,
+                args.change_trapdoor,
+                compute_owner_key(args.spending_secret),
+            ),
+            change_token_amount: 4,
+            ..args
+        };
+
+        assert!(!is_satisfiable(TransferRelation::from(args)));
+    }
+
+    #[test]
+    fn transfer_with_overflowing_change_is_unsatisfiable() {
+        // `17` and `-7` sum up to `10` over the field.
+        let circuit = TransferRelation::from(TransferRelationArgs::template());
+        let change_token_amount = -BackendTokenAmount::from(7u64);
+        let token_amount = BackendTokenAmount::from(17u64);
+        let circuit = TransferRelation {
+            new_note: BackendNote::from(BigInteger256::new(compute_note_from_backend(
+                circuit.token_id,
+                token_amount,
+                circuit.new_trapdoor,
+                circuit.recipient_owner_key,
+            ))),
+            token_amount,
+            change_note: BackendNote::from(BigInteger256::new(compute_note_from_backend(
+                circuit.token_id,
+                change_token_amount,
+                circuit.change_trapdoor,
+                circuit.owner_key,
+            ))),
+            change_token_amount,
+            ..circuit
+        };
+
+        assert!(!is_satisfiable(circuit));
+    }
+
+    #[test]
+    fn transfer_with_wrong_spending_secret_is_unsatisfiable() {
+        let circuit = TransferRelation {
+            spending_secret: BackendSpendingSecret::from(20u64),
+            ..TransferRelation::from(TransferRelationArgs::template())
+        };
+
+        assert!(!is_satisfiable(circuit));
+    }
+
+    #[test]
+    fn transfer_with_wrong_merkle_path_is_unsatisfiable() {
+        let mut circuit: TransferRelation = TransferRelationArgs::template().into();
+        circuit.merkle_path.swap(0, 1);
+
+        assert!(!is_satisfiable(circuit));
+    }
+
+    #[test]
+    fn transfer_proving_procedure() {
+        let circuit: TransferRelation = TransferRelationArgs::template().into();
+        let input = circuit.public_input();
+
+        let mut rng = ark_std::test_rng();
+        let (pk, vk) =
+            Groth16::<Bls12_381>::circuit_specific_setup(circuit.clone(), &mut rng).unwrap();
+
+        let proof = Groth16::prove(&pk, circuit, &mut rng).unwrap();
+        let valid_proof = Groth16::verify(&vk, &input, &proof).unwrap();
+        assert!(valid_proof);
+
+        let mut input_with_other_ephemeral_key = input.clone();
+        input_with_other_ephemeral_key[5] += CircuitField::one();
+        let valid_proof = Groth16::verify(&vk, &input_with_other_ephemeral_key, &proof).unwrap();
+        assert!(!valid_proof);
+    }
+
+    #[test]
+    fn recipient_can_withdraw_transferred_note() {
+        let args = TransferRelationArgs::template();
+        let (secret, _) = generate_transfer_keys(&mut StdRng::from_seed([1; 32]));
+
+        let owner_key = compute_owner_key(RECIPIENT_SPENDING_SECRET);
+        let (token_amount, trapdoor) = receive_transferred_note(
+            &secret,
+            owner_key,
+            args.new_note,
+            args.token_id,
+            &args.ephemeral_key,
+            args.encrypted_amount,
+        )
+        .expect("Note was transferred to this key");
+        assert_eq!(token_amount, args.token_amount);
+
+        // The transferred note lands next to some other note in a tree with 2 leaves.
+        let sibling = compute_note(0, 1, [2, 0, 0, 0], [3, 0, 0, 0]);
+        let (new_trapdoor, new_token_amount) = ([31, 0, 0, 0], 2);
+        let withdrawal = WithdrawRelationArgs {
+            max_path_len: 1,
+            old_nullifier: compute_nullifier(RECIPIENT_SPENDING_SECRET, 0),
+            merkle_root: compute_parent_hash(args.new_note, sibling),
+            new_note: compute_note(args.token_id, new_token_amount, new_trapdoor, owner_key),
+            token_id: args.token_id,
+            token_amount_out: token_amount - new_token_amount,
+            fee: 1,
+            recipient: [7; 32],
+            old_trapdoor: trapdoor,
+            new_trapdoor,
+            spending_secret: RECIPIENT_SPENDING_SECRET,
+            merkle_path: vec![sibling],
+            leaf_index: 0,
+            old_note: args.new_note,
+            whole_token_amount: token_amount,
+            new_token_amount,
+        };
+
+        assert!(is_satisfiable(WithdrawRelation::from(withdrawal)));
+    }
+}
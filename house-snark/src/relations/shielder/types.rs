@@ -6,8 +6,14 @@ pub(super) type FpVar = ark_r1cs_std::fields::fp::FpVar<CircuitField>;
 pub(super) type ByteVar = ark_r1cs_std::uint8::UInt8<CircuitField>;
 
 // Types accepted by the relation constructors.
-pub type FrontendNullifier = u64;
-pub type FrontendTrapdoor = u64;
+/// Hash of the spending secret and the leaf index of a note (revealed when spending the note).
+pub type FrontendNullifier = [u64; 4];
+/// Random blinding of a note: a field element, little-endian limbs.
+pub type FrontendTrapdoor = [u64; 4];
+/// Secret owning notes: a field element, little-endian limbs.
+pub type FrontendSpendingSecret = [u64; 4];
+/// Hash of the spending secret, included in every note owned by it.
+pub type FrontendOwnerKey = [u64; 4];
 pub type FrontendNote = [u64; 4];
 pub type FrontendTokenId = u16;
 pub type FrontendTokenAmount = u64;
@@ -16,10 +22,20 @@ pub type FrontendMerklePath = Vec<[u64; 4]>;
 pub type FrontendLeafIndex = u64;
 pub type FrontendAccount = [u8; 32];
 pub type FrontendMerklePathNode = [u64; 4];
+/// Public key for receiving private transfers (or an ephemeral key of a single transfer): the `x`
+/// coordinate of a Jubjub point, little-endian.
+pub type FrontendTransferKey = [u8; 32];
+/// Secret counterpart of `FrontendTransferKey`: a Jubjub scalar, little-endian.
+pub type FrontendTransferSecret = [u8; 32];
+
+/// Number of bits, which every token amount must fit in (in circuit).
+pub(super) const TOKEN_AMOUNT_BITS: usize = FrontendTokenAmount::BITS as usize;
 
 // Types used internally by the relations (but still outside circuit environment).
 pub(super) type BackendNullifier = CircuitField;
 pub(super) type BackendTrapdoor = CircuitField;
+pub(super) type BackendSpendingSecret = CircuitField;
+pub(super) type BackendOwnerKey = CircuitField;
 pub(super) type BackendNote = CircuitField;
 pub(super) type BackendTokenId = CircuitField;
 pub(super) type BackendTokenAmount = CircuitField;
@@ -27,6 +43,7 @@ pub(super) type BackendMerkleRoot = CircuitField;
 pub(super) type BackendMerklePath = Vec<CircuitField>;
 pub(super) type BackendLeafIndex = CircuitField;
 pub(super) type BackendAccount = CircuitField;
+pub(super) type BackendTransferKey = CircuitField;
 
 /*
 This is a setup for using Pedersen hashing (with field element compressing). It would work well, but
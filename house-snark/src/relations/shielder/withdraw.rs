@@ -1,85 +1,191 @@
-use std::ops::{Add, Div};
+use std::ops::Add;
 
-use ark_ff::{BigInteger, BigInteger256};
-use ark_r1cs_std::{alloc::AllocVar, eq::EqGadget, fields::FieldVar, R1CSVar, ToBytesGadget};
+use ark_ff::BigInteger256;
+use ark_r1cs_std::{alloc::AllocVar, eq::EqGadget};
 use ark_relations::{
     ns,
     r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
 };
 use clap::Args;
+use serde::{Deserialize, Serialize};
 
 use super::{
-    note::check_note,
+    note::{
+        check_merkle_proof, check_note, check_nullifier, check_owner_key, compute_note,
+        compute_nullifier, compute_owner_key, compute_parent_hash,
+    },
     parser::{
         parse_frontend_account, parse_frontend_merkle_path_single, parse_frontend_merkle_root,
-        parse_frontend_note,
+        parse_frontend_note, parse_frontend_nullifier, parse_frontend_spending_secret,
+        parse_frontend_trapdoor, validate_merkle_path_len,
     },
-    tangle::tangle_in_field,
+    range_check::{enforce_fits_in_bits, enforce_less_or_equal},
     types::{
         BackendAccount, BackendLeafIndex, BackendMerklePath, BackendMerkleRoot, BackendNote,
-        BackendNullifier, BackendTokenAmount, BackendTokenId, BackendTrapdoor, ByteVar, FpVar,
-        FrontendAccount, FrontendLeafIndex, FrontendMerklePath, FrontendMerkleRoot, FrontendNote,
-        FrontendNullifier, FrontendTokenAmount, FrontendTokenId, FrontendTrapdoor,
+        BackendNullifier, BackendOwnerKey, BackendSpendingSecret, BackendTokenAmount,
+        BackendTokenId, BackendTrapdoor, FpVar, FrontendAccount, FrontendLeafIndex,
+        FrontendMerklePath, FrontendMerkleRoot, FrontendNote, FrontendNullifier,
+        FrontendSpendingSecret, FrontendTokenAmount, FrontendTokenId, FrontendTrapdoor,
+        TOKEN_AMOUNT_BITS,
     },
     CircuitField,
 };
-use crate::relations::GetPublicInput;
+use crate::relations::{witness::WitnessTemplate, GetPublicInput};
 
-#[derive(Clone, Eq, PartialEq, Hash, Debug, Args)]
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct WithdrawRelationArgs {
+    // Constant parameters.
+    #[clap(long, default_value = "16")]
+    #[serde(with = "crate::relations::witness::integer")]
+    pub max_path_len: u8,
+
     // Public inputs.
-    #[clap(long)]
+    #[clap(long, value_parser = parse_frontend_nullifier)]
+    #[serde(with = "super::parser::note_hex")]
     pub old_nullifier: FrontendNullifier,
     #[clap(long, value_parser = parse_frontend_merkle_root)]
+    #[serde(with = "super::parser::note_hex")]
     pub merkle_root: FrontendMerkleRoot,
     #[clap(long, value_parser = parse_frontend_note)]
+    #[serde(with = "super::parser::note_hex")]
     pub new_note: FrontendNote,
     #[clap(long)]
+    #[serde(with = "crate::relations::witness::integer")]
     pub token_id: FrontendTokenId,
     #[clap(long)]
+    #[serde(with = "crate::relations::witness::integer")]
     pub token_amount_out: FrontendTokenAmount,
     #[clap(long)]
+    #[serde(with = "crate::relations::witness::integer")]
     pub fee: FrontendTokenAmount,
     #[clap(long, value_parser = parse_frontend_account)]
+    #[serde(with = "crate::relations::witness::bytes32")]
     pub recipient: FrontendAccount,
 
     // Private inputs.
-    #[clap(long)]
+    #[clap(long, value_parser = parse_frontend_trapdoor)]
+    #[serde(with = "super::parser::note_hex")]
     pub old_trapdoor: FrontendTrapdoor,
-    #[clap(long)]
+    #[clap(long, value_parser = parse_frontend_trapdoor)]
+    #[serde(with = "super::parser::note_hex")]
     pub new_trapdoor: FrontendTrapdoor,
-    #[clap(long)]
-    pub new_nullifier: FrontendNullifier,
+    #[clap(long, value_parser = parse_frontend_spending_secret)]
+    #[serde(with = "super::parser::note_hex")]
+    pub spending_secret: FrontendSpendingSecret,
     #[clap(long, value_delimiter = ',', value_parser = parse_frontend_merkle_path_single)]
+    #[serde(with = "super::parser::merkle_path_hex")]
     pub merkle_path: FrontendMerklePath,
     #[clap(long)]
+    #[serde(with = "crate::relations::witness::integer")]
     pub leaf_index: FrontendLeafIndex,
     #[clap(long, value_parser = parse_frontend_note)]
+    #[serde(with = "super::parser::note_hex")]
     pub old_note: FrontendNote,
     #[clap(long)]
+    #[serde(with = "crate::relations::witness::integer")]
     pub whole_token_amount: FrontendTokenAmount,
     #[clap(long)]
+    #[serde(with = "crate::relations::witness::integer")]
     pub new_token_amount: FrontendTokenAmount,
 }
 
+impl WitnessTemplate for WithdrawRelationArgs {
+    fn template() -> Self {
+        WithdrawRelationArgs::template_of_height(2)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        validate_merkle_path_len(&self.merkle_path, self.max_path_len)
+    }
+}
+
+impl WithdrawRelationArgs {
+    /// Withdrawal of a note at leaf `1` of a tree of height `max_path_len`.
+    pub fn template_of_height(max_path_len: u8) -> Self {
+        assert!(max_path_len > 0, "Tree must have at least two leaves");
+
+        let token_id = 1;
+        let spending_secret = [19, 0, 0, 0];
+        let (old_trapdoor, whole_token_amount) = ([17, 0, 0, 0], 10);
+        let (new_trapdoor, new_token_amount) = ([27, 0, 0, 0], 3);
+        let leaf_index = 1;
+
+        let owner_key = compute_owner_key(spending_secret);
+        let old_note = compute_note(token_id, whole_token_amount, old_trapdoor, owner_key);
+        let merkle_path = (0..max_path_len as u64)
+            .map(|level| {
+                let base = 4 * level;
+                compute_note(
+                    base as FrontendTokenId,
+                    base + 1,
+                    base + 2,
+                    [base + 3, 0, 0, 0],
+                )
+            })
+            .collect::<Vec<_>>();
+        let merkle_root =
+            merkle_path
+                .iter()
+                .enumerate()
+                .fold(old_note, |node, (level, &sibling)| {
+                    if (leaf_index >> level) & 1 == 1 {
+                        compute_parent_hash(sibling, node)
+                    } else {
+                        compute_parent_hash(node, sibling)
+                    }
+                });
+
+        WithdrawRelationArgs {
+            max_path_len,
+            old_nullifier: compute_nullifier(spending_secret, leaf_index),
+            merkle_root,
+            new_note: compute_note(token_id, new_token_amount, new_trapdoor, owner_key),
+            token_id,
+            token_amount_out: 7,
+            fee: 1,
+            recipient: [7; 32],
+            old_trapdoor,
+            new_trapdoor,
+            spending_secret,
+            merkle_path,
+            leaf_index,
+            old_note,
+            whole_token_amount,
+            new_token_amount,
+        }
+    }
+}
+
 /// 'Withdraw' relation for the Shielder application.
 ///
 /// It expresses the facts that:
-///  - `new_note` is a prefix of the result of tangling together `token_id`, `whole_token_amount`,
-///    `old_trapdoor` and `old_nullifier`,
-///  - `old_note` is a prefix of the result of tangling together `token_id`, `new_token_amount`,
-///    `new_trapdoor` and `new_nullifier`,
+///  - `owner_key` is the result of tangling `spending_secret`,
+///  - `old_note` is a prefix of the result of tangling together `token_id`, `whole_token_amount`,
+///    `old_trapdoor` and `owner_key`,
+///  - `new_note` is a prefix of the result of tangling together `token_id`, `new_token_amount`,
+///    `new_trapdoor` and `owner_key` (so the remaining tokens stay with the same owner),
+///  - `old_nullifier` is the result of tangling together `spending_secret` and `leaf_index`,
 ///  - `new_token_amount + token_amount_out = whole_token_amount`
+///  - `whole_token_amount`, `new_token_amount`, `token_amount_out` and `fee` are 64-bit values and
+///    `fee <= token_amount_out`
 ///  - `merkle_path` is a valid Merkle proof for `old_note` being present at `leaf_index` in some
 ///    Merkle tree with `merkle_root` hash in the root
-/// It also includes two artificial inputs `fee` and `recipient` just to strengthen the application
-/// security by treating them as public inputs (and thus integral part of the SNARK).
+///  - `leaf_index` is smaller than `2^max_path_len`
+/// The tree height is fixed by the constant parameter `max_path_len` (the length of `merkle_path`),
+/// so a single proving key serves every leaf of a tree of that height.
+/// It also includes an artificial input `recipient` (and, apart from the range check, `fee`) just
+/// to strengthen the application security by treating them as public inputs (and thus integral
+/// part of the SNARK).
 ///
 /// When providing a public input to proof verification, you should keep the order of variable
 /// declarations in circuit, i.e.: `fee`, `recipient`, `token_id`, `old_nullifier`, `new_note`,
 /// `token_amount_out`, `merkle_root`.
 #[derive(Clone)]
 pub struct WithdrawRelation {
+    // Constant parameters.
+    pub max_path_len: u8,
+
     // Public inputs.
     pub fee: BackendTokenAmount,
     pub recipient: BackendAccount,
@@ -92,7 +198,9 @@ pub struct WithdrawRelation {
     // Private inputs.
     pub old_trapdoor: BackendTrapdoor,
     pub new_trapdoor: BackendTrapdoor,
-    pub new_nullifier: BackendNullifier,
+    pub spending_secret: BackendSpendingSecret,
+    /// Derived from `spending_secret`.
+    pub owner_key: BackendOwnerKey,
     pub merkle_path: BackendMerklePath,
     pub leaf_index: BackendLeafIndex,
     pub old_note: BackendNote,
@@ -103,6 +211,7 @@ pub struct WithdrawRelation {
 impl WithdrawRelation {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
+        max_path_len: u8,
         old_nullifier: FrontendNullifier,
         merkle_root: FrontendMerkleRoot,
         new_note: FrontendNote,
@@ -110,7 +219,7 @@ impl WithdrawRelation {
         token_amount_out: FrontendTokenAmount,
         old_trapdoor: FrontendTrapdoor,
         new_trapdoor: FrontendTrapdoor,
-        new_nullifier: FrontendNullifier,
+        spending_secret: FrontendSpendingSecret,
         merkle_path: FrontendMerklePath,
         leaf_index: FrontendLeafIndex,
         old_note: FrontendNote,
@@ -119,15 +228,25 @@ impl WithdrawRelation {
         fee: FrontendTokenAmount,
         recipient: FrontendAccount,
     ) -> Self {
+        assert_eq!(
+            merkle_path.len(),
+            max_path_len as usize,
+            "Merkle path length doesn't match the tree height"
+        );
+
         Self {
-            old_nullifier: BackendNullifier::from(old_nullifier),
+            max_path_len,
+            old_nullifier: BackendNullifier::from(BigInteger256::new(old_nullifier)),
             merkle_root: BackendMerkleRoot::from(BigInteger256::new(merkle_root)),
             new_note: BackendNote::from(BigInteger256::new(new_note)),
             token_id: BackendTokenId::from(token_id),
             token_amount_out: BackendTokenAmount::from(token_amount_out),
-            old_trapdoor: BackendTrapdoor::from(old_trapdoor),
-            new_trapdoor: BackendTrapdoor::from(new_trapdoor),
-            new_nullifier: BackendNullifier::from(new_nullifier),
+            old_trapdoor: BackendTrapdoor::from(BigInteger256::new(old_trapdoor)),
+            new_trapdoor: BackendTrapdoor::from(BigInteger256::new(new_trapdoor)),
+            spending_secret: BackendSpendingSecret::from(BigInteger256::new(spending_secret)),
+            owner_key: BackendOwnerKey::from(BigInteger256::new(compute_owner_key(
+                spending_secret,
+            ))),
             merkle_path: merkle_path
                 .iter()
                 .map(|node| BackendNote::from(BigInteger256::new(*node)))
@@ -150,6 +269,7 @@ impl WithdrawRelation {
 impl From<WithdrawRelationArgs> for WithdrawRelation {
     fn from(args: WithdrawRelationArgs) -> Self {
         let WithdrawRelationArgs {
+            max_path_len,
             old_nullifier,
             merkle_root,
             new_note,
@@ -157,7 +277,7 @@ impl From<WithdrawRelationArgs> for WithdrawRelation {
             token_amount_out,
             old_trapdoor,
             new_trapdoor,
-            new_nullifier,
+            spending_secret,
             merkle_path,
             leaf_index,
             old_note,
@@ -167,6 +287,7 @@ impl From<WithdrawRelationArgs> for WithdrawRelation {
             recipient,
         } = args;
         WithdrawRelation::new(
+            max_path_len,
             old_nullifier,
             merkle_root,
             new_note,
@@ -174,7 +295,7 @@ impl From<WithdrawRelationArgs> for WithdrawRelation {
             token_amount_out,
             old_trapdoor,
             new_trapdoor,
-            new_nullifier,
+            spending_secret,
             merkle_path,
             leaf_index,
             old_note,
@@ -194,9 +315,18 @@ impl ConstraintSynthesizer<CircuitField> for WithdrawRelation {
         //-----------------------------------------------
         // Baking `fee` and `recipient` into the circuit.
         //-----------------------------------------------
-        let _fee = FpVar::new_input(ns!(cs, "fee"), || Ok(&self.fee))?;
+        let fee = FpVar::new_input(ns!(cs, "fee"), || Ok(&self.fee))?;
         let _recipient = FpVar::new_input(ns!(cs, "recipient"), || Ok(&self.recipient))?;
 
+        //--------------------------------------
+        // Check the ownership of the old note.
+        //--------------------------------------
+        let spending_secret =
+            FpVar::new_witness(ns!(cs, "spending secret"), || Ok(&self.spending_secret))?;
+        let owner_key = FpVar::new_witness(ns!(cs, "owner key"), || Ok(&self.owner_key))?;
+
+        check_owner_key(&spending_secret, &owner_key)?;
+
         //------------------------------
         // Check the old note arguments.
         //------------------------------
@@ -212,7 +342,7 @@ impl ConstraintSynthesizer<CircuitField> for WithdrawRelation {
             &token_id,
             &whole_token_amount,
             &old_trapdoor,
-            &old_nullifier,
+            &owner_key,
             &old_note,
         )?;
 
@@ -223,14 +353,12 @@ impl ConstraintSynthesizer<CircuitField> for WithdrawRelation {
         let new_token_amount =
             FpVar::new_witness(ns!(cs, "new token amount"), || Ok(&self.new_token_amount))?;
         let new_trapdoor = FpVar::new_witness(ns!(cs, "new trapdoor"), || Ok(&self.new_trapdoor))?;
-        let new_nullifier =
-            FpVar::new_witness(ns!(cs, "new nullifier"), || Ok(&self.new_nullifier))?;
 
         check_note(
             &token_id,
             &new_token_amount,
             &new_trapdoor,
-            &new_nullifier,
+            &owner_key,
             &new_note,
         )?;
 
@@ -239,94 +367,84 @@ impl ConstraintSynthesizer<CircuitField> for WithdrawRelation {
         //----------------------------------
         let token_amount_out =
             FpVar::new_input(ns!(cs, "token amount out"), || Ok(&self.token_amount_out))?;
-        // some range checks for overflows?
+        // All the amounts must be proper `u64` values - otherwise, the sum below could wrap around
+        // the field modulus, e.g. for `new_token_amount = -1`.
+        for amount in [
+            &whole_token_amount,
+            &new_token_amount,
+            &token_amount_out,
+            &fee,
+        ] {
+            enforce_fits_in_bits(amount, TOKEN_AMOUNT_BITS)?;
+        }
+        enforce_less_or_equal(&fee, &token_amount_out, TOKEN_AMOUNT_BITS)?;
+
         let token_sum = token_amount_out.add(new_token_amount);
         token_sum.enforce_equal(&whole_token_amount)?;
 
-        //------------------------
-        // Check the merkle proof.
-        //------------------------
+        //--------------------------------------------
+        // Check the nullifier and the merkle proof.
+        //--------------------------------------------
         let merkle_root = FpVar::new_input(ns!(cs, "merkle root"), || Ok(&self.merkle_root))?;
-        let mut leaf_index = FpVar::new_witness(ns!(cs, "leaf index"), || Ok(&self.leaf_index))?;
-
-        let mut current_hash_bytes = old_note.to_bytes()?;
-        for hash in self.merkle_path {
-            let sibling = FpVar::new_witness(ns!(cs, "merkle path node"), || Ok(hash))?;
-            let bytes: Vec<ByteVar> = if leaf_index.value().unwrap_or_default().0.is_even() {
-                [current_hash_bytes.clone(), sibling.to_bytes()?].concat()
-            } else {
-                [sibling.to_bytes()?, current_hash_bytes.clone()].concat()
-            };
-
-            current_hash_bytes = tangle_in_field::<2>(bytes)?;
-
-            leaf_index = FpVar::constant(
-                leaf_index
-                    .value()
-                    .unwrap_or_default()
-                    .div(CircuitField::from(2)),
-            );
-        }
+        let leaf_index = FpVar::new_witness(ns!(cs, "leaf index"), || Ok(&self.leaf_index))?;
 
-        for (a, b) in merkle_root
-            .to_bytes()?
-            .iter()
-            .zip(current_hash_bytes.iter())
-        {
-            a.enforce_equal(b)?;
-        }
-
-        Ok(())
+        check_nullifier(&spending_secret, &leaf_index, &old_nullifier)?;
+        check_merkle_proof(cs, &merkle_root, &old_note, &leaf_index, &self.merkle_path)
     }
 }
 
 impl GetPublicInput<CircuitField> for WithdrawRelation {
-    fn public_input(&self) -> Vec<CircuitField> {
+    fn named_public_input(&self) -> Vec<(String, CircuitField)> {
         [
-            self.fee,
-            self.recipient,
-            self.token_id,
-            self.old_nullifier,
-            self.new_note,
-            self.token_amount_out,
-            self.merkle_root,
+            ("fee", self.fee),
+            ("recipient", self.recipient),
+            ("token_id", self.token_id),
+            ("old_nullifier", self.old_nullifier),
+            ("new_note", self.new_note),
+            ("token_amount_out", self.token_amount_out),
+            ("merkle_root", self.merkle_root),
         ]
-        .to_vec()
+        .into_iter()
+        .map(|(name, value)| (String::from(name), value))
+        .collect()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use ark_bls12_381::Bls12_381;
+    use ark_ff::One;
     use ark_groth16::Groth16;
     use ark_relations::r1cs::ConstraintSystem;
     use ark_snark::SNARK;
 
     use super::*;
-    use crate::relations::shielder::note::{compute_note, compute_parent_hash};
+    use crate::relations::shielder::note::compute_note_from_backend;
+
+    const SPENDING_SECRET: FrontendSpendingSecret = [19, 0, 0, 0];
 
     fn get_circuit_and_input() -> (WithdrawRelation, [CircuitField; 7]) {
         let token_id: FrontendTokenId = 1;
+        let owner_key = compute_owner_key(SPENDING_SECRET);
 
-        let old_trapdoor: FrontendTrapdoor = 17;
-        let old_nullifier: FrontendNullifier = 19;
+        let old_trapdoor: FrontendTrapdoor = [17, 0, 0, 0];
         let whole_token_amount: FrontendTokenAmount = 10;
 
-        let new_trapdoor: FrontendTrapdoor = 27;
-        let new_nullifier: FrontendNullifier = 87;
+        let new_trapdoor: FrontendTrapdoor = [27, 0, 0, 0];
         let new_token_amount: FrontendTokenAmount = 3;
 
         let token_amount_out: FrontendTokenAmount = 7;
 
-        let old_note = compute_note(token_id, whole_token_amount, old_trapdoor, old_nullifier);
-        let new_note = compute_note(token_id, new_token_amount, new_trapdoor, new_nullifier);
+        let old_note = compute_note(token_id, whole_token_amount, old_trapdoor, owner_key);
+        let new_note = compute_note(token_id, new_token_amount, new_trapdoor, owner_key);
 
         // Our leaf has a left bro. Their parent has a right bro. Our grandpa is the root.
-        let leaf_index = 5;
+        let leaf_index = 1;
+        let old_nullifier = compute_nullifier(SPENDING_SECRET, leaf_index);
 
-        let sibling_note = compute_note(0, 1, 2, 3);
+        let sibling_note = compute_note(0, 1, [2, 0, 0, 0], [3, 0, 0, 0]);
         let parent_note = compute_parent_hash(sibling_note, old_note);
-        let uncle_note = compute_note(4, 5, 6, 7);
+        let uncle_note = compute_note(4, 5, [6, 0, 0, 0], [7, 0, 0, 0]);
         let merkle_root = compute_parent_hash(parent_note, uncle_note);
 
         let merkle_path = vec![sibling_note, uncle_note];
@@ -338,6 +456,7 @@ mod tests {
         ];
 
         let circuit = WithdrawRelation::new(
+            2,
             old_nullifier,
             merkle_root,
             new_note,
@@ -345,7 +464,7 @@ mod tests {
             token_amount_out,
             old_trapdoor,
             new_trapdoor,
-            new_nullifier,
+            SPENDING_SECRET,
             merkle_path,
             leaf_index,
             old_note,
@@ -375,6 +494,194 @@ mod tests {
         assert!(is_satisfied);
     }
 
+    /// Move the note from `get_circuit_and_input` to `leaf_index` in a tree with 4 leaves.
+    fn get_circuit_for_leaf(leaf_index: FrontendLeafIndex) -> WithdrawRelation {
+        let (circuit, _input) = get_circuit_and_input();
+
+        let mut leaves: Vec<FrontendNote> = (0..4)
+            .map(|i| compute_note(0, i, [2, 0, 0, 0], [3, 0, 0, 0]))
+            .collect();
+        leaves[leaf_index as usize] =
+            compute_note(1, 10, [17, 0, 0, 0], compute_owner_key(SPENDING_SECRET));
+        let parents = [
+            compute_parent_hash(leaves[0], leaves[1]),
+            compute_parent_hash(leaves[2], leaves[3]),
+        ];
+        let merkle_root = compute_parent_hash(parents[0], parents[1]);
+
+        let idx = leaf_index as usize;
+        let merkle_path = [leaves[idx ^ 1], parents[(idx >> 1) ^ 1]];
+
+        WithdrawRelation {
+            old_nullifier: BackendNullifier::from(BigInteger256::new(compute_nullifier(
+                SPENDING_SECRET,
+                leaf_index,
+            ))),
+            merkle_root: BackendMerkleRoot::from(BigInteger256::new(merkle_root)),
+            merkle_path: merkle_path
+                .iter()
+                .map(|node| BackendNote::from(BigInteger256::new(*node)))
+                .collect(),
+            leaf_index: BackendLeafIndex::from(leaf_index),
+            ..circuit
+        }
+    }
+
+    fn is_satisfiable(circuit: WithdrawRelation) -> bool {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn templates_of_any_height_are_satisfiable() {
+        for height in [1, 5] {
+            let circuit: WithdrawRelation = WithdrawRelationArgs::template_of_height(height).into();
+            assert!(
+                is_satisfiable(circuit),
+                "Template of height {} is unsatisfied",
+                height
+            );
+        }
+    }
+
+    #[test]
+    fn withdraw_with_wrong_old_note_is_unsatisfiable() {
+        let (circuit, _input) = get_circuit_and_input();
+        let wrong_note = compute_note(1, 11, [17, 0, 0, 0], compute_owner_key(SPENDING_SECRET));
+        let circuit = WithdrawRelation {
+            old_note: BackendNote::from(BigInteger256::new(wrong_note)),
+            ..circuit
+        };
+
+        assert!(!is_satisfiable(circuit));
+    }
+
+    #[test]
+    fn withdraw_with_wrong_spending_secret_is_unsatisfiable() {
+        let (circuit, _input) = get_circuit_and_input();
+        let circuit = WithdrawRelation {
+            spending_secret: BackendSpendingSecret::from(20u64),
+            ..circuit
+        };
+
+        assert!(!is_satisfiable(circuit));
+    }
+
+    #[test]
+    fn withdraw_with_nullifier_of_other_leaf_is_unsatisfiable() {
+        let circuit = WithdrawRelation {
+            old_nullifier: BackendNullifier::from(BigInteger256::new(compute_nullifier(
+                SPENDING_SECRET,
+                0,
+            ))),
+            ..get_circuit_for_leaf(1)
+        };
+
+        assert!(!is_satisfiable(circuit));
+    }
+
+    #[test]
+    fn withdraw_with_wrong_merkle_path_is_unsatisfiable() {
+        let (mut circuit, _input) = get_circuit_and_input();
+        let wrong_sibling = compute_note(0, 1, [2, 0, 0, 0], [4, 0, 0, 0]);
+        circuit.merkle_path[0] = BackendNote::from(BigInteger256::new(wrong_sibling));
+
+        assert!(!is_satisfiable(circuit));
+    }
+
+    #[test]
+    fn withdraw_with_wrong_leaf_index_is_unsatisfiable() {
+        let circuit = WithdrawRelation {
+            leaf_index: BackendLeafIndex::from(2u64),
+            ..get_circuit_for_leaf(1)
+        };
+
+        assert!(!is_satisfiable(circuit));
+    }
+
+    #[test]
+    fn withdraw_with_leaf_index_out_of_tree_is_unsatisfiable() {
+        // 5 = 0b101, so the two lowest bits point to the correct leaf (1). The nullifier matches
+        // the index, so that only the range check can fail.
+        let circuit = WithdrawRelation {
+            old_nullifier: BackendNullifier::from(BigInteger256::new(compute_nullifier(
+                SPENDING_SECRET,
+                5,
+            ))),
+            leaf_index: BackendLeafIndex::from(5u64),
+            ..get_circuit_for_leaf(1)
+        };
+
+        assert!(!is_satisfiable(circuit));
+    }
+
+    #[test]
+    fn one_proving_key_serves_every_leaf() {
+        let mut rng = ark_std::test_rng();
+        let (pk, vk) =
+            Groth16::<Bls12_381>::circuit_specific_setup(get_circuit_for_leaf(0), &mut rng)
+                .unwrap();
+
+        for leaf_index in 0..4 {
+            let circuit = get_circuit_for_leaf(leaf_index);
+            let input = circuit.public_input();
+
+            let proof = Groth16::prove(&pk, circuit, &mut rng).unwrap();
+            let valid_proof = Groth16::verify(&vk, &input, &proof).unwrap();
+            assert!(valid_proof);
+        }
+    }
+
+    #[test]
+    fn withdraw_with_overflowing_new_token_amount_is_unsatisfiable() {
+        // `new_token_amount = -1` together with `token_amount_out = whole_token_amount + 1` would
+        // satisfy the balance equation over the field.
+        let (circuit, _input) = get_circuit_and_input();
+        let new_token_amount = -BackendTokenAmount::one();
+        let new_note = compute_note_from_backend(
+            circuit.token_id,
+            new_token_amount,
+            circuit.new_trapdoor,
+            circuit.owner_key,
+        );
+        let circuit = WithdrawRelation {
+            new_note: BackendNote::from(BigInteger256::new(new_note)),
+            new_token_amount,
+            token_amount_out: circuit.whole_token_amount + BackendTokenAmount::one(),
+            ..circuit
+        };
+
+        assert!(!is_satisfiable(circuit));
+    }
+
+    #[test]
+    fn withdraw_with_overflowing_token_amount_out_is_unsatisfiable() {
+        // `token_amount_out = -1` together with `new_token_amount = whole_token_amount + 1` would
+        // satisfy the balance equation over the field.
+        let (circuit, _input) = get_circuit_and_input();
+        let new_note = compute_note(1, 11, [27, 0, 0, 0], compute_owner_key(SPENDING_SECRET));
+        let circuit = WithdrawRelation {
+            new_note: BackendNote::from(BigInteger256::new(new_note)),
+            new_token_amount: BackendTokenAmount::from(11u64),
+            token_amount_out: -BackendTokenAmount::one(),
+            ..circuit
+        };
+
+        assert!(!is_satisfiable(circuit));
+    }
+
+    #[test]
+    fn withdraw_with_fee_exceeding_token_amount_out_is_unsatisfiable() {
+        let (circuit, _input) = get_circuit_and_input();
+        let circuit = WithdrawRelation {
+            fee: BackendTokenAmount::from(8u64),
+            ..circuit
+        };
+
+        assert!(!is_satisfiable(circuit));
+    }
+
     #[test]
     fn withdraw_proving_procedure() {
         let (circuit, input) = get_circuit_and_input();
@@ -0,0 +1,155 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+};
+
+use ark_relations::r1cs::{ConstraintMatrices, ConstraintSystemRef};
+
+use crate::relations::types::CircuitField;
+
+/// Namespace for constraints enforced outside of any namespace.
+const ROOT_NAMESPACE: &str = "<root>";
+
+/// Number of constraints and non-zero entries of the constraint matrices.
+#[derive(Clone, Default, Eq, PartialEq, Debug)]
+pub struct ConstraintCounts {
+    pub num_constraints: usize,
+    pub a_num_non_zero: usize,
+    pub b_num_non_zero: usize,
+    pub c_num_non_zero: usize,
+}
+
+/// Size of a synthesized relation.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ConstraintStatistics {
+    /// Number of instance variables (including the constant `1`).
+    pub num_instance_variables: usize,
+    pub num_witness_variables: usize,
+    pub total: ConstraintCounts,
+    /// Breakdown by the outermost namespace (or gadget) in which the constraints were enforced.
+    pub by_namespace: BTreeMap<String, ConstraintCounts>,
+}
+
+impl ConstraintStatistics {
+    /// Collects statistics from a finalized constraint system.
+    ///
+    /// Namespaces are known only if the constraints were traced with `ConstraintLayer`.
+    pub(super) fn collect(cs: &ConstraintSystemRef<CircuitField>) -> Self {
+        let ConstraintMatrices {
+            num_instance_variables,
+            num_witness_variables,
+            a,
+            b,
+            c,
+            ..
+        } = cs
+            .to_matrices()
+            .expect("Failed to build constraint matrices");
+        let names = cs.constraint_names();
+
+        let mut total = ConstraintCounts::default();
+        let mut by_namespace = BTreeMap::<String, ConstraintCounts>::new();
+        for (i, ((a, b), c)) in a.iter().zip(b.iter()).zip(c.iter()).enumerate() {
+            let namespace = names
+                .as_ref()
+                .map_or(ROOT_NAMESPACE, |names| outermost_namespace(&names[i]));
+            for counts in [
+                &mut total,
+                by_namespace.entry(namespace.to_string()).or_default(),
+            ] {
+                counts.num_constraints += 1;
+                counts.a_num_non_zero += a.len();
+                counts.b_num_non_zero += b.len();
+                counts.c_num_non_zero += c.len();
+            }
+        }
+
+        ConstraintStatistics {
+            num_instance_variables,
+            num_witness_variables,
+            total,
+            by_namespace,
+        }
+    }
+}
+
+/// Extracts the outermost namespace from a constraint trace like `/module::namespace/gadget`.
+fn outermost_namespace(constraint_name: &str) -> &str {
+    constraint_name
+        .split('/')
+        .find(|step| !step.is_empty())
+        .map(|step| step.rsplit("::").next().unwrap_or(step))
+        .filter(|namespace| !namespace.is_empty())
+        .unwrap_or(ROOT_NAMESPACE)
+}
+
+impl Display for ConstraintStatistics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Constraints:        {}", self.total.num_constraints)?;
+        writeln!(f, "Instance variables: {}", self.num_instance_variables)?;
+        writeln!(f, "Witness variables:  {}", self.num_witness_variables)?;
+        writeln!(
+            f,
+            "Non-zero entries:   A: {}, B: {}, C: {}",
+            self.total.a_num_non_zero, self.total.b_num_non_zero, self.total.c_num_non_zero
+        )?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:<40} {:>12} {:>10} {:>10} {:>10}",
+            "Namespace", "Constraints", "A", "B", "C"
+        )?;
+        for (namespace, counts) in &self.by_namespace {
+            writeln!(
+                f,
+                "{:<40} {:>12} {:>10} {:>10} {:>10}",
+                namespace,
+                counts.num_constraints,
+                counts.a_num_non_zero,
+                counts.b_num_non_zero,
+                counts.c_num_non_zero
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_outermost_namespace() {
+        assert_eq!(
+            outermost_namespace(
+                "/house_snark::relations::merkle path node/r1cs_std::enforce_equal"
+            ),
+            "merkle path node"
+        );
+        assert_eq!(outermost_namespace("/enforce_equal"), "enforce_equal");
+        assert_eq!(outermost_namespace(""), ROOT_NAMESPACE);
+    }
+
+    #[cfg(all(feature = "std", feature = "withdraw"))]
+    #[test]
+    fn withdraw_constraints_are_attributed_to_logical_blocks() {
+        use crate::relations::{Relation, WithdrawRelationArgs, Witness, WitnessTemplate};
+
+        let statistics =
+            Relation::Withdraw(Witness(WithdrawRelationArgs::template())).constraint_statistics();
+
+        for namespace in [
+            "merkle path node",
+            "merkle root",
+            "note",
+            "nullifier",
+            "owner key",
+        ] {
+            let counts = statistics
+                .by_namespace
+                .get(namespace)
+                .unwrap_or_else(|| panic!("No constraints in namespace {}", namespace));
+            assert!(counts.num_constraints > 0, "{}", namespace);
+        }
+    }
+}
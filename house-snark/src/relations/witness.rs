@@ -0,0 +1,300 @@
+//! Witness files: relation arguments given in a JSON or TOML file instead of CLI flags.
+//!
+//! The schema of a witness file mirrors the arguments struct of the relation (field names are the
+//! same as in Rust, i.e. in `snake_case`). Integers can be given as numbers, decimal strings or
+//! `0x`-prefixed hex strings, while byte arrays (notes, Merkle tree nodes, accounts) are hex strings.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::{error::ErrorKind, value_parser, Arg, ArgMatches, Args, Command, FromArgMatches};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Name of the argument holding the path to a witness file.
+const WITNESS_FILE: &str = "witness_file";
+
+/// Relation arguments that can be read from a witness file.
+pub trait WitnessTemplate: Serialize + DeserializeOwned {
+    /// Example arguments, printed by `witness-template`.
+    fn template() -> Self;
+
+    /// Checks the constraints between arguments that their types cannot express (like the length
+    /// of a Merkle path matching the tree height).
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Format of a witness file.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, clap::ValueEnum)]
+pub enum WitnessFormat {
+    Json,
+    Toml,
+}
+
+impl WitnessFormat {
+    /// Format of the witness file at `path`, judged by its extension (JSON unless `.toml`).
+    pub fn of_file(path: &Path) -> Self {
+        match path.extension() {
+            Some(ext) if ext == "toml" => WitnessFormat::Toml,
+            _ => WitnessFormat::Json,
+        }
+    }
+
+    pub fn render<T: Serialize>(&self, args: &T) -> String {
+        match self {
+            WitnessFormat::Json => {
+                serde_json::to_string_pretty(args).expect("Failed to serialize witness to JSON")
+            }
+            WitnessFormat::Toml => {
+                toml::to_string_pretty(args).expect("Failed to serialize witness to TOML")
+            }
+        }
+    }
+
+    pub fn parse<T: DeserializeOwned>(&self, content: &str) -> Result<T, String> {
+        match self {
+            WitnessFormat::Json => serde_json::from_str(content).map_err(|e| e.to_string()),
+            WitnessFormat::Toml => toml::from_str(content).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// Relation arguments `T`, given either with CLI flags or in a witness file (`--witness-file`).
+///
+/// When a witness file is used, none of the flags of `T` is required (nor allowed).
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Witness<T>(pub T);
+
+fn read_witness_file<T: DeserializeOwned>(path: &Path) -> Result<T, clap::Error> {
+    let content = fs::read_to_string(path).map_err(|e| {
+        clap::Error::raw(
+            ErrorKind::Io,
+            format!("Failed to read witness file {:?}: {}", path, e),
+        )
+    })?;
+    WitnessFormat::of_file(path).parse(&content).map_err(|e| {
+        clap::Error::raw(
+            ErrorKind::InvalidValue,
+            format!("Invalid witness file {:?}: {}", path, e),
+        )
+    })
+}
+
+/// Adds `--witness-file` to `cmd`, making all its other arguments optional in its presence.
+fn with_witness_file(cmd: Command) -> Command {
+    let ids = cmd
+        .get_arguments()
+        .map(|arg| arg.get_id().clone())
+        .collect::<Vec<_>>();
+
+    let mut cmd = cmd.arg(
+        Arg::new(WITNESS_FILE)
+            .long("witness-file")
+            .value_name("WITNESS_FILE")
+            .value_parser(value_parser!(PathBuf))
+            .conflicts_with_all(ids.clone())
+            .help("Read relation arguments from a JSON or TOML file (see `witness-template`)"),
+    );
+    for id in ids {
+        cmd = cmd.mut_arg(id, |arg| {
+            if arg.is_required_set() {
+                arg.required(false).required_unless_present(WITNESS_FILE)
+            } else {
+                arg
+            }
+        });
+    }
+    cmd
+}
+
+impl<T: Args + DeserializeOwned> Args for Witness<T> {
+    fn augment_args(cmd: Command) -> Command {
+        with_witness_file(T::augment_args(cmd))
+    }
+
+    fn augment_args_for_update(cmd: Command) -> Command {
+        with_witness_file(T::augment_args_for_update(cmd))
+    }
+}
+
+fn validate<T: WitnessTemplate>(args: &T) -> Result<(), clap::Error> {
+    args.validate().map_err(|e| {
+        clap::Error::raw(
+            ErrorKind::ValueValidation,
+            format!("Invalid relation arguments: {}", e),
+        )
+    })
+}
+
+impl<T: FromArgMatches + WitnessTemplate> FromArgMatches for Witness<T> {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, clap::Error> {
+        let args = match matches.get_one::<PathBuf>(WITNESS_FILE) {
+            Some(path) => read_witness_file(path)?,
+            None => T::from_arg_matches(matches)?,
+        };
+        validate(&args)?;
+        Ok(Witness(args))
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), clap::Error> {
+        match matches.get_one::<PathBuf>(WITNESS_FILE) {
+            Some(path) => self.0 = read_witness_file(path)?,
+            None => self.0.update_from_arg_matches(matches)?,
+        }
+        validate(&self.0)
+    }
+}
+
+/// (De)serialization of integers, which can be given as numbers, decimal strings or `0x`-prefixed
+/// hex strings.
+pub mod integer {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Integer {
+        Number(u64),
+        Text(String),
+    }
+
+    pub fn serialize<S: Serializer, T: Copy + Into<u64>>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64((*value).into())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, T: TryFrom<u64>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        let value = match Integer::deserialize(deserializer)? {
+            Integer::Number(value) => value,
+            Integer::Text(text) => match text.strip_prefix("0x") {
+                Some(hex) => u64::from_str_radix(hex, 16),
+                None => text.parse(),
+            }
+            .map_err(D::Error::custom)?,
+        };
+        T::try_from(value).map_err(|_| D::Error::custom(format!("{} is out of range", value)))
+    }
+}
+
+/// (De)serialization of 32-byte arrays as `0x`-prefixed hex strings.
+pub mod bytes32 {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn encode(bytes: &[u8]) -> String {
+        format!("0x{}", hex::encode(bytes))
+    }
+
+    pub fn decode(text: &str) -> Result<[u8; 32], String> {
+        let bytes = hex::decode(text.strip_prefix("0x").unwrap_or(text))
+            .map_err(|e| format!("{} is not hex-encoded: {}", text, e))?;
+        bytes
+            .try_into()
+            .map_err(|bytes: Vec<u8>| format!("Expected 32 bytes, got {}", bytes.len()))
+    }
+
+    pub fn serialize<S: Serializer>(bytes: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 32], D::Error> {
+        decode(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+    use clap::{Parser, Subcommand};
+
+    use super::*;
+    use crate::relations::{types::CircuitField, Relation};
+
+    #[derive(Debug, Parser)]
+    struct Cli {
+        #[clap(subcommand)]
+        relation: Relation,
+    }
+
+    fn relation_names() -> Vec<String> {
+        Relation::augment_subcommands(Command::new("relation"))
+            .get_subcommands()
+            .map(|cmd| cmd.get_name().to_string())
+            .collect()
+    }
+
+    fn parse_witness_file(name: &str, format: WitnessFormat, content: &str) -> Relation {
+        let extension = format!("{:?}", format).to_lowercase();
+        let path = std::env::temp_dir().join(format!("house-snark-{}-witness.{}", name, extension));
+        fs::write(&path, content).unwrap();
+        Relation::from_witness_file(name, &path).expect("Failed to parse witness file")
+    }
+
+    #[test]
+    fn templates_are_valid_witness_files() {
+        for name in relation_names() {
+            for format in [WitnessFormat::Json, WitnessFormat::Toml] {
+                let template = Relation::witness_template(&name, format);
+                let relation = parse_witness_file(&name, format, &template);
+
+                let cs = ConstraintSystem::<CircuitField>::new_ref();
+                relation.generate_constraints(cs.clone()).unwrap();
+                assert!(
+                    cs.is_satisfied().unwrap(),
+                    "Template of {} is unsatisfied",
+                    name
+                );
+            }
+        }
+    }
+
+    #[cfg(feature = "xor")]
+    #[test]
+    fn integers_can_be_given_as_numbers_or_strings() {
+        let relation = parse_witness_file(
+            "xor",
+            WitnessFormat::Json,
+            r#"{"public_xoree": 2, "private_xoree": "3", "result": "0x01"}"#,
+        );
+        assert_eq!(
+            relation,
+            Relation::Xor(Witness(crate::relations::XorRelation {
+                public_xoree: 2,
+                private_xoree: 3,
+                result: 1,
+            }))
+        );
+    }
+
+    #[cfg(feature = "xor")]
+    #[test]
+    fn witness_file_excludes_flags() {
+        let result = Cli::try_parse_from([
+            "house-snark",
+            "xor",
+            "--public-xoree",
+            "2",
+            "--witness-file",
+            "witness.json",
+        ]);
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::ArgumentConflict);
+    }
+
+    #[cfg(feature = "withdraw")]
+    #[test]
+    fn merkle_path_must_match_tree_height() {
+        let mut args = crate::relations::WithdrawRelationArgs::template();
+        args.max_path_len += 1;
+
+        let path = std::env::temp_dir().join("house-snark-withdraw-invalid-witness.json");
+        fs::write(&path, WitnessFormat::Json.render(&args)).unwrap();
+
+        let error = Relation::from_witness_file("withdraw", &path).unwrap_err();
+        assert!(error.contains("Merkle path has 2 nodes"), "{}", error);
+    }
+}
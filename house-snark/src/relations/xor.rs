@@ -3,8 +3,9 @@ use ark_r1cs_std::prelude::{AllocVar, EqGadget, UInt8};
 use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
 use ark_serialize::CanonicalSerialize;
 use clap::Args;
+use serde::{Deserialize, Serialize};
 
-use crate::relations::{byte_to_bits, GetPublicInput};
+use crate::relations::{byte_to_bits, name_elements, witness::WitnessTemplate, GetPublicInput};
 
 /// XOR relation: a ⊕ b = c
 ///
@@ -13,15 +14,19 @@ use crate::relations::{byte_to_bits, GetPublicInput};
 ///  - 1 private witness (b | `private_xoree`)
 ///  - 1 constant        (c | `result`)
 /// such that: a ^ b = c.
-#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Args)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct XorRelation {
     // ToDo: Especially for Groth16, it is better to provide public input as a field element.
     // Otherwise, we have to provide it to circuit bit by bit.
     #[clap(long, short = 'a', default_value = "2")]
+    #[serde(with = "crate::relations::witness::integer")]
     pub public_xoree: u8,
     #[clap(long, short = 'b', default_value = "3")]
+    #[serde(with = "crate::relations::witness::integer")]
     pub private_xoree: u8,
     #[clap(long, short = 'c', default_value = "1")]
+    #[serde(with = "crate::relations::witness::integer")]
     pub result: u8,
 }
 
@@ -40,8 +45,18 @@ impl<Field: PrimeField> ConstraintSynthesizer<Field> for XorRelation {
     }
 }
 
+impl WitnessTemplate for XorRelation {
+    fn template() -> Self {
+        XorRelation {
+            public_xoree: 2,
+            private_xoree: 3,
+            result: 1,
+        }
+    }
+}
+
 impl<CircuitField: PrimeField + CanonicalSerialize> GetPublicInput<CircuitField> for XorRelation {
-    fn public_input(&self) -> Vec<CircuitField> {
-        byte_to_bits(self.public_xoree).to_vec()
+    fn named_public_input(&self) -> Vec<(String, CircuitField)> {
+        name_elements("public_xoree", byte_to_bits(self.public_xoree))
     }
 }
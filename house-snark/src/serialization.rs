@@ -1,6 +1,19 @@
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
+use anyhow::{anyhow, Result};
+use ark_ff::{BigInteger, PrimeField};
 use ark_serialize::CanonicalSerialize;
+use num_bigint::BigUint;
+use serde_json::json;
+
+use crate::{
+    envelope::{open, ArtifactKind},
+    environment::Curve,
+    relations::Relation,
+};
 
 pub fn serialize<T: CanonicalSerialize>(t: &T) -> Vec<u8> {
     let mut bytes = vec![0; t.serialized_size()];
@@ -8,6 +21,29 @@ pub fn serialize<T: CanonicalSerialize>(t: &T) -> Vec<u8> {
     bytes.to_vec()
 }
 
+/// Identifier of proving system `system_id` working over `curve`, as used in artifact file names.
+pub fn env_id(system_id: &str, curve: Curve) -> String {
+    format!("{}.{}", system_id, curve.id())
+}
+
+/// Human-readable form of a named public input: a JSON array listing every element by name (in the
+/// order of the circuit), both as a decimal and as a big-endian hex number.
+pub fn public_input_json<F: PrimeField>(public_input: &[(String, F)]) -> String {
+    let elements = public_input
+        .iter()
+        .map(|(name, value)| {
+            let repr = value.into_repr();
+            let decimal: BigUint = repr.into();
+            json!({
+                "name": name,
+                "decimal": decimal.to_string(),
+                "hex": format!("0x{}", hex::encode(repr.to_bytes_be())),
+            })
+        })
+        .collect::<Vec<_>>();
+    serde_json::to_string_pretty(&elements).expect("Failed to serialize public input to JSON")
+}
+
 fn save_bytes(bytes: &[u8], prefix: &str, identifier: &str) {
     let path = format!("{}.{}.bytes", prefix, identifier);
     fs::write(path, bytes).unwrap_or_else(|_| panic!("Failed to save {}", identifier));
@@ -17,22 +53,142 @@ pub fn save_srs(srs: &[u8], env_id: &str) {
     save_bytes(srs, env_id, "srs");
 }
 
+/// Saves an updated SRS and its update proof as `<env_id>.srs.<n>.bytes` and
+/// `<env_id>.srs_update.<n>.bytes`, where `n` is the smallest positive number for which neither
+/// file exists yet. This way no SRS (in particular, the one being updated) is ever overwritten.
+///
+/// Returns the path of the new SRS.
+pub fn save_srs_update(srs: &[u8], proof: &[u8], env_id: &str) -> PathBuf {
+    let path = |identifier: &str, n: usize| {
+        PathBuf::from(format!("{}.{}.{}.bytes", env_id, identifier, n))
+    };
+    let n = (1..)
+        .find(|&n| !path("srs", n).exists() && !path("srs_update", n).exists())
+        .expect("Ran out of SRS update numbers");
+
+    fs::write(path("srs_update", n), proof).expect("Failed to save srs_update");
+    fs::write(path("srs", n), srs).expect("Failed to save srs");
+    path("srs", n)
+}
+
 pub fn save_keys(rel_name: &str, env_id: &str, pk: &[u8], vk: &[u8]) {
     let prefix = format!("{}.{}", rel_name, env_id);
     save_bytes(pk, &prefix, "pk");
     save_bytes(vk, &prefix, "vk");
 }
 
+pub fn save_ceremony(rel_name: &str, env_id: &str, ceremony: &[u8]) {
+    let prefix = format!("{}.{}", rel_name, env_id);
+    save_bytes(ceremony, &prefix, "ceremony");
+}
+
 pub fn save_proving_artifacts(rel_name: &str, env_id: &str, proof: &[u8], input: &[u8]) {
     let prefix = format!("{}.{}", rel_name, env_id);
     save_bytes(proof, &prefix, "proof");
     save_bytes(input, &prefix, "public_input");
 }
 
-pub fn read_srs(srs_file: PathBuf) -> Vec<u8> {
-    fs::read(srs_file).expect("Failed to read SRS from the provided path")
+pub fn save_aggregation_srs(srs: &[u8], env_id: &str) {
+    save_bytes(srs, env_id, "aggregation_srs");
+}
+
+pub fn save_aggregate_proof(proof: &[u8], env_id: &str) {
+    save_bytes(proof, env_id, "aggregate_proof");
+}
+
+pub fn save_public_input_json(rel_name: &str, env_id: &str, json: &str) {
+    let path = format!("{}.{}.public_input.json", rel_name, env_id);
+    fs::write(path, json).expect("Failed to save public_input.json");
+}
+
+/// Reads `file` containing `what`.
+fn read_file(file: &Path, what: &str) -> Result<Vec<u8>> {
+    fs::read(file).map_err(|e| anyhow!("Failed to read {} from {:?}: {}", what, file, e))
+}
+
+pub fn read_srs(srs_file: PathBuf, system_id: &str, curve: Curve) -> Result<Vec<u8>> {
+    let bytes = read_file(&srs_file, "SRS")?;
+    open(&bytes, ArtifactKind::Srs, system_id, curve, None)
+}
+
+pub fn read_srs_update_proof(update_proof_file: PathBuf) -> Result<Vec<u8>> {
+    read_file(&update_proof_file, "update proof")
+}
+
+/// Reads the proving key, checking (if it is enveloped) that it has been generated for `relation`.
+pub fn read_proving_key(
+    proving_key_file: PathBuf,
+    system_id: &str,
+    curve: Curve,
+    relation: &Relation,
+) -> Result<Vec<u8>> {
+    let bytes = read_file(&proving_key_file, "proving key")?;
+    open(
+        &bytes,
+        ArtifactKind::ProvingKey,
+        system_id,
+        curve,
+        Some(relation),
+    )
+}
+
+pub fn read_verifying_key(
+    verifying_key_file: PathBuf,
+    system_id: &str,
+    curve: Curve,
+) -> Result<Vec<u8>> {
+    let bytes = read_file(&verifying_key_file, "verifying key")?;
+    open(&bytes, ArtifactKind::VerifyingKey, system_id, curve, None)
+}
+
+pub fn read_proof(proof_file: PathBuf, system_id: &str, curve: Curve) -> Result<Vec<u8>> {
+    let bytes = read_file(&proof_file, "proof")?;
+    open(&bytes, ArtifactKind::Proof, system_id, curve, None)
 }
 
-pub fn read_proving_key(proving_key_file: PathBuf) -> Vec<u8> {
-    fs::read(proving_key_file).expect("Failed to read proving key from the provided path")
+pub fn read_public_input(
+    public_input_file: PathBuf,
+    system_id: &str,
+    curve: Curve,
+) -> Result<Vec<u8>> {
+    let bytes = read_file(&public_input_file, "public input")?;
+    open(&bytes, ArtifactKind::PublicInput, system_id, curve, None)
+}
+
+pub fn read_ceremony(ceremony_file: PathBuf) -> Result<Vec<u8>> {
+    read_file(&ceremony_file, "ceremony")
+}
+
+pub fn read_artifact(artifact_file: PathBuf) -> Result<Vec<u8>> {
+    read_file(&artifact_file, "artifact")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::CircuitField;
+
+    #[test]
+    fn public_input_json_lists_elements_by_name() {
+        let json = public_input_json(&[
+            (String::from("fee"), CircuitField::from(1u8)),
+            (String::from("token_id"), -CircuitField::from(1u8)),
+        ]);
+        let elements: serde_json::Value =
+            serde_json::from_str(&json).expect("Failed to parse public_input.json");
+
+        assert_eq!(elements[0]["name"], "fee");
+        assert_eq!(elements[0]["decimal"], "1");
+        assert_eq!(elements[0]["hex"], format!("0x{:064x}", 1));
+
+        assert_eq!(elements[1]["name"], "token_id");
+        assert_eq!(
+            elements[1]["decimal"],
+            "52435875175126190479447740508185965837690552500527637822603658699938581184512"
+        );
+        assert_eq!(
+            elements[1]["hex"],
+            "0x73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000000"
+        );
+    }
 }
@@ -0,0 +1,224 @@
+//! Updating and trimming KZG universal parameters (used as Marlin and PLONK SRS).
+//!
+//! An update re-randomizes the trapdoors of an existing SRS: `beta` is multiplied by a fresh
+//! secret `t` and the hiding generator `gamma * G` by a fresh secret `u`. Together with the new SRS,
+//! the contributor publishes proofs of knowledge of both scalars, so that anyone can check that the
+//! new SRS extends the old one instead of replacing it. As long as a single contributor discards
+//! their scalars, nobody knows the final trapdoors. Updates are supported only over BLS12-381,
+//! while trimming works over any curve.
+
+use std::collections::BTreeMap;
+
+use ark_bls12_381::{Bls12_381, G1Affine};
+use ark_ec::{AffineCurve, PairingEngine, ProjectiveCurve};
+use ark_ff::{Field, UniformRand};
+use ark_poly_commit::kzg10::UniversalParams;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write};
+use ark_std::rand::{CryptoRng, RngCore};
+use blake2::{Blake2s, Digest};
+
+use crate::{
+    environment::CircuitField,
+    pairing_checks::{random_combinations, same_ratio, KnowledgeProof},
+    serialization::serialize,
+};
+
+pub type KzgSrs<E = Bls12_381> = UniversalParams<E>;
+
+/// Proof that an SRS has been obtained by updating another one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SrsUpdateProof {
+    /// Proof of knowledge of the scalar multiplying `beta`.
+    beta: KnowledgeProof,
+    /// Proof of knowledge of the scalar multiplying the hiding generator.
+    gamma: KnowledgeProof,
+}
+
+/// Re-randomizes `srs` with fresh secrets from `rng`.
+pub fn update<R: RngCore + CryptoRng>(srs: &KzgSrs, rng: &mut R) -> (KzgSrs, SrsUpdateProof) {
+    let t = CircuitField::rand(rng);
+    let u = CircuitField::rand(rng);
+    let t_inverse = t.inverse().expect("Sampled zero scalar");
+
+    let context_hash = hash_srs(srs);
+    let proof = SrsUpdateProof {
+        beta: KnowledgeProof::prove(t, &context_hash, rng),
+        gamma: KnowledgeProof::prove(u, &context_hash, rng),
+    };
+
+    let powers_of_g = srs
+        .powers_of_g
+        .iter()
+        .enumerate()
+        .map(|(i, g)| g.mul(t.pow([i as u64])))
+        .collect::<Vec<_>>();
+    let powers_of_gamma_g = srs
+        .powers_of_gamma_g
+        .iter()
+        .map(|(i, g)| (*i, g.mul(u * t.pow([*i as u64])).into_affine()))
+        .collect();
+    let neg_powers_of_h = srs
+        .neg_powers_of_h
+        .iter()
+        .map(|(i, h)| (*i, h.mul(t_inverse.pow([*i as u64])).into_affine()))
+        .collect();
+    let beta_h = srs.beta_h.mul(t).into_affine();
+
+    let updated = UniversalParams {
+        powers_of_g: <Bls12_381 as PairingEngine>::G1Projective::batch_normalization_into_affine(
+            &powers_of_g,
+        ),
+        powers_of_gamma_g,
+        h: srs.h,
+        beta_h,
+        neg_powers_of_h,
+        prepared_h: srs.prepared_h.clone(),
+        prepared_beta_h: beta_h.into(),
+    };
+    (updated, proof)
+}
+
+/// Checks that `new` is well-formed and has been obtained by updating `old`.
+pub fn verify_update(old: &KzgSrs, new: &KzgSrs, proof: &SrsUpdateProof) -> bool {
+    let same_shape = old.powers_of_g.len() == new.powers_of_g.len()
+        && old
+            .powers_of_gamma_g
+            .keys()
+            .eq(new.powers_of_gamma_g.keys())
+        && old.neg_powers_of_h.keys().eq(new.neg_powers_of_h.keys());
+    if !same_shape || old.powers_of_g.len() < 2 || !old.powers_of_gamma_g.contains_key(&0) {
+        return false;
+    }
+    if old.powers_of_g[0] != new.powers_of_g[0] || old.h != new.h || !is_well_formed(new) {
+        return false;
+    }
+
+    let context_hash = hash_srs(old);
+    let beta_updated = proof.beta.verify(&context_hash).map_or(false, |ratio| {
+        same_ratio((old.powers_of_g[1], new.powers_of_g[1]), ratio)
+    });
+    let gamma_updated = proof.gamma.verify(&context_hash).map_or(false, |ratio| {
+        same_ratio(
+            (old.powers_of_gamma_g[&0], new.powers_of_gamma_g[&0]),
+            ratio,
+        )
+    });
+    beta_updated && gamma_updated
+}
+
+/// Cuts `srs` down to polynomials of degree at most `degree`.
+pub fn trim<E: PairingEngine>(srs: &KzgSrs<E>, degree: usize) -> KzgSrs<E> {
+    assert!(
+        degree < srs.powers_of_g.len(),
+        "SRS is too small for the requested degree"
+    );
+    UniversalParams {
+        powers_of_g: srs.powers_of_g[..=degree].to_vec(),
+        // One additional power is needed for hiding.
+        powers_of_gamma_g: take_keys_up_to(&srs.powers_of_gamma_g, degree + 1),
+        h: srs.h,
+        beta_h: srs.beta_h,
+        neg_powers_of_h: take_keys_up_to(&srs.neg_powers_of_h, degree),
+        prepared_h: srs.prepared_h.clone(),
+        prepared_beta_h: srs.prepared_beta_h.clone(),
+    }
+}
+
+/// Checks that consecutive elements of every power sequence in `srs` differ by `beta`, i.e. by
+/// the same ratio as `h` and `beta_h`.
+fn is_well_formed(srs: &KzgSrs) -> bool {
+    let beta_ratio = (srs.h, srs.beta_h);
+    let powers_of_gamma_g = srs.powers_of_gamma_g.values().copied().collect::<Vec<_>>();
+    let consecutive = |powers: &[G1Affine]| {
+        let (lower, higher) = random_combinations(&powers[..powers.len() - 1], &powers[1..]);
+        same_ratio((lower, higher), beta_ratio)
+    };
+    let neg_powers_consistent = srs.neg_powers_of_h.iter().all(|(i, neg_power)| {
+        srs.powers_of_g.get(*i).map_or(false, |power| {
+            same_ratio((srs.powers_of_g[0], *power), (*neg_power, srs.h))
+        })
+    });
+
+    consecutive(&srs.powers_of_g) && consecutive(&powers_of_gamma_g) && neg_powers_consistent
+}
+
+fn take_keys_up_to<T: Copy>(map: &BTreeMap<usize, T>, max_key: usize) -> BTreeMap<usize, T> {
+    map.range(..=max_key).map(|(k, v)| (*k, *v)).collect()
+}
+
+fn hash_srs(srs: &KzgSrs) -> [u8; 32] {
+    Blake2s::digest(&serialize(srs)).into()
+}
+
+impl CanonicalSerialize for SrsUpdateProof {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.beta.serialize(&mut writer)?;
+        self.gamma.serialize(&mut writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.beta.serialized_size() + self.gamma.serialized_size()
+    }
+}
+
+impl CanonicalDeserialize for SrsUpdateProof {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        Ok(SrsUpdateProof {
+            beta: KnowledgeProof::deserialize(&mut reader)?,
+            gamma: KnowledgeProof::deserialize(&mut reader)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_poly::univariate::DensePolynomial;
+    use ark_poly_commit::{marlin_pc::MarlinKZG10, PolynomialCommitment};
+
+    use super::*;
+    use crate::environment::rng;
+
+    fn srs(max_degree: usize) -> KzgSrs {
+        MarlinKZG10::<Bls12_381, DensePolynomial<CircuitField>>::setup(
+            max_degree,
+            None,
+            &mut rng(None),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn honest_update_verifies() {
+        let old = srs(10);
+        let (new, proof) = update(&old, &mut rng(None));
+        assert!(verify_update(&old, &new, &proof));
+
+        let (newer, proof) = update(&new, &mut rng(None));
+        assert!(verify_update(&new, &newer, &proof));
+    }
+
+    #[test]
+    fn replacing_srs_is_detected() {
+        let old = srs(10);
+        let (_, proof) = update(&old, &mut rng(None));
+        let unrelated = srs(10);
+        assert!(!verify_update(&old, &unrelated, &proof));
+    }
+
+    #[test]
+    fn tampering_with_powers_is_detected() {
+        let old = srs(10);
+        let (mut new, proof) = update(&old, &mut rng(None));
+        new.powers_of_g[5] = new.powers_of_g[4];
+        assert!(!verify_update(&old, &new, &proof));
+    }
+
+    #[test]
+    fn trimmed_srs_keeps_prefix() {
+        let srs = srs(10);
+        let trimmed = trim(&srs, 4);
+        assert_eq!(trimmed.powers_of_g, srs.powers_of_g[..5]);
+        assert_eq!(trimmed.powers_of_gamma_g.len(), 6);
+        assert!(is_well_formed(&trimmed));
+    }
+}
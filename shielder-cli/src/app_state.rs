@@ -4,10 +4,11 @@ use std::{
 };
 
 use aleph_client::AccountId;
+use house_snark::{compute_note, generate_spending_secret};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
-use crate::{DepositId, Nullifier, TokenAmount, TokenId, Trapdoor};
+use crate::{DepositId, Note, SpendingSecret, TokenAmount, TokenId, Trapdoor};
 
 #[derive(Clone, Eq, PartialEq, Debug, Deserialize, Serialize)]
 pub struct Deposit {
@@ -16,7 +17,31 @@ pub struct Deposit {
     pub token_amount: TokenAmount,
     pub leaf_idx: u32,
     pub trapdoor: Trapdoor,
-    pub nullifier: Nullifier,
+}
+
+/// Deposit created before notes were bound to a spending secret. It cannot be spent with the
+/// current relations, but it is kept in the state, so that its secrets are not lost.
+#[derive(Clone, Eq, PartialEq, Debug, Deserialize, Serialize)]
+pub struct LegacyDeposit {
+    pub deposit_id: DepositId,
+    pub token_id: TokenId,
+    pub token_amount: TokenAmount,
+    pub leaf_idx: u32,
+    pub trapdoor: u64,
+    pub nullifier: u64,
+}
+
+impl LegacyDeposit {
+    /// Note of the deposit. Before notes were bound to a spending secret, the nullifier was hashed
+    /// in place of the owner key.
+    pub fn note(&self) -> Note {
+        compute_note(
+            self.token_id,
+            self.token_amount,
+            [self.trapdoor, 0, 0, 0],
+            [self.nullifier, 0, 0, 0],
+        )
+    }
 }
 
 impl Display for Deposit {
@@ -29,27 +54,43 @@ impl Display for Deposit {
     }
 }
 
+/// Current version of the state format. Older states are migrated when read (see `state_file`).
+pub const STATE_VERSION: u32 = 1;
+
 #[derive(Clone, Eq, PartialEq, Debug, Deserialize, Serialize)]
 pub struct AppState {
+    pub version: u32,
     #[serde(skip)]
     pub caller_seed: String,
     pub node_address: String,
     pub contract_address: AccountId,
+    /// Secret owning all the deposits. Its nullifiers and owner key are derived on demand.
+    #[serde(default = "new_spending_secret")]
+    pub spending_secret: SpendingSecret,
 
     deposit_counter: DepositId,
     deposits: Vec<Deposit>,
+    #[serde(default)]
+    legacy_deposits: Vec<LegacyDeposit>,
 }
 
 const DEFAULT_NODE_ADDRESS: &str = "ws://127.0.0.1:9944";
 
+fn new_spending_secret() -> SpendingSecret {
+    generate_spending_secret(&mut rand::thread_rng())
+}
+
 impl Default for AppState {
     fn default() -> Self {
         Self {
+            version: STATE_VERSION,
             caller_seed: String::new(),
             node_address: DEFAULT_NODE_ADDRESS.to_string(),
             contract_address: AccountId::new([0u8; 32]),
+            spending_secret: new_spending_secret(),
             deposit_counter: 0,
             deposits: Default::default(),
+            legacy_deposits: Default::default(),
         }
     }
 }
@@ -109,7 +150,6 @@ impl AppState {
         token_id: TokenId,
         token_amount: TokenAmount,
         trapdoor: Trapdoor,
-        nullifier: Nullifier,
         leaf_idx: u32,
     ) {
         self.deposits.push(Deposit {
@@ -118,7 +158,6 @@ impl AppState {
             token_amount,
             leaf_idx,
             trapdoor,
-            nullifier,
         });
         self.deposit_counter += 1;
     }
@@ -131,6 +170,10 @@ impl AppState {
             .collect()
     }
 
+    pub fn legacy_deposits(&self) -> Vec<LegacyDeposit> {
+        self.legacy_deposits.clone()
+    }
+
     pub fn get_deposit_by_id(&self, deposit_id: DepositId) -> Option<Deposit> {
         self.deposits
             .iter()
@@ -45,6 +45,9 @@ pub(super) enum StateWriteCommand {
 pub(super) enum StateReadCommand {
     ShowAssets(ShowAssetsCmd),
     PrintState,
+    /// Print deposits created before notes were bound to a spending secret, together with
+    /// everything needed to withdraw them with the previous version of the CLI.
+    ShowLegacyDeposits,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, Subcommand)]
@@ -156,7 +156,7 @@ impl Shielder {
             &*recipient.to_string(),
             &*format!("{:?}", fee_for_caller),
             &*format!("0x{}", hex::encode(merkle_root_bytes)),
-            &*old_nullifier.to_string(),
+            &*format!("0x{}", hex::encode(bytes_from_note(&old_nullifier))),
             &*format!("0x{}", hex::encode(new_note_bytes)),
             &*format!("0x{}", hex::encode(proof)),
         ];
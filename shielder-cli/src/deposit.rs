@@ -3,11 +3,11 @@ use std::fs;
 use aleph_client::SignedConnection;
 use anyhow::Result;
 use house_snark::{
-    compute_note, DepositRelation, NonUniversalProvingSystem, RawKeys, SomeProvingSystem,
+    compute_note, compute_owner_key, generate_trapdoor, DepositRelation, NonUniversalProvingSystem,
+    RawKeys, SomeProvingSystem,
 };
-use rand::Rng;
 
-use crate::{app_state::AppState, config::DepositCmd, contract::Shielder, Nullifier, Trapdoor};
+use crate::{app_state::AppState, config::DepositCmd, contract::Shielder};
 
 pub(super) fn do_deposit(
     contract: Shielder,
@@ -24,19 +24,24 @@ pub(super) fn do_deposit(
 
     let mut rng = rand::thread_rng();
 
-    let trapdoor: Trapdoor = rng.gen::<u64>();
-    let nullifier: Nullifier = rng.gen::<u64>();
-    let note = compute_note(token_id, token_amount, trapdoor, nullifier);
+    let trapdoor = generate_trapdoor(&mut rng);
+    let spending_secret = app_state.spending_secret;
+    let note = compute_note(
+        token_id,
+        token_amount,
+        trapdoor,
+        compute_owner_key(spending_secret),
+    );
 
-    let circuit = DepositRelation::new(note, token_id, token_amount, trapdoor, nullifier);
+    let circuit = DepositRelation::new(note, token_id, token_amount, trapdoor, spending_secret);
 
-    let pk = match fs::read(proving_key_file) {
+    let pk = match fs::read(&proving_key_file) {
         Ok(bytes) => bytes,
         Err(_e) => {
             let system = NonUniversalProvingSystem::Groth16;
-            let RawKeys { pk, vk } = system.generate_keys(circuit.clone());
+            let RawKeys { pk, vk } = system.generate_keys(circuit.clone(), &mut rng);
 
-            fs::write("deposit.pk.bytes", pk.clone()).unwrap();
+            fs::write(&proving_key_file, pk.clone()).unwrap();
             // NOTE: not needed here but for registering in the snarcos pallet
             fs::write("deposit.vk.bytes", vk).unwrap();
 
@@ -45,10 +50,10 @@ pub(super) fn do_deposit(
     };
 
     let system = SomeProvingSystem::NonUniversal(NonUniversalProvingSystem::Groth16);
-    let proof = system.prove(circuit, pk);
+    let proof = system.prove(circuit, pk, &mut rng);
     let leaf_idx = contract.deposit(&connection, cmd.token_id, cmd.amount, note, &proof)?;
 
-    app_state.add_deposit(cmd.token_id, cmd.amount, trapdoor, nullifier, leaf_idx);
+    app_state.add_deposit(cmd.token_id, cmd.amount, trapdoor, leaf_idx);
 
     Ok(())
 }
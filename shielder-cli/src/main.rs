@@ -4,13 +4,16 @@ use aleph_client::{keypair_from_string, SignedConnection};
 use anyhow::{anyhow, Result};
 use clap::Parser;
 use config::LoggingFormat;
-use house_snark::{MerklePath, MerkleRoot, Note, Nullifier, TokenAmount, TokenId, Trapdoor};
+use house_snark::{
+    bytes_from_note, MerklePath, MerkleRoot, Note, Nullifier, SpendingSecret, TokenAmount, TokenId,
+    Trapdoor,
+};
 use inquire::Password;
 use tracing::info;
 use tracing_subscriber::EnvFilter;
 use zeroize::Zeroize;
 use ContractInteractionCommand::{Deposit, RegisterToken, Withdraw};
-use StateReadCommand::{PrintState, ShowAssets};
+use StateReadCommand::{PrintState, ShowAssets, ShowLegacyDeposits};
 use StateWriteCommand::{SetContractAddress, SetNode};
 
 use crate::{
@@ -61,7 +64,19 @@ fn perform_state_read_action(app_state: &mut AppState, command: StateReadCommand
             info!(caller_seed=?app_state.caller_seed, 
                 node_address=%app_state.node_address, 
                 contract_address=%app_state.contract_address,
-                deposits=?app_state.deposits())
+                deposits=?app_state.deposits(),
+                legacy_deposits=?app_state.legacy_deposits())
+        }
+        ShowLegacyDeposits => {
+            for deposit in app_state.legacy_deposits() {
+                info!(deposit_id=deposit.deposit_id,
+                    token_id=deposit.token_id,
+                    token_amount=deposit.token_amount,
+                    leaf_idx=deposit.leaf_idx,
+                    trapdoor=deposit.trapdoor,
+                    nullifier=deposit.nullifier,
+                    note=%format!("0x{}", hex::encode(bytes_from_note(&deposit.note()))))
+            }
         }
     };
     Ok(())
@@ -6,9 +6,10 @@ use std::{
 
 use anyhow::{anyhow, Result};
 use chacha20poly1305::{aead::Aead, KeyInit, XChaCha20Poly1305};
-use tracing::info;
+use serde_json::{json, Value};
+use tracing::{info, warn};
 
-use crate::app_state::AppState;
+use crate::app_state::{AppState, STATE_VERSION};
 
 /// Try to get `AppState` from `path`. If `path` describes non-existing file, the default `AppState`
 /// will be created, saved to `path` and returned.
@@ -37,14 +38,95 @@ pub fn save_app_state(app_state: &AppState, path: &PathBuf, password: &str) -> R
         .map_err(|e| anyhow!("Failed to save application state: {e}"))
 }
 
-/// Read `AppState` from `path`.
+/// Read `AppState` from `path`. States saved in an older format are migrated to the current one.
 fn read_from(path: &Path, password: &str) -> Result<AppState> {
     let file_content = fs::read(path).map_err(|e| anyhow!("Failed to read file content: {e}"))?;
     let decrypted_content = decrypt(&file_content, password)?;
-    serde_json::from_str::<AppState>(&decrypted_content)
+    let state = serde_json::from_str::<Value>(&decrypted_content)
+        .map_err(|e| anyhow!("Failed to deserialize application state: {e}"))?;
+    if state.get("version").is_none() {
+        let backup = back_up(path, "unversioned")?;
+        warn!(
+            "Unversioned state was backed up to {backup:?} before migration. The previous version \
+            of the CLI can still use it (`--state-file`)."
+        );
+    }
+    serde_json::from_value::<AppState>(migrate(state)?)
         .map_err(|e| anyhow!("Failed to deserialize application state: {e}"))
 }
 
+/// Copy the file at `path` to `<path>.<suffix>`, unless it has been backed up already. Returns the
+/// path of the backup.
+fn back_up(path: &Path, suffix: &str) -> Result<PathBuf> {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(format!(".{suffix}"));
+    let backup = PathBuf::from(backup);
+    if !backup.exists() {
+        fs::copy(path, &backup).map_err(|e| anyhow!("Failed to back up {path:?}: {e}"))?;
+    }
+    Ok(backup)
+}
+
+/// Bring `state` (in any supported format) to `STATE_VERSION`.
+fn migrate(mut state: Value) -> Result<Value> {
+    let version = match state.get("version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| anyhow!("Invalid state version: {version}"))?,
+    };
+
+    if version > STATE_VERSION as u64 {
+        return Err(anyhow!(
+            "State version {version} is not supported (the newest known is {STATE_VERSION})"
+        ));
+    }
+    if version == 0 {
+        migrate_from_unversioned(&mut state)?;
+    }
+    Ok(state)
+}
+
+/// Unversioned states kept trapdoors as `u64` numbers - they are widened to field elements (the
+/// notes stay the same). Deposits with a `nullifier` come from before notes were bound to a
+/// spending secret and cannot be spent with this version anymore - they are moved to
+/// `legacy_deposits` (only the previous version of the CLI can withdraw them, from the previously
+/// deployed contract).
+fn migrate_from_unversioned(state: &mut Value) -> Result<()> {
+    let state = state
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("Application state is not a JSON object"))?;
+
+    let deposits = match state.remove("deposits") {
+        None => vec![],
+        Some(Value::Array(deposits)) => deposits,
+        Some(_) => return Err(anyhow!("Deposits in application state are not a list")),
+    };
+    let (legacy_deposits, mut deposits): (Vec<_>, Vec<_>) = deposits
+        .into_iter()
+        .partition(|deposit| deposit.get("nullifier").is_some());
+
+    for deposit in deposits.iter_mut() {
+        if let Some(trapdoor) = deposit.get("trapdoor").and_then(Value::as_u64) {
+            deposit["trapdoor"] = json!([trapdoor, 0, 0, 0]);
+        }
+    }
+    if !legacy_deposits.is_empty() {
+        warn!(
+            "{} deposit(s) were created before notes were bound to a spending secret and cannot \
+            be withdrawn with this version. They are kept as legacy deposits - withdraw them with \
+            the previous version of the CLI from the previously deployed contract (see \
+            `show-legacy-deposits`).",
+            legacy_deposits.len()
+        );
+    }
+
+    state.insert("deposits".to_string(), Value::Array(deposits));
+    state.insert("legacy_deposits".to_string(), Value::Array(legacy_deposits));
+    state.insert("version".to_string(), json!(1));
+    Ok(())
+}
+
 /// Create the default `AppState`, save it to `path` and return it.
 fn create_and_save_default_state(path: &PathBuf, password: &str) -> Result<AppState> {
     File::create(path).map_err(|e| anyhow!("Failed to create {path:?}: {e}"))?;
@@ -78,3 +160,61 @@ fn decrypt(content: &[u8], password: &str) -> Result<String> {
     String::from_utf8(decrypted)
         .map_err(|e| anyhow!("Failed to decrypt data - probably the password is incorrect: {e}"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unversioned_state_is_migrated() {
+        let mut state = serde_json::to_value(AppState::default()).unwrap();
+        let state_object = state.as_object_mut().unwrap();
+        state_object.remove("version");
+        state_object.remove("legacy_deposits");
+        state_object.insert(
+            "deposits".to_string(),
+            json!([
+                { "deposit_id": 0, "token_id": 0, "token_amount": 10, "leaf_idx": 0,
+                  "trapdoor": 17, "nullifier": 19 },
+                { "deposit_id": 1, "token_id": 0, "token_amount": 5, "leaf_idx": 1,
+                  "trapdoor": 23 },
+            ]),
+        );
+
+        let state = serde_json::from_value::<AppState>(migrate(state).unwrap()).unwrap();
+
+        assert_eq!(state.version, STATE_VERSION);
+        let deposits = state.deposits();
+        assert_eq!(deposits.len(), 1);
+        assert_eq!(deposits[0].trapdoor, [23, 0, 0, 0]);
+        let legacy_deposits = state.legacy_deposits();
+        assert_eq!(legacy_deposits.len(), 1);
+        assert_eq!(legacy_deposits[0].nullifier, 19);
+    }
+
+    #[test]
+    fn current_state_is_not_changed() {
+        let state = AppState::default();
+        let migrated = migrate(serde_json::to_value(&state).unwrap()).unwrap();
+        assert_eq!(serde_json::from_value::<AppState>(migrated).unwrap(), state);
+    }
+
+    #[test]
+    fn backup_is_not_overwritten() {
+        let path = std::env::temp_dir().join("shielder-cli-backed-up-state");
+        let _ = fs::remove_file(path.with_file_name("shielder-cli-backed-up-state.unversioned"));
+        fs::write(&path, "old").unwrap();
+        let backup = back_up(&path, "unversioned").unwrap();
+
+        fs::write(&path, "new").unwrap();
+        assert_eq!(back_up(&path, "unversioned").unwrap(), backup);
+        assert_eq!(fs::read_to_string(backup).unwrap(), "old");
+    }
+
+    #[test]
+    fn newer_state_is_rejected() {
+        let mut state = serde_json::to_value(AppState::default()).unwrap();
+        state["version"] = json!(STATE_VERSION + 1);
+        assert!(migrate(state).is_err());
+    }
+}
@@ -3,17 +3,17 @@ use std::fs;
 use aleph_client::{account_from_keypair, keypair_from_string, SignedConnection};
 use anyhow::{anyhow, Result};
 use house_snark::{
-    compute_note, NonUniversalProvingSystem, RawKeys, SomeProvingSystem, WithdrawRelation,
+    compute_note, compute_nullifier, compute_owner_key, generate_trapdoor,
+    NonUniversalProvingSystem, RawKeys, SomeProvingSystem, WithdrawRelation,
 };
 use inquire::{CustomType, Select};
-use rand::Rng;
 use tracing::debug;
 
 use crate::{
     app_state::{AppState, Deposit},
     config::WithdrawCmd,
     contract::Shielder,
-    Nullifier, TokenAmount, Trapdoor,
+    TokenAmount,
 };
 
 pub(super) fn do_withdraw(
@@ -36,12 +36,14 @@ pub(super) fn do_withdraw(
         token_id,
         token_amount: whole_token_amount,
         trapdoor: old_trapdoor,
-        nullifier: old_nullifier,
         leaf_idx,
         ..
     } = deposit;
 
-    let old_note = compute_note(token_id, whole_token_amount, old_trapdoor, old_nullifier);
+    let spending_secret = app_state.spending_secret;
+    let owner_key = compute_owner_key(spending_secret);
+    let old_nullifier = compute_nullifier(spending_secret, leaf_idx.into());
+    let old_note = compute_note(token_id, whole_token_amount, old_trapdoor, owner_key);
 
     if let Some(seed) = caller_seed {
         connection = SignedConnection::new(&app_state.node_address, keypair_from_string(&seed));
@@ -61,12 +63,12 @@ pub(super) fn do_withdraw(
     debug!(?merkle_path, "retrieved merkle path");
 
     let mut rng = rand::thread_rng();
-    let new_trapdoor: Trapdoor = rng.gen::<u64>();
-    let new_nullifier: Nullifier = rng.gen::<u64>();
+    let new_trapdoor = generate_trapdoor(&mut rng);
     let new_token_amount = whole_token_amount - withdraw_amount;
-    let new_note = compute_note(token_id, new_token_amount, new_trapdoor, new_nullifier);
+    let new_note = compute_note(token_id, new_token_amount, new_trapdoor, owner_key);
 
     let circuit = WithdrawRelation::new(
+        merkle_path.len() as u8,
         old_nullifier,
         merkle_root,
         new_note,
@@ -74,7 +76,7 @@ pub(super) fn do_withdraw(
         withdraw_amount,
         old_trapdoor,
         new_trapdoor,
-        new_nullifier,
+        spending_secret,
         merkle_path,
         leaf_idx.into(),
         old_note,
@@ -88,7 +90,7 @@ pub(super) fn do_withdraw(
         Ok(bytes) => bytes,
         Err(_e) => {
             let system = NonUniversalProvingSystem::Groth16;
-            let RawKeys { pk, vk } = system.generate_keys(circuit.clone());
+            let RawKeys { pk, vk } = system.generate_keys(circuit.clone(), &mut rng);
 
             fs::write("withdraw.pk.bytes", pk.clone()).unwrap();
             // NOTE: not needed here but usefull for registering in the snarcos pallet
@@ -99,7 +101,7 @@ pub(super) fn do_withdraw(
     };
 
     let system = SomeProvingSystem::NonUniversal(NonUniversalProvingSystem::Groth16);
-    let proof = system.prove(circuit, pk);
+    let proof = system.prove(circuit, pk, &mut rng);
 
     let leaf_idx = contract.withdraw(
         &connection,
@@ -117,13 +119,7 @@ pub(super) fn do_withdraw(
 
     // save new deposit to the state
     if new_token_amount > 0 {
-        app_state.add_deposit(
-            token_id,
-            new_token_amount,
-            new_trapdoor,
-            new_nullifier,
-            leaf_idx,
-        );
+        app_state.add_deposit(token_id, new_token_amount, new_trapdoor, leaf_idx);
     }
 
     Ok(())